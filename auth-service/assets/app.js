@@ -30,8 +30,26 @@ signupLoginLink.addEventListener("click", (e) => {
     signupSection.style.display = "none";
 });
 
+// A federated login has to come back to the same pending authorization request
+const federatedLoginLink = document.getElementById("federated-login-link");
+const federatedReturnTo = new URLSearchParams(window.location.search).get("return_to");
+if (federatedReturnTo !== null) {
+    federatedLoginLink.href = "/login/federated?return_to=" + encodeURIComponent(federatedReturnTo);
+}
+
 // -----------------------------------------------------
 
+// /authorize sends users without a session here and expects them back once they are logged in
+function finishLogin() {
+    const returnTo = new URLSearchParams(window.location.search).get("return_to");
+    // Only local authorization requests are followed, anything else would be an open redirect
+    if (returnTo !== null && returnTo.startsWith("/authorize?")) {
+        window.location.assign(returnTo);
+        return;
+    }
+    alert("You have successfully logged in.");
+}
+
 const loginForm = document.getElementById("login-form");
 const loginButton = document.getElementById("login-form-submit");
 const loginErrAlter = document.getElementById("login-err-alert");
@@ -66,7 +84,7 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            finishLogin();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -145,7 +163,7 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            finishLogin();
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
@@ -161,4 +179,74 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+// Links only open the UI, the token is redeemed here so link scanners in mail clients can not use it up
+const magicLoginLink = document.getElementById("magic-login-link");
+
+magicLoginLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = loginForm.email.value;
+
+    fetch('login/magic', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ email, method: "link" }),
+    }).then(response => {
+        if (response.ok) {
+            loginErrAlter.style.display = "none";
+            alert("If the account exists, a login link has been sent.");
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    loginErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    loginErrAlter.style.display = "block";
+                } else {
+                    loginErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+});
+
+const magicToken = new URLSearchParams(window.location.search).get("magic_token");
+if (magicToken !== null) {
+    window.history.replaceState(null, "", window.location.pathname);
+
+    fetch('login/magic/verify', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token: magicToken }),
+    }).then(response => {
+        if (response.status === 206) {
+            // The link token names the user in its sub claim
+            const claims = magicToken.split(".")[1].replace(/-/g, "+").replace(/_/g, "/");
+            TwoFAForm.email.value = JSON.parse(atob(claims)).sub;
+            response.json().then(data => {
+                TwoFAForm.login_attempt_id.value = data.loginAttemptId;
+            });
+
+            loginSection.style.display = "none";
+            twoFASection.style.display = "block";
+            signupSection.style.display = "none";
+        } else if (response.status === 200) {
+            finishLogin();
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    loginErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    loginErrAlter.style.display = "block";
+                } else {
+                    loginErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Existing accounts are treated as verified, new accounts have to verify their email first
+ALTER TABLE users ADD COLUMN IF NOT EXISTS verified BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ALTER COLUMN verified SET DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- Set while a deleted account is kept as a tombstone, the row is purged once the grace period is over
+ALTER TABLE users ADD COLUMN IF NOT EXISTS restorable_until TIMESTAMPTZ;
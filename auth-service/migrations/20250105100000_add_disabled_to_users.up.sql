@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- Set by an admin to block the user from logging in
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled BOOLEAN NOT NULL DEFAULT FALSE;
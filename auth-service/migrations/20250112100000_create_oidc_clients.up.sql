@@ -0,0 +1,8 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS oidc_clients(
+   client_id TEXT PRIMARY KEY,
+   name TEXT NOT NULL,
+   redirect_uris TEXT[] NOT NULL,
+   secret_hash TEXT,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
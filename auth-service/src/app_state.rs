@@ -1,12 +1,24 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::domain::{ApiKeyStore, AuthorizationCodeStore, BannedTokenStore, EmailClient, FederatedIdentityStore, IdentityProvider, LoginThrottleStore, OidcClientStore, PasswordPolicy, PasswordResetTokenStore, RateLimitStore, RateLimits, RecoveryCodeStore, RefreshTokenStore, SessionStore, TotpStore, TwoFACodeStore, UserStore};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore + Send + Sync>>;
+pub type TotpStoreType = Arc<RwLock<dyn TotpStore + Send + Sync>>;
+pub type LoginThrottleStoreType = Arc<RwLock<dyn LoginThrottleStore + Send + Sync>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore + Send + Sync>>;
+pub type OidcClientStoreType = Arc<RwLock<dyn OidcClientStore + Send + Sync>>;
+pub type AuthorizationCodeStoreType = Arc<RwLock<dyn AuthorizationCodeStore + Send + Sync>>;
+pub type FederatedIdentityStoreType = Arc<RwLock<dyn FederatedIdentityStore + Send + Sync>>;
+pub type ApiKeyStoreType = Arc<RwLock<dyn ApiKeyStore + Send + Sync>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore + Send + Sync>>;
+pub type IdentityProviderType = Arc<dyn IdentityProvider + Send + Sync>;
 // New!
 
 #[derive(Clone)]
@@ -14,11 +26,75 @@ pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
-    pub email_client: EmailClientType
+    // Kept apart from the 2FA codes, so a magic login request can not replace or redeem a pending 2FA code
+    pub magic_login_code_store: TwoFACodeStoreType,
+    // Codes confirming that 2FA is enabled or disabled, apart from the login codes for the same reason
+    pub two_fa_confirmation_code_store: TwoFACodeStoreType,
+    pub email_client: EmailClientType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub totp_store: TotpStoreType,
+    pub login_throttle_store: LoginThrottleStoreType,
+    pub session_store: SessionStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub oidc_client_store: OidcClientStoreType,
+    pub authorization_code_store: AuthorizationCodeStoreType,
+    pub federated_identity_store: FederatedIdentityStoreType,
+    pub api_key_store: ApiKeyStoreType,
+    pub rate_limit_store: RateLimitStoreType,
+    pub rate_limits: Arc<RateLimits>,
+    // Applies to new passwords, chosen at signup, reset or change
+    pub password_policy: Arc<PasswordPolicy>,
+    // Federated login is only offered when an external provider is configured
+    pub identity_provider: Option<IdentityProviderType>,
 }
 
 impl AppState {
-    pub fn new(user_store: UserStoreType, banned_token_store: BannedTokenStoreType, two_fa_code_store: TwoFACodeStoreType, email_client: EmailClientType,) -> Self {
-        Self { user_store, banned_token_store, two_fa_code_store, email_client }
+    // Every store is injected, so main and the tests can choose between the Redis, Postgres and hashmap backends
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_store: UserStoreType,
+        banned_token_store: BannedTokenStoreType,
+        two_fa_code_store: TwoFACodeStoreType,
+        magic_login_code_store: TwoFACodeStoreType,
+        two_fa_confirmation_code_store: TwoFACodeStoreType,
+        email_client: EmailClientType,
+        refresh_token_store: RefreshTokenStoreType,
+        password_reset_token_store: PasswordResetTokenStoreType,
+        totp_store: TotpStoreType,
+        login_throttle_store: LoginThrottleStoreType,
+        session_store: SessionStoreType,
+        recovery_code_store: RecoveryCodeStoreType,
+        oidc_client_store: OidcClientStoreType,
+        authorization_code_store: AuthorizationCodeStoreType,
+        federated_identity_store: FederatedIdentityStoreType,
+        api_key_store: ApiKeyStoreType,
+        rate_limit_store: RateLimitStoreType,
+        rate_limits: RateLimits,
+        password_policy: PasswordPolicy,
+        identity_provider: Option<IdentityProviderType>,
+    ) -> Self {
+        Self {
+            user_store,
+            banned_token_store,
+            two_fa_code_store,
+            magic_login_code_store,
+            two_fa_confirmation_code_store,
+            email_client,
+            refresh_token_store,
+            password_reset_token_store,
+            totp_store,
+            login_throttle_store,
+            session_store,
+            recovery_code_store,
+            oidc_client_store,
+            authorization_code_store,
+            federated_identity_store,
+            api_key_store,
+            rate_limit_store,
+            rate_limits: Arc::new(rate_limits),
+            password_policy: Arc::new(password_policy),
+            identity_provider,
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,150 @@
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+
+use super::{
+    oidc::{hash_secret, random_string},
+    Email,
+};
+
+// Lets /verify-token tell API keys from JWTs without a store lookup
+pub const API_KEY_PREFIX: &str = "lbk_";
+const API_KEY_SECRET_LENGTH: usize = 40;
+const API_KEY_NAME_MAX_LENGTH: usize = 100;
+pub const API_KEY_MAX_LIFETIME_DAYS: u32 = 365;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// A long lived credential for scripts and CI jobs, presented to /verify-token in place of a JWT
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiKey {
+    pub id: String,
+    pub email: Email,
+    pub name: String,
+    // Only the hash of the key is stored, the key itself is shown once when it is created
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    // Unix timestamps, keys without expiry stay valid until they are revoked
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+impl ApiKey {
+    // Returns the new key together with its secret value
+    pub fn generate(
+        email: Email,
+        name: String,
+        scopes: Vec<String>,
+        lifetime_days: Option<u32>,
+    ) -> Result<(Self, String)> {
+        let name = name.trim().to_owned();
+        if name.is_empty() || name.chars().count() > API_KEY_NAME_MAX_LENGTH {
+            return Err(eyre!("API key name must have 1 to {} characters", API_KEY_NAME_MAX_LENGTH));
+        }
+
+        let mut scopes = scopes;
+        for scope in &scopes {
+            validate_scope(scope)?;
+        }
+        scopes.sort();
+        scopes.dedup();
+
+        let created_at = Utc::now().timestamp();
+        let expires_at = match lifetime_days {
+            Some(days) if days == 0 || days > API_KEY_MAX_LIFETIME_DAYS => {
+                return Err(eyre!("API key lifetime must be 1 to {} days", API_KEY_MAX_LIFETIME_DAYS));
+            }
+            Some(days) => Some(created_at + i64::from(days) * SECONDS_PER_DAY),
+            None => None,
+        };
+
+        let secret = format!("{}{}", API_KEY_PREFIX, random_string(API_KEY_SECRET_LENGTH));
+        let key = Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            email,
+            name,
+            key_hash: Self::hash(&secret),
+            scopes,
+            created_at,
+            expires_at,
+        };
+
+        Ok((key, secret))
+    }
+
+    pub fn is_api_key(token: &str) -> bool {
+        token.starts_with(API_KEY_PREFIX)
+    }
+
+    // Keys are long and random, so a plain SHA-256 is enough to look them up without storing them
+    pub fn hash(secret: &str) -> String {
+        hash_secret(secret)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now().timestamp())
+    }
+}
+
+// Scopes are opaque to the auth service, they only have to be single words (ex: repo:read)
+fn validate_scope(scope: &str) -> Result<()> {
+    let valid = !scope.is_empty()
+        && scope
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '.' | '_' | '-'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(eyre!("Invalid scope {}", scope))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_owned()).unwrap()
+    }
+
+    #[test]
+    fn generated_key_is_only_stored_as_hash() {
+        let (key, secret) = ApiKey::generate(email(), "CI".to_owned(), vec![], None).unwrap();
+        assert!(ApiKey::is_api_key(&secret));
+        assert_eq!(key.key_hash, ApiKey::hash(&secret));
+        assert_ne!(key.key_hash, secret);
+        assert!(!key.is_expired());
+        assert_eq!(key.expires_at, None);
+    }
+
+    #[test]
+    fn scopes_are_validated_and_deduplicated() {
+        let scopes = vec!["repo:write".to_owned(), "repo:read".to_owned(), "repo:read".to_owned()];
+        let (key, _) = ApiKey::generate(email(), "CI".to_owned(), scopes, None).unwrap();
+        assert_eq!(key.scopes, vec!["repo:read".to_owned(), "repo:write".to_owned()]);
+
+        assert!(ApiKey::generate(email(), "CI".to_owned(), vec!["repo read".to_owned()], None).is_err());
+        assert!(ApiKey::generate(email(), "CI".to_owned(), vec!["".to_owned()], None).is_err());
+    }
+
+    #[test]
+    fn name_and_lifetime_are_validated() {
+        assert!(ApiKey::generate(email(), " ".to_owned(), vec![], None).is_err());
+        assert!(ApiKey::generate(email(), "a".repeat(101), vec![], None).is_err());
+        assert!(ApiKey::generate(email(), "CI".to_owned(), vec![], Some(0)).is_err());
+        assert!(ApiKey::generate(email(), "CI".to_owned(), vec![], Some(366)).is_err());
+
+        let (key, _) = ApiKey::generate(email(), "CI".to_owned(), vec![], Some(30)).unwrap();
+        assert_eq!(key.expires_at, Some(key.created_at + 30 * SECONDS_PER_DAY));
+    }
+
+    #[test]
+    fn key_past_its_expiry_is_expired() {
+        let (key, _) = ApiKey::generate(email(), "CI".to_owned(), vec![], Some(1)).unwrap();
+        let key = ApiKey {
+            expires_at: Some(Utc::now().timestamp() - 1),
+            ..key
+        };
+        assert!(key.is_expired());
+    }
+}
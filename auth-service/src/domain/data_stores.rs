@@ -1,4 +1,4 @@
-use crate::domain::{Email,Password,TwoFACode,LoginAttemptId};
+use crate::domain::{Email,Password,TwoFACode,LoginAttemptId,RefreshToken,PasswordResetToken,TotpSecret,Session,RecoveryCode,Role,OidcClient,AuthorizationCode,AuthorizationGrant,ApiKey,RateLimit};
 use super::User;
 
 use color_eyre::eyre::Report;
@@ -35,9 +35,27 @@ impl PartialEq for UserStoreError {
 pub trait UserStore {
     // Add the `add_user`, `get_user`, and `validate_user` methods.
     // Make sure all methods are async so we can use async user stores in the future
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&mut self, user: User, password: Password) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    // Stores hashing passwords upgrade hashes made with outdated settings once the password is known to be right
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError>;
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError>;
+    async fn set_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError>;
+    // Replaces the roles of the user, they take effect with the next JWT the user receives
+    async fn set_roles(&mut self, email: &Email, roles: Vec<Role>) -> Result<(), UserStoreError>;
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError>;
+    // Returns one page of users ordered by email, and the total number of users matching the search.
+    // The search matches any part of the email address, ignoring case.
+    async fn list_users(&self, search: Option<&str>, offset: u64, limit: u64) -> Result<(Vec<User>, u64), UserStoreError>;
+    // Removes the user for good
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // Keeps a tombstone that can be restored during the grace period. Until then the user is treated as
+    // not found everywhere else, but the email address can not be used for a new account.
+    async fn soft_delete_user(&mut self, email: &Email, grace_period_seconds: u64) -> Result<(), UserStoreError>;
+    async fn restore_user(&mut self, email: &Email, password: &Password) -> Result<(), UserStoreError>;
+    // Deletes every tombstone whose grace period is over, returns how many were removed
+    async fn purge_deleted_users(&mut self) -> Result<u64, UserStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -50,6 +68,10 @@ pub enum BannedTokenStoreError {
 pub trait BannedTokenStore {
     async fn add_token_to_banned_store(&mut self, token: String) -> Result<(), BannedTokenStoreError>;
     async fn verify_token_in_banned_store(&self, token: &str) -> Result<bool, BannedTokenStoreError>;
+    // Every token carries the user's token generation from the moment it was issued.
+    // Incrementing the generation bans all tokens the user received so far.
+    async fn get_token_generation(&self, email: &Email) -> Result<u64, BannedTokenStoreError>;
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<u64, BannedTokenStoreError>;
 }
 
 
@@ -88,9 +110,332 @@ pub trait TwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    // Removes the code of the login attempt in a single step, so concurrent requests can never both consume it
+    async fn consume_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError>;
+    // Counts a wrong code for the login attempt and returns the number of wrong codes so far
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError>;
+}
+
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token reused")]
+    TokenReused,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::TokenReused, Self::TokenReused)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Refresh tokens are grouped into families. Every rotation issues a new token in the same family,
+// so presenting an already used token means the family leaked and the whole family gets revoked.
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        family_id: String,
+        token: RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError>;
+    // Marks the token as used and returns its owner and family.
+    // Returns `TokenReused` (and revokes the family) if the token was already used.
+    async fn use_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, String), RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_user_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError>;
 }
 
+#[derive(Debug, Error)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Password reset token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
 
+impl PartialEq for PasswordResetTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
 
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    // Removes the token and returns its owner, so a reset token can only be used once
+    async fn consume_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError>;
+}
 
+#[derive(Debug, Error)]
+pub enum TotpStoreError {
+    #[error("TOTP secret not found")]
+    SecretNotFound,
+    #[error("TOTP code already used")]
+    CodeReused,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TotpStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SecretNotFound, Self::SecretNotFound)
+                | (Self::CodeReused, Self::CodeReused)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait TotpStore {
+    // Stores a new, not yet confirmed secret, replacing any previous one
+    async fn set_secret(&mut self, email: Email, secret: TotpSecret) -> Result<(), TotpStoreError>;
+    // Returns the secret and whether it has been confirmed
+    async fn get_secret(&self, email: &Email) -> Result<(TotpSecret, bool), TotpStoreError>;
+    async fn enable(&mut self, email: &Email) -> Result<(), TotpStoreError>;
+    // Returns `CodeReused` unless the time step is newer than the last one used, so every code works only once
+    async fn use_time_step(&mut self, email: &Email, time_step: u64) -> Result<(), TotpStoreError>;
+    // Turns TOTP off, a later enrollment starts with a new secret
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum LoginThrottleStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for LoginThrottleStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait LoginThrottleStore {
+    // Counts a failed attempt and returns the number of failures so far.
+    // The counter expires `window_seconds` after the last failure.
+    async fn record_failure(&mut self, key: &str, window_seconds: u64) -> Result<u32, LoginThrottleStoreError>;
+    async fn lock(&mut self, key: &str, seconds: u64) -> Result<(), LoginThrottleStoreError>;
+    // Returns the remaining lockout in seconds, if the key is locked
+    async fn get_lockout(&self, key: &str) -> Result<Option<u64>, LoginThrottleStoreError>;
+    // Resets both the failure counter and any lockout
+    async fn clear(&mut self, key: &str) -> Result<(), LoginThrottleStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Sessions expire together with their refresh token family unless they are refreshed
+#[async_trait::async_trait]
+pub trait SessionStore {
+    // Adding an existing session again extends its lifetime
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, session_id: &str) -> Result<Session, SessionStoreError>;
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    async fn remove_session(&mut self, session_id: &str) -> Result<(), SessionStoreError>;
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Recovery code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
 
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Stores keep only the hashes of the codes
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore {
+    // Replaces all codes of the user, so previously issued codes stop working
+    async fn set_codes(&mut self, email: &Email, codes: &[RecoveryCode]) -> Result<(), RecoveryCodeStoreError>;
+    // Removes the code in a single step, so every code can be used only once
+    async fn use_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), RecoveryCodeStoreError>;
+}
+
+
+#[derive(Debug, Error)]
+pub enum OidcClientStoreError {
+    #[error("Client not found")]
+    ClientNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OidcClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientNotFound, Self::ClientNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait OidcClientStore {
+    async fn add_client(&mut self, client: OidcClient) -> Result<(), OidcClientStoreError>;
+    async fn get_client(&self, client_id: &str) -> Result<OidcClient, OidcClientStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuthorizationCodeStoreError {
+    #[error("Authorization code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuthorizationCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AuthorizationCodeStore {
+    async fn add_code(&mut self, code: AuthorizationCode, grant: AuthorizationGrant) -> Result<(), AuthorizationCodeStoreError>;
+    // Removes the code while reading it, so a code can be redeemed only once
+    async fn consume_code(&mut self, code: &AuthorizationCode) -> Result<AuthorizationGrant, AuthorizationCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum FederatedIdentityStoreError {
+    #[error("Identity not found")]
+    IdentityNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for FederatedIdentityStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::IdentityNotFound, Self::IdentityNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Links the subject of an external identity provider to a local user. Once linked, the provider's subject
+// decides the user, so a changed email address at the provider does not lead to a different account.
+#[async_trait::async_trait]
+pub trait FederatedIdentityStore {
+    async fn link_identity(&mut self, issuer: &str, subject: &str, email: &Email) -> Result<(), FederatedIdentityStoreError>;
+    async fn get_linked_email(&self, issuer: &str, subject: &str) -> Result<Email, FederatedIdentityStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyStoreError {
+    #[error("API key not found")]
+    KeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ApiKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::KeyNotFound, Self::KeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Keys are looked up by the hash of their secret, the secret itself is never stored
+#[async_trait::async_trait]
+pub trait ApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError>;
+    async fn get_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, ApiKeyStoreError>;
+    async fn get_user_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError>;
+    // Keys of other users are reported as missing, so key ids can not be probed
+    async fn remove_key(&mut self, email: &Email, id: &str) -> Result<(), ApiKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Token buckets of the rate limiter, one per client and route
+#[async_trait::async_trait]
+pub trait RateLimitStore {
+    // Takes a token from the bucket of the key, returns the seconds until the next token if it is empty
+    async fn take_token(&mut self, key: &str, limit: RateLimit) -> Result<Option<u64>, RateLimitStoreError>;
+}
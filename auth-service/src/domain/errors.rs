@@ -1,6 +1,8 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
+use super::PasswordPolicyError;
+
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User already exists")]
@@ -13,7 +15,60 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Account disabled")]
+    AccountDisabled,
+    // The user is logged in but lacks the role the endpoint requires
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("TOTP already enabled")]
+    TotpAlreadyEnabled,
+    #[error("2FA already enabled")]
+    TwoFAAlreadyEnabled,
+    #[error("2FA not enabled")]
+    TwoFANotEnabled,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("API key not found")]
+    ApiKeyNotFound,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Federated login not configured")]
+    FederatedLoginNotConfigured,
+    // Carries the message telling the user which rule the new password breaks
+    #[error("Password policy violation: {0}")]
+    PasswordPolicyViolation(String),
+    // Carries the number of seconds after which the client may retry
+    #[error("Too many requests")]
+    TooManyRequests(u64),
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
 
+// Failing to look up breached passwords is our problem, not the user's
+impl From<PasswordPolicyError> for AuthAPIError {
+    fn from(error: PasswordPolicyError) -> Self {
+        match error {
+            PasswordPolicyError::UnexpectedError(e) => AuthAPIError::UnexpectedError(e),
+            violation => AuthAPIError::PasswordPolicyViolation(violation.to_string()),
+        }
+    }
+}
+
+// Errors of the OpenID Connect endpoints, reported with the error codes of RFC 6749 instead of our own messages
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("invalid_request")]
+    InvalidRequest(String),
+    #[error("invalid_client")]
+    InvalidClient,
+    #[error("invalid_grant")]
+    InvalidGrant,
+    #[error("unsupported_grant_type")]
+    UnsupportedGrantType,
+    #[error("invalid_token")]
+    InvalidToken,
+    #[error("server_error")]
+    UnexpectedError(#[source] Report),
+}
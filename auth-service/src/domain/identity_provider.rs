@@ -0,0 +1,32 @@
+use super::Email;
+use color_eyre::eyre::Result;
+
+// A user as asserted by an external OpenID Connect provider
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: Email,
+    pub email_verified: bool,
+}
+
+// An external provider users can log in with, see `routes::start_federated_login`
+#[async_trait::async_trait]
+pub trait IdentityProvider {
+    // Where the browser is sent to log in at the provider
+    async fn authorization_url(
+        &self,
+        redirect_uri: &str,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String>;
+    // Redeems the code the provider sent back and returns the identity from the validated ID token
+    async fn exchange_code(
+        &self,
+        redirect_uri: &str,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<ExternalIdentity>;
+}
@@ -1,7 +1,7 @@
 use uuid::Uuid;
 use color_eyre::eyre::{Context, Result};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub struct LoginAttemptId(String);
 
 impl LoginAttemptId {
@@ -3,15 +3,43 @@ pub mod errors;
 pub mod data_stores;
 pub mod email;
 pub mod password;
+pub mod password_hash;
+pub mod password_hasher;
 pub mod two_fa_code;
 pub mod login_attempt_id;
 pub mod email_client;
+pub mod refresh_token;
+pub mod password_reset_token;
+pub mod totp_secret;
+pub mod session;
+pub mod recovery_code;
+pub mod role;
+pub mod oidc;
+pub mod identity_provider;
+pub mod api_key;
+pub mod rate_limit;
+pub mod password_strength;
+pub mod password_policy;
 
 pub use user::*;
 pub use errors::*;
 pub use data_stores::*;
 pub use email::*;
 pub use password::*;
+pub use password_hash::*;
+pub use password_hasher::*;
 pub use two_fa_code::*;
 pub use login_attempt_id::*;
-pub use email_client::*;
\ No newline at end of file
+pub use email_client::*;
+pub use refresh_token::*;
+pub use password_reset_token::*;
+pub use totp_secret::*;
+pub use session::*;
+pub use recovery_code::*;
+pub use role::*;
+pub use oidc::*;
+pub use identity_provider::*;
+pub use api_key::*;
+pub use rate_limit::*;
+pub use password_strength::*;
+pub use password_policy::*;
\ No newline at end of file
@@ -0,0 +1,237 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{distributions::Alphanumeric, prelude::*};
+use ring::digest::{digest, SHA256};
+use color_eyre::eyre::{eyre, Result};
+use url::Url;
+
+use super::Email;
+
+const CLIENT_SECRET_LENGTH: usize = 48;
+const AUTHORIZATION_CODE_LENGTH: usize = 48;
+// RFC 7636 limits the code verifier to 43-128 characters
+const CODE_VERIFIER_MIN_LENGTH: usize = 43;
+const CODE_VERIFIER_MAX_LENGTH: usize = 128;
+
+// A relying party that may sign users in through /authorize. Confidential clients authenticate at /token
+// with their secret, public clients (ex: single page apps) rely on PKCE alone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OidcClient {
+    pub client_id: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    // Only the hash of the secret is stored, the secret itself is shown once at registration
+    pub secret_hash: Option<String>,
+}
+
+impl OidcClient {
+    // Returns the new client together with its secret if it is confidential
+    pub fn register(name: String, redirect_uris: Vec<String>, confidential: bool) -> Result<(Self, Option<String>)> {
+        if name.trim().is_empty() {
+            return Err(eyre!("Client name must not be empty"));
+        }
+        if redirect_uris.is_empty() {
+            return Err(eyre!("At least one redirect URI is required"));
+        }
+        for redirect_uri in &redirect_uris {
+            validate_redirect_uri(redirect_uri)?;
+        }
+
+        let secret = confidential.then(|| random_string(CLIENT_SECRET_LENGTH));
+        let client = Self {
+            client_id: uuid::Uuid::new_v4().to_string(),
+            name,
+            redirect_uris,
+            secret_hash: secret.as_deref().map(hash_secret),
+        };
+
+        Ok((client, secret))
+    }
+
+    pub fn is_confidential(&self) -> bool {
+        self.secret_hash.is_some()
+    }
+
+    // Redirect URIs are compared exactly, as required by OAuth 2.0 Security Best Current Practice
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|allowed| allowed == redirect_uri)
+    }
+
+    pub fn verify_secret(&self, secret: Option<&str>) -> bool {
+        match (&self.secret_hash, secret) {
+            (None, _) => true,
+            (Some(secret_hash), Some(secret)) => hash_secret(secret) == *secret_hash,
+            (Some(_), None) => false,
+        }
+    }
+}
+
+// Redirect URIs must be absolute and carry no fragment. Plain http is only allowed for local development.
+fn validate_redirect_uri(redirect_uri: &str) -> Result<()> {
+    let url = Url::parse(redirect_uri).map_err(|_| eyre!("Invalid redirect URI {}", redirect_uri))?;
+
+    let is_local = matches!(url.host_str(), Some("localhost") | Some("127.0.0.1") | Some("[::1]"));
+    let scheme_allowed = url.scheme() == "https" || (url.scheme() == "http" && is_local);
+
+    if !scheme_allowed || url.fragment().is_some() {
+        return Err(eyre!("Invalid redirect URI {}", redirect_uri));
+    }
+
+    Ok(())
+}
+
+pub(super) fn hash_secret(secret: &str) -> String {
+    digest(&SHA256, secret.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub(super) fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+// Single use code handed to the relying party by /authorize and redeemed at /token
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct AuthorizationCode(String);
+
+impl AuthorizationCode {
+    pub fn parse(code: String) -> Result<Self> {
+        if code.len() == AUTHORIZATION_CODE_LENGTH && code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Ok(Self(code))
+        } else {
+            Err(eyre!("Invalid authorization code"))
+        }
+    }
+}
+
+impl Default for AuthorizationCode {
+    fn default() -> Self {
+        Self(random_string(AUTHORIZATION_CODE_LENGTH))
+    }
+}
+
+impl AsRef<str> for AuthorizationCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Everything /token needs to know about the authorization an authorization code stands for
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthorizationGrant {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub email: Email,
+    pub scope: String,
+    pub nonce: Option<String>,
+    // S256 challenge the code verifier sent to /token has to match
+    pub code_challenge: String,
+    // Unix timestamp of the login the grant was issued for
+    pub auth_time: i64,
+}
+
+impl AuthorizationGrant {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split_whitespace().any(|granted| granted == scope)
+    }
+
+    pub fn verify_code_verifier(&self, code_verifier: &str) -> bool {
+        let valid_verifier = (CODE_VERIFIER_MIN_LENGTH..=CODE_VERIFIER_MAX_LENGTH).contains(&code_verifier.len())
+            && code_verifier
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'));
+
+        valid_verifier && pkce_challenge(code_verifier) == self.code_challenge
+    }
+}
+
+// A random PKCE code verifier, for the logins we start at external providers
+pub fn generate_code_verifier() -> String {
+    random_string(CODE_VERIFIER_MAX_LENGTH / 2)
+}
+
+// The S256 code challenge of a PKCE code verifier
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, code_verifier.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn register(redirect_uri: &str, confidential: bool) -> Result<(OidcClient, Option<String>)> {
+        OidcClient::register("Test app".to_owned(), vec![redirect_uri.to_owned()], confidential)
+    }
+
+    #[test]
+    fn redirect_uris_are_validated() {
+        assert!(register("https://app.example.com/callback", false).is_ok());
+        assert!(register("http://127.0.0.1:8000/callback", false).is_ok());
+        assert!(register("http://app.example.com/callback", false).is_err());
+        assert!(register("https://app.example.com/callback#fragment", false).is_err());
+        assert!(register("/callback", false).is_err());
+        assert!(OidcClient::register("Test app".to_owned(), vec![], false).is_err());
+    }
+
+    #[test]
+    fn only_confidential_clients_get_a_secret() {
+        let (client, secret) = register("https://app.example.com/callback", true).unwrap();
+        let secret = secret.unwrap();
+        assert!(client.is_confidential());
+        assert!(client.verify_secret(Some(&secret)));
+        assert!(!client.verify_secret(Some("wrong-secret")));
+        assert!(!client.verify_secret(None));
+
+        let (client, secret) = register("https://app.example.com/callback", false).unwrap();
+        assert_eq!(secret, None);
+        assert!(!client.is_confidential());
+        assert!(client.verify_secret(None));
+    }
+
+    #[test]
+    fn redirect_uri_must_match_exactly() {
+        let (client, _) = register("https://app.example.com/callback", false).unwrap();
+        assert!(client.allows_redirect_uri("https://app.example.com/callback"));
+        assert!(!client.allows_redirect_uri("https://app.example.com/callback/"));
+        assert!(!client.allows_redirect_uri("https://app.example.com/callback?next=/"));
+    }
+
+    #[test]
+    fn code_verifier_is_checked_against_challenge() {
+        // Example from RFC 7636 appendix B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        assert_eq!(pkce_challenge(verifier), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+
+        let grant = AuthorizationGrant {
+            client_id: "client".to_owned(),
+            redirect_uri: "https://app.example.com/callback".to_owned(),
+            email: Email::parse("test@example.com".to_owned()).unwrap(),
+            scope: "openid".to_owned(),
+            nonce: None,
+            code_challenge: pkce_challenge(verifier),
+            auth_time: 0,
+        };
+        assert!(grant.verify_code_verifier(verifier));
+        assert!(!grant.verify_code_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXx"));
+        assert!(!grant.verify_code_verifier("too-short"));
+
+        let verifier = generate_code_verifier();
+        let grant = AuthorizationGrant {
+            code_challenge: pkce_challenge(&verifier),
+            ..grant
+        };
+        assert!(grant.verify_code_verifier(&verifier));
+    }
+
+    #[test]
+    fn valid_auto_generated_code_is_accepted() {
+        let code = AuthorizationCode::default();
+        assert!(AuthorizationCode::parse(code.as_ref().to_owned()).is_ok());
+        assert!(AuthorizationCode::parse("not-a-code".to_owned()).is_err());
+    }
+}
@@ -1,12 +1,16 @@
 use color_eyre::eyre::{eyre, Result};
 use validator::validate_length;
 
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+// Keeps huge inputs away from Argon2, whatever the password policy allows
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub struct Password(String);
 
 impl Password {
     pub fn parse(s: String) -> Result<Password> {
-        if validate_length(&s,Some(8),None, None) {
+        if validate_length(&s, Some(MIN_PASSWORD_LENGTH as u64), Some(MAX_PASSWORD_LENGTH as u64), None) {
             Ok(Self(s))
         } else {
             Err(eyre!(format!("{} is not a valid password.",s)))
@@ -38,6 +42,11 @@ mod tests {
         assert!(Password::parse(password).is_err());
     }
     #[test]
+    fn string_more_than_1024_characters_is_rejected() {
+        let password = "a".repeat(1025);
+        assert!(Password::parse(password).is_err());
+    }
+    #[test]
     fn string_more_than_8_characters_is_accepted() {
         let password = "12345678".to_owned();
         assert!(Password::parse(password).is_ok());
@@ -0,0 +1,50 @@
+use color_eyre::eyre::{eyre, Result};
+
+// A stored password hash in the PHC string format, ex: $argon2id$v=19$m=15000,t=2,p=1$<salt>$<hash>.
+// Kept apart from `Password`, so plaintext passwords and hashes can not be mixed up.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PasswordHash(String);
+
+impl PasswordHash {
+    pub fn parse(s: String) -> Result<PasswordHash> {
+        argon2::PasswordHash::new(&s).map_err(|e| eyre!("Invalid password hash: {}", e))?;
+        Ok(Self(s))
+    }
+}
+
+impl AsRef<str> for PasswordHash {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Hashes are as good as the passwords they stem from, so they never show up in logs
+impl std::fmt::Debug for PasswordHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PasswordHash(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PasswordHash;
+
+    #[test]
+    fn phc_strings_are_accepted() {
+        let hash = "$argon2id$v=19$m=15000,t=2,p=1$c29tZXNhbHRzb21lc2FsdA$3VqcE7aGqVBzGLY/aV2oYutFBKHhrzFh/VuK4cYoLeg";
+        assert_eq!(PasswordHash::parse(hash.to_owned()).unwrap().as_ref(), hash);
+    }
+
+    #[test]
+    fn plaintext_passwords_are_rejected() {
+        for hash in ["", "password123", "argon2id$v=19"] {
+            assert!(PasswordHash::parse(hash.to_owned()).is_err(), "Failed for input: {}", hash);
+        }
+    }
+
+    #[test]
+    fn debug_output_hides_the_hash() {
+        let hash = "$argon2id$v=19$m=15000,t=2,p=1$c29tZXNhbHRzb21lc2FsdA$3VqcE7aGqVBzGLY/aV2oYutFBKHhrzFh/VuK4cYoLeg";
+        assert_eq!(format!("{:?}", PasswordHash::parse(hash.to_owned()).unwrap()), "PasswordHash(..)");
+    }
+}
@@ -0,0 +1,16 @@
+use std::sync::Arc;
+use color_eyre::eyre::Result;
+
+use super::{Password, PasswordHash};
+
+// Turns passwords into the hashes every user store keeps, see `services::Argon2PasswordHasher`
+#[async_trait::async_trait]
+pub trait PasswordHasher {
+    async fn hash(&self, password: &Password) -> Result<PasswordHash>;
+    // A wrong password is Ok(false), errors are left for hashes that can not be checked at all
+    async fn verify(&self, password: &Password, password_hash: &PasswordHash) -> Result<bool>;
+    // Hashes made with other settings than `hash` uses today, stores replace them once the password is known
+    fn needs_rehash(&self, password_hash: &PasswordHash) -> bool;
+}
+
+pub type PasswordHasherType = Arc<dyn PasswordHasher + Send + Sync>;
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use color_eyre::eyre::{eyre, Report, Result};
+use thiserror::Error;
+
+use super::{password_strength, MAX_PASSWORD_LENGTH, MIN_PASSWORD_LENGTH};
+
+// Passwords known from data breaches, see `services::HibpRangeFiles`
+#[async_trait::async_trait]
+pub trait BreachedPasswordCorpus {
+    // How often the password appears in the corpus, 0 if it was never breached
+    async fn breach_count(&self, password: &str) -> Result<u64>;
+}
+
+pub type BreachedPasswordCorpusType = Arc<dyn BreachedPasswordCorpus + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum PasswordPolicyError {
+    #[error("Password must be at least {0} characters long")]
+    TooShort(usize),
+    #[error("Password must be at most {0} characters long")]
+    TooLong(usize),
+    #[error("Password is too easy to guess")]
+    TooWeak,
+    #[error("Password has appeared in a data breach")]
+    Breached,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasswordPolicyError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TooShort(_), Self::TooShort(_))
+                | (Self::TooLong(_), Self::TooLong(_))
+                | (Self::TooWeak, Self::TooWeak)
+                | (Self::Breached, Self::Breached)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Rules for new passwords, checked at signup, reset and change. Existing passwords are only parsed with
+// `Password::parse`, so tightening the policy never locks anyone out.
+#[derive(Clone)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    // Minimum score of `password_strength`, 0 accepts anything
+    min_strength: u8,
+    breached_passwords: Option<BreachedPasswordCorpusType>,
+}
+
+impl PasswordPolicy {
+    // Lengths are counted in characters and must lie within the bounds `Password::parse` accepts
+    pub fn with_length(mut self, min_length: usize, max_length: usize) -> Result<Self> {
+        if min_length < MIN_PASSWORD_LENGTH || max_length > MAX_PASSWORD_LENGTH || min_length > max_length {
+            return Err(eyre!(
+                "Password lengths must lie between {} and {}",
+                MIN_PASSWORD_LENGTH,
+                MAX_PASSWORD_LENGTH
+            ));
+        }
+        self.min_length = min_length;
+        self.max_length = max_length;
+        Ok(self)
+    }
+
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    pub fn with_min_strength(mut self, min_strength: u8) -> Result<Self> {
+        if min_strength > 4 {
+            return Err(eyre!("Password strength ranges from 0 to 4"));
+        }
+        self.min_strength = min_strength;
+        Ok(self)
+    }
+
+    pub fn with_breached_passwords(mut self, corpus: BreachedPasswordCorpusType) -> Self {
+        self.breached_passwords = Some(corpus);
+        self
+    }
+
+    // User inputs, like the email address, make passwords built from them count as weak
+    pub async fn check(&self, password: &str, user_inputs: &[&str]) -> Result<(), PasswordPolicyError> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(PasswordPolicyError::TooShort(self.min_length));
+        }
+        if length > self.max_length {
+            return Err(PasswordPolicyError::TooLong(self.max_length));
+        }
+
+        if let Some(corpus) = &self.breached_passwords {
+            let count = corpus
+                .breach_count(password)
+                .await
+                .map_err(PasswordPolicyError::UnexpectedError)?;
+            if count > 0 {
+                return Err(PasswordPolicyError::Breached);
+            }
+        }
+
+        if password_strength(password, user_inputs) < self.min_strength {
+            return Err(PasswordPolicyError::TooWeak);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: MIN_PASSWORD_LENGTH,
+            max_length: 128,
+            min_strength: 3,
+            breached_passwords: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeCorpus(Vec<&'static str>);
+
+    #[async_trait::async_trait]
+    impl BreachedPasswordCorpus for FakeCorpus {
+        async fn breach_count(&self, password: &str) -> Result<u64> {
+            Ok(self.0.iter().filter(|breached| **breached == password).count() as u64)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_length() {
+        let policy = PasswordPolicy::default().with_length(12, 16).unwrap();
+
+        assert_eq!(policy.check("k8#Tq2!vZr9", &[]).await, Err(PasswordPolicyError::TooShort(12)));
+        assert_eq!(policy.check("k8#Tq2!vZr9mW4xYp", &[]).await, Err(PasswordPolicyError::TooLong(16)));
+        assert_eq!(policy.check("k8#Tq2!vZr9m", &[]).await, Ok(()));
+        // Characters are counted, not bytes
+        assert_eq!(policy.check("ünïcødé-pässwörd", &[]).await, Ok(()));
+
+        assert!(PasswordPolicy::default().with_length(4, 16).is_err());
+        assert!(PasswordPolicy::default().with_length(16, 12).is_err());
+        assert!(PasswordPolicy::default().with_length(8, MAX_PASSWORD_LENGTH + 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_strength() {
+        let policy = PasswordPolicy::default();
+
+        assert_eq!(policy.check("password123", &[]).await, Err(PasswordPolicyError::TooWeak));
+        assert_eq!(
+            policy.check("marlowe-2024", &["jane.marlowe@example.com"]).await,
+            Err(PasswordPolicyError::TooWeak)
+        );
+        assert_eq!(policy.check("Glacier-Tandem-47-Orbit", &[]).await, Ok(()));
+
+        let lenient = PasswordPolicy::default().with_min_strength(0).unwrap();
+        assert_eq!(lenient.check("password123", &[]).await, Ok(()));
+        assert!(PasswordPolicy::default().with_min_strength(5).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_breached() {
+        let policy = PasswordPolicy::default()
+            .with_breached_passwords(Arc::new(FakeCorpus(vec!["Glacier-Tandem-47-Orbit"])));
+
+        assert_eq!(policy.check("Glacier-Tandem-47-Orbit", &[]).await, Err(PasswordPolicyError::Breached));
+        assert_eq!(policy.check("Glacier-Tandem-48-Orbit", &[]).await, Ok(()));
+    }
+}
@@ -0,0 +1,52 @@
+use rand::{distributions::Alphanumeric, prelude::*};
+use color_eyre::eyre::{eyre, Result};
+
+const PASSWORD_RESET_TOKEN_LENGTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct PasswordResetToken(String);
+
+impl PasswordResetToken {
+    pub fn parse(token: String) -> Result<Self> {
+        if token.len() == PASSWORD_RESET_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid password reset token"))
+        }
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        // The reset token is emailed to the user, so it has to be long enough not to be guessable
+        let token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(PASSWORD_RESET_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        Self(token)
+    }
+}
+
+impl AsRef<str> for PasswordResetToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PasswordResetToken;
+
+    #[test]
+    fn non_valid_token_is_rejected() {
+        let token = "not-a-reset-token".to_string();
+        assert!(PasswordResetToken::parse(token).is_err());
+    }
+
+    #[test]
+    fn valid_auto_generated_token_is_accepted() {
+        let token = PasswordResetToken::default();
+        assert!(PasswordResetToken::parse(token.as_ref().to_string()).is_ok());
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use lazy_static::lazy_static;
+
+// Strength estimation in the spirit of zxcvbn. The password is split into the cheapest sequence of guessable
+// patterns (common passwords, user data, repeats, sequences, keyboard runs and years), characters that are
+// part of no pattern cost 10 guesses each, and the total number of guesses gives a score from 0 to 4.
+
+// Most common first, the rank is the number of guesses an attacker needs to reach the word
+const COMMON_PASSWORDS: &[&str] = &[
+    "password", "qwerty", "iloveyou", "admin", "welcome", "monkey", "login", "abc", "starwars", "dragon",
+    "passw", "master", "hello", "freedom", "whatever", "qazwsx", "trustno", "letmein", "football", "baseball",
+    "sunshine", "princess", "shadow", "superman", "michael", "charlie", "batman", "secret", "summer", "winter",
+    "spring", "autumn", "computer", "internet", "love", "test", "guest", "root", "changeme", "default",
+    "access", "flower", "hunter", "ranger", "soccer", "hockey", "jordan", "harley", "thomas", "jessica",
+    "ashley", "daniel", "pepper", "ginger", "cookie", "chocolate", "cheese", "purple", "orange", "banana",
+    "apple", "tigger", "buster", "maggie", "killer", "nicole", "hannah", "matrix", "mustang", "ferrari",
+    "corvette", "yankees", "cowboys", "eagles", "lakers", "liverpool", "arsenal", "chelsea", "barcelona", "pokemon",
+    "naruto", "minecraft", "fortnite", "pass", "passwd", "zaq", "asdf", "zxcv", "qwer", "zxcvbnm",
+    "asdfgh", "asdfghjkl", "qwertyuiop", "baby", "angel", "lovely", "family", "friend", "friends", "forever",
+    "money", "silver", "golden", "diamond", "blue", "black", "red", "green", "superstar", "rockstar",
+    "dolphin", "william", "robert", "andrew", "joshua", "hottie", "cool", "god", "jesus", "heaven",
+    "samsung", "google", "amazon", "yahoo", "facebook", "linkedin", "adobe", "dropbox", "office", "windows",
+    "server", "system", "user", "temp", "demo", "sample", "private", "public", "security", "company",
+];
+
+// Rows of the keyboard, runs along them are as easy to type as they are to guess
+const KEYBOARD_ROWS: &[&str] = &["`1234567890-=", "qwertyuiop[]", "asdfghjkl;'", "zxcvbnm,./", "qazwsxedcrfvtgbyhnujmikolp"];
+
+// Each match costs at least this many guesses, so splitting a password into tiny patterns does not pay off
+const MIN_MATCH_GUESSES: f64 = 50.0;
+const BRUTE_FORCE_CARDINALITY: f64 = 10.0;
+
+lazy_static! {
+    static ref COMMON_PASSWORD_RANKS: HashMap<&'static str, usize> = COMMON_PASSWORDS
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(rank, word)| (*word, rank + 1))
+        .collect();
+}
+
+// 0 is too guessable, 1 very guessable, 2 somewhat guessable, 3 safely unguessable and 4 very unguessable.
+// User inputs, like the email address, count as known to the attacker.
+pub fn password_strength(password: &str, user_inputs: &[&str]) -> u8 {
+    let log10_guesses = estimate_log10_guesses(&password.chars().collect::<Vec<_>>(), user_inputs);
+
+    match log10_guesses {
+        guesses if guesses < 3.0 => 0,
+        guesses if guesses < 6.0 => 1,
+        guesses if guesses < 8.0 => 2,
+        guesses if guesses < 10.0 => 3,
+        _ => 4,
+    }
+}
+
+struct Match {
+    start: usize,
+    end: usize,
+    log10_guesses: f64,
+}
+
+// Finds the cheapest way to cover the password with patterns and brute forced characters
+fn estimate_log10_guesses(password: &[char], user_inputs: &[&str]) -> f64 {
+    let matches = find_matches(password, user_inputs);
+
+    let mut cheapest = vec![0.0; password.len() + 1];
+    for end in 1..=password.len() {
+        cheapest[end] = cheapest[end - 1] + BRUTE_FORCE_CARDINALITY.log10();
+        for candidate in matches.iter().filter(|candidate| candidate.end == end) {
+            cheapest[end] = cheapest[end].min(cheapest[candidate.start] + candidate.log10_guesses);
+        }
+    }
+
+    cheapest[password.len()]
+}
+
+fn find_matches(password: &[char], user_inputs: &[&str]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let mut add = |start: usize, end: usize, guesses: f64| {
+        matches.push(Match {
+            start,
+            end,
+            log10_guesses: guesses.max(MIN_MATCH_GUESSES).log10(),
+        })
+    };
+
+    let user_words: HashMap<String, usize> = user_inputs
+        .iter()
+        .flat_map(|input| {
+            let input = input.to_lowercase();
+            // Whole inputs and the parts of email addresses as well as the words in them
+            let mut words: Vec<String> = input
+                .split(|c: char| !c.is_alphanumeric())
+                .chain(input.split('@'))
+                .map(str::to_owned)
+                .collect();
+            words.push(input);
+            words
+        })
+        .filter(|word| word.chars().count() >= 3)
+        .enumerate()
+        .map(|(rank, word)| (word, rank + 1))
+        .collect();
+
+    for start in 0..password.len() {
+        for end in start + 2..=password.len() {
+            let token = &password[start..end];
+
+            if let Some(guesses) = dictionary_guesses(token, &user_words) {
+                add(start, end, guesses);
+            }
+            if let Some(guesses) = repeat_guesses(token, user_inputs) {
+                add(start, end, guesses);
+            }
+            if let Some(guesses) = sequence_guesses(token) {
+                add(start, end, guesses);
+            }
+            if let Some(guesses) = keyboard_guesses(token) {
+                add(start, end, guesses);
+            }
+            if let Some(guesses) = year_guesses(token) {
+                add(start, end, guesses);
+            }
+        }
+    }
+
+    matches
+}
+
+// Common passwords and user data, also reversed, capitalized or with l33t substitutions
+fn dictionary_guesses(token: &[char], user_words: &HashMap<String, usize>) -> Option<f64> {
+    if token.len() < 3 {
+        return None;
+    }
+
+    let lowercase: String = token.iter().flat_map(|c| c.to_lowercase()).collect();
+    let rank_of = |word: &str| {
+        user_words
+            .get(word)
+            .copied()
+            .or_else(|| COMMON_PASSWORD_RANKS.get(word).copied())
+    };
+
+    // Digits and symbols are only read as l33t when the token is not known as it is
+    let (unleeted, substitutions) = unleet(&lowercase);
+    [(lowercase, 0), (unleeted, substitutions)]
+        .into_iter()
+        .find_map(|(word, substitutions)| {
+            let reversed: String = word.chars().rev().collect();
+            let (rank, reversed_factor) = match (rank_of(&word), rank_of(&reversed)) {
+                (Some(rank), _) => (rank, 1.0),
+                (None, Some(rank)) => (rank, 2.0),
+                (None, None) => return None,
+            };
+            Some(rank as f64 * reversed_factor * 2f64.powi(substitutions as i32))
+        })
+        .map(|guesses| guesses * uppercase_variations(token))
+}
+
+fn unleet(word: &str) -> (String, usize) {
+    let mut substitutions = 0;
+    let word = word
+        .chars()
+        .map(|c| {
+            let plain = match c {
+                '4' | '@' => 'a',
+                '8' => 'b',
+                '(' => 'c',
+                '3' => 'e',
+                '6' | '9' => 'g',
+                '1' | '!' | '|' => 'i',
+                '0' => 'o',
+                '$' | '5' => 's',
+                '7' | '+' => 't',
+                '2' => 'z',
+                c => c,
+            };
+            if plain != c {
+                substitutions += 1;
+            }
+            plain
+        })
+        .collect();
+
+    (word, substitutions)
+}
+
+// Capitalizing the first letter or everything is the first thing an attacker tries
+fn uppercase_variations(token: &[char]) -> f64 {
+    let uppercase = token.iter().filter(|c| c.is_uppercase()).count();
+    let lowercase = token.iter().filter(|c| c.is_lowercase()).count();
+
+    if uppercase == 0 {
+        1.0
+    } else if lowercase == 0 || (uppercase == 1 && token[0].is_uppercase()) {
+        2.0
+    } else {
+        2f64.powi(uppercase.min(lowercase) as i32 + 1)
+    }
+}
+
+// A block repeated several times, like aaaa or abcabc, costs the guesses of the block times the repeats
+fn repeat_guesses(token: &[char], user_inputs: &[&str]) -> Option<f64> {
+    (1..=token.len() / 2)
+        .filter(|block_len| token.len().is_multiple_of(*block_len))
+        .find(|block_len| token.chunks(*block_len).all(|block| block == &token[..*block_len]))
+        .map(|block_len| {
+            let block_guesses = 10f64.powf(estimate_log10_guesses(&token[..block_len], user_inputs));
+            block_guesses * (token.len() / block_len) as f64
+        })
+}
+
+// Runs like abcd, 9876 or acegi
+fn sequence_guesses(token: &[char]) -> Option<f64> {
+    if token.len() < 3 {
+        return None;
+    }
+
+    let delta = token[1] as i64 - token[0] as i64;
+    let same_class = |c: &char| {
+        c.is_ascii_digit() == token[0].is_ascii_digit()
+            && c.is_ascii_lowercase() == token[0].is_ascii_lowercase()
+            && c.is_ascii_uppercase() == token[0].is_ascii_uppercase()
+    };
+    if delta == 0
+        || delta.abs() > 5
+        || !token.iter().all(same_class)
+        || token.windows(2).any(|pair| pair[1] as i64 - pair[0] as i64 != delta)
+    {
+        return None;
+    }
+
+    let base = match token[0] {
+        'a' | 'A' | 'z' | 'Z' | '0' | '1' | '9' => 4.0,
+        c if c.is_ascii_digit() => 10.0,
+        _ => 26.0,
+    };
+    let direction = if delta < 0 { 2.0 } else { 1.0 };
+
+    Some(base * direction * delta.abs() as f64 * token.len() as f64)
+}
+
+// Runs along a row of the keyboard, like qwerty or lkjh
+fn keyboard_guesses(token: &[char]) -> Option<f64> {
+    if token.len() < 4 {
+        return None;
+    }
+
+    let run: String = token.iter().flat_map(|c| c.to_lowercase()).collect();
+    let reversed: String = run.chars().rev().collect();
+    KEYBOARD_ROWS
+        .iter()
+        .any(|row| row.contains(&run) || row.contains(&reversed))
+        .then_some(20.0 * token.len() as f64)
+}
+
+// Recent years are guessed before anything else made of four digits
+fn year_guesses(token: &[char]) -> Option<f64> {
+    let year: String = token.iter().collect();
+    match year.parse::<u32>() {
+        Ok(1900..=2099) if token.len() == 4 => Some(120.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_and_patterned_passwords_are_weak() {
+        for password in [
+            "password", "password123", "P@ssw0rd", "Password1!", "qwertyuiop", "123456789", "abcdefgh",
+            "aaaaaaaaaa", "abcabcabcabc", "drowssap", "letmein2024", "zxcvbnm123", "iloveyou",
+        ] {
+            assert!(password_strength(password, &[]) <= 1, "Failed for input: {}", password);
+        }
+    }
+
+    #[test]
+    fn user_inputs_are_known_to_the_attacker() {
+        let email = "jane.marlowe@example.com";
+        assert!(password_strength("Marlowe1990", &[]) >= 2);
+        assert!(password_strength("Marlowe1990", &[email]) <= 1);
+        assert!(password_strength("JANE.MARLOWE", &[email]) <= 1);
+        // Digits in user inputs are taken as they are, not as l33t
+        assert!(password_strength("x7k2p9q4w8z3", &["x7k2p9q4w8z3@example.com"]) <= 1);
+    }
+
+    #[test]
+    fn random_passwords_and_passphrases_are_strong() {
+        for password in ["Glacier-Tandem-47-Orbit", "k8#Tq2!vZr9m", "mauve otter juggles brass lanterns"] {
+            assert_eq!(password_strength(password, &[]), 4, "Failed for input: {}", password);
+        }
+    }
+
+    #[test]
+    fn strength_grows_with_unpatterned_characters() {
+        assert!(password_strength("kx9q", &[]) < password_strength("kx9qvm27", &[]));
+        assert!(password_strength("kx9qvm27", &[]) <= password_strength("kx9qvm27tj4w", &[]));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use color_eyre::eyre::{eyre, Context, Result};
+
+// A token bucket holding up to `capacity` requests, refilled completely over `period_seconds`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period_seconds: u64,
+}
+
+// State of a bucket, tokens are fractional as they refill continuously
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub updated_at_ms: i64,
+}
+
+impl RateLimit {
+    pub fn new(capacity: u32, period_seconds: u64) -> Result<Self> {
+        if capacity == 0 || period_seconds == 0 {
+            return Err(eyre!("Rate limit capacity and period must be positive"));
+        }
+        Ok(Self {
+            capacity,
+            period_seconds,
+        })
+    }
+
+    // Parses `<capacity>/<period in seconds>`, ex: 10/60
+    pub fn parse(limit: &str) -> Result<Self> {
+        let (capacity, period_seconds) = limit
+            .split_once('/')
+            .ok_or_else(|| eyre!("Invalid rate limit: {}", limit))?;
+
+        Self::new(
+            capacity.trim().parse().wrap_err("Invalid rate limit capacity")?,
+            period_seconds.trim().parse().wrap_err("Invalid rate limit period")?,
+        )
+    }
+
+    pub fn period_ms(&self) -> i64 {
+        self.period_seconds as i64 * 1000
+    }
+
+    // Refills the bucket for the time passed since it was last used and takes a token from it.
+    // Returns the new state and, if the bucket was empty, the seconds until the next token.
+    pub fn take_token(&self, bucket: Option<TokenBucket>, now_ms: i64) -> (TokenBucket, Option<u64>) {
+        let capacity = self.capacity as f64;
+        let period_ms = self.period_ms() as f64;
+
+        let tokens = match bucket {
+            Some(bucket) => {
+                let elapsed_ms = (now_ms - bucket.updated_at_ms).max(0) as f64;
+                (bucket.tokens + elapsed_ms * capacity / period_ms).min(capacity)
+            }
+            None => capacity,
+        };
+
+        let (tokens, retry_after) = if tokens >= 1.0 {
+            (tokens - 1.0, None)
+        } else {
+            let wait_ms = (1.0 - tokens) * period_ms / capacity;
+            (tokens, Some(((wait_ms / 1000.0).ceil() as u64).max(1)))
+        };
+
+        let bucket = TokenBucket {
+            tokens,
+            updated_at_ms: now_ms,
+        };
+        (bucket, retry_after)
+    }
+}
+
+// Limits per route, keyed by the route pattern as it is registered with the router, ex: /sessions/:session_id
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimits {
+    // A route mapped to none is not limited at all
+    routes: HashMap<String, Option<RateLimit>>,
+    // Applies to every route without a limit of its own, none leaves them unlimited
+    default: Option<RateLimit>,
+}
+
+impl RateLimits {
+    pub fn unlimited() -> Self {
+        Self {
+            routes: HashMap::new(),
+            default: None,
+        }
+    }
+
+    pub fn with_route(mut self, route: &str, limit: RateLimit) -> Self {
+        self.routes.insert(route.to_owned(), Some(limit));
+        self
+    }
+
+    pub fn with_default(mut self, limit: Option<RateLimit>) -> Self {
+        self.default = limit;
+        self
+    }
+
+    pub fn get(&self, route: &str) -> Option<RateLimit> {
+        match self.routes.get(route) {
+            Some(limit) => *limit,
+            None => self.default,
+        }
+    }
+
+    // Parses comma separated `<route>=<limit>` pairs on top of the defaults, `*` replaces the default limit
+    // and `off` lifts a limit, ex: /login=5/60,/signup=off,*=300/60
+    pub fn parse(config: &str) -> Result<Self> {
+        let mut limits = Self::default();
+
+        for pair in config.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (route, limit) = pair
+                .split_once('=')
+                .ok_or_else(|| eyre!("Invalid rate limit setting: {}", pair))?;
+            let limit = match limit.trim() {
+                "off" => None,
+                limit => Some(RateLimit::parse(limit)?),
+            };
+
+            match route.trim() {
+                "*" => limits.default = limit,
+                route if route.starts_with('/') => {
+                    limits.routes.insert(route.to_owned(), limit);
+                }
+                route => return Err(eyre!("Invalid rate limited route: {}", route)),
+            }
+        }
+
+        Ok(limits)
+    }
+}
+
+// Routes that send emails or check credentials get tight limits, the rest only a generous one against floods
+impl Default for RateLimits {
+    fn default() -> Self {
+        let limit = |capacity, period_seconds| RateLimit {
+            capacity,
+            period_seconds,
+        };
+
+        Self::unlimited()
+            .with_default(Some(limit(300, 60)))
+            .with_route("/signup", limit(10, 60))
+            .with_route("/login", limit(30, 60))
+            .with_route("/login/magic", limit(10, 60))
+            .with_route("/login/magic/verify", limit(30, 60))
+            .with_route("/verify-2fa", limit(30, 60))
+            .with_route("/2fa/code", limit(10, 60))
+            .with_route("/forgot-password", limit(10, 60))
+            .with_route("/reset-password", limit(30, 60))
+            .with_route("/restore-account", limit(30, 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limit() {
+        assert_eq!(RateLimit::parse("10/60").unwrap(), RateLimit::new(10, 60).unwrap());
+        assert_eq!(RateLimit::parse(" 5 / 1 ").unwrap(), RateLimit::new(5, 1).unwrap());
+
+        for limit in ["", "10", "10/", "/60", "0/60", "10/0", "-1/60", "ten/60"] {
+            assert!(RateLimit::parse(limit).is_err(), "Failed for input: {}", limit);
+        }
+    }
+
+    #[test]
+    fn test_take_token() {
+        let limit = RateLimit::new(2, 60).unwrap();
+
+        // A new bucket starts full
+        let (bucket, retry_after) = limit.take_token(None, 0);
+        assert_eq!(retry_after, None);
+        let (bucket, retry_after) = limit.take_token(Some(bucket), 0);
+        assert_eq!(retry_after, None);
+
+        // One token refills every 30 seconds
+        let (bucket, retry_after) = limit.take_token(Some(bucket), 15_000);
+        assert_eq!(retry_after, Some(15));
+        let (bucket, retry_after) = limit.take_token(Some(bucket), 31_000);
+        assert_eq!(retry_after, None);
+
+        // The bucket never holds more than its capacity
+        let (bucket, _) = limit.take_token(Some(bucket), 3_600_000);
+        assert_eq!(bucket.tokens, 1.0);
+    }
+
+    #[test]
+    fn test_parse_rate_limits() {
+        let limits = RateLimits::parse("/login=5/60, /signup=off, *=1000/60,/sessions/:session_id=3/10").unwrap();
+
+        assert_eq!(limits.get("/login"), Some(RateLimit::new(5, 60).unwrap()));
+        assert_eq!(limits.get("/sessions/:session_id"), Some(RateLimit::new(3, 10).unwrap()));
+        assert_eq!(limits.get("/signup"), None);
+        // Routes without a limit of their own fall back to the default
+        assert_eq!(limits.get("/.well-known/jwks.json"), Some(RateLimit::new(1000, 60).unwrap()));
+        assert_eq!(limits.get("/verify-2fa"), RateLimits::default().get("/verify-2fa"));
+
+        assert_eq!(RateLimits::parse("").unwrap(), RateLimits::default());
+        assert_eq!(RateLimits::parse("*=off").unwrap().get("/jwks"), None);
+
+        for config in ["/login", "login=5/60", "/login=5"] {
+            assert!(RateLimits::parse(config).is_err(), "Failed for input: {}", config);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use rand::prelude::*;
+use ring::digest::{digest, SHA256};
+use color_eyre::eyre::{eyre, Result};
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_GROUP_LENGTH: usize = 5;
+// Leaves out characters that are easily mistaken for each other when written down (0/o, 1/l/i)
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+// A one-time backup code for 2FA users, formatted as two groups of five characters (ex: 4kq7m-x2vwp)
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct RecoveryCode(String);
+
+impl RecoveryCode {
+    pub fn parse(code: String) -> Result<Self> {
+        // Users type these in from a printout, so case and the separator are not significant
+        let normalized: String = code
+            .trim()
+            .chars()
+            .filter(|c| *c != '-' && *c != ' ')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        if normalized.len() == 2 * RECOVERY_CODE_GROUP_LENGTH
+            && normalized.bytes().all(|c| RECOVERY_CODE_ALPHABET.contains(&c))
+        {
+            let (first, second) = normalized.split_at(RECOVERY_CODE_GROUP_LENGTH);
+            Ok(Self(format!("{}-{}", first, second)))
+        } else {
+            Err(eyre!("Invalid recovery code"))
+        }
+    }
+
+    pub fn generate_set() -> Vec<Self> {
+        (0..RECOVERY_CODE_COUNT).map(|_| Self::default()).collect()
+    }
+
+    // Only this hash is ever stored, the codes themselves are shown to the user once
+    pub fn hash(&self) -> String {
+        digest(&SHA256, self.0.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let mut group = || -> String {
+            (0..RECOVERY_CODE_GROUP_LENGTH)
+                .map(|_| char::from(*RECOVERY_CODE_ALPHABET.choose(&mut rng).expect("alphabet is not empty")))
+                .collect()
+        };
+        Self(format!("{}-{}", group(), group()))
+    }
+}
+
+impl AsRef<str> for RecoveryCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_valid_code_is_rejected() {
+        assert!(RecoveryCode::parse("123456".to_string()).is_err());
+        assert!(RecoveryCode::parse("abcde-fghi".to_string()).is_err());
+        // 0, 1, i, l and o are never generated
+        assert!(RecoveryCode::parse("abcde-fgh10".to_string()).is_err());
+    }
+
+    #[test]
+    fn valid_auto_generated_code_is_accepted() {
+        let code = RecoveryCode::default();
+        assert_eq!(RecoveryCode::parse(code.as_ref().to_string()).unwrap(), code);
+    }
+
+    #[test]
+    fn code_is_normalized() {
+        let code = RecoveryCode::parse(" ABCDE fghjk ".to_string()).unwrap();
+        assert_eq!(code.as_ref(), "abcde-fghjk");
+        assert_eq!(RecoveryCode::parse("abcdefghjk".to_string()).unwrap(), code);
+    }
+
+    #[test]
+    fn generated_set_has_distinct_hashes() {
+        let codes = RecoveryCode::generate_set();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        let hashes: std::collections::HashSet<String> = codes.iter().map(RecoveryCode::hash).collect();
+        assert_eq!(hashes.len(), RECOVERY_CODE_COUNT);
+        assert!(hashes.iter().all(|hash| hash.len() == 64));
+    }
+}
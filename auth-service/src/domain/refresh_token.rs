@@ -0,0 +1,67 @@
+use rand::{distributions::Alphanumeric, prelude::*};
+use color_eyre::eyre::{eyre, Result};
+
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct RefreshToken(String);
+
+impl RefreshToken {
+    pub fn parse(token: String) -> Result<Self> {
+        if token.len() == REFRESH_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid refresh token"))
+        }
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        // Refresh tokens are opaque random strings, they are only meaningful to the refresh token store
+        let token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(REFRESH_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        Self(token)
+    }
+}
+
+impl AsRef<str> for RefreshToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RefreshToken;
+
+    #[test]
+    fn empty_token_is_rejected() {
+        assert!(RefreshToken::parse("".to_string()).is_err());
+    }
+
+    #[test]
+    fn short_token_is_rejected() {
+        assert!(RefreshToken::parse("abc123".to_string()).is_err());
+    }
+
+    #[test]
+    fn token_with_invalid_characters_is_rejected() {
+        let token = format!("{}!", "a".repeat(63));
+        assert!(RefreshToken::parse(token).is_err());
+    }
+
+    #[test]
+    fn auto_generated_token_is_accepted() {
+        let token = RefreshToken::default();
+        assert!(RefreshToken::parse(token.as_ref().to_string()).is_ok());
+    }
+
+    #[test]
+    fn auto_generated_tokens_are_unique() {
+        assert_ne!(RefreshToken::default(), RefreshToken::default());
+    }
+}
@@ -0,0 +1,48 @@
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+// Every user has the `User` role, staff accounts get `Admin` on top of it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Admin,
+}
+
+impl Role {
+    pub fn parse(role: &str) -> Result<Self> {
+        match role {
+            "user" => Ok(Self::User),
+            "admin" => Ok(Self::Admin),
+            _ => Err(eyre!("Invalid role: {}", role)),
+        }
+    }
+}
+
+impl AsRef<str> for Role {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::User => "user",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Role;
+
+    #[test]
+    fn roles_round_trip() {
+        for role in [Role::User, Role::Admin] {
+            assert_eq!(Role::parse(role.as_ref()).unwrap(), role);
+            assert_eq!(serde_json::to_string(&role).unwrap(), format!("\"{}\"", role.as_ref()));
+        }
+    }
+
+    #[test]
+    fn unknown_role_is_rejected() {
+        assert!(Role::parse("superuser").is_err());
+        assert!(Role::parse("Admin").is_err());
+    }
+}
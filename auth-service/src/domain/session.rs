@@ -0,0 +1,28 @@
+use std::net::IpAddr;
+use chrono::Utc;
+
+use super::Email;
+
+// A logged in device. Every JWT names its session in the sid claim and the session id doubles as the
+// id of the refresh token family, so revoking the session ends both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub email: Email,
+    // Unix timestamp of the login
+    pub created_at: i64,
+    pub ip: String,
+    pub user_agent: Option<String>,
+}
+
+impl Session {
+    pub fn new(email: Email, ip: IpAddr, user_agent: Option<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            email,
+            created_at: Utc::now().timestamp(),
+            ip: ip.to_string(),
+            user_agent,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use rand::prelude::*;
+use color_eyre::eyre::{eyre, Result};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use super::{Email, TwoFACode};
+
+// RFC 6238 defaults, these are the only parameters every authenticator app supports
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+// Number of time steps before and after the current one that are still accepted
+const TOTP_SKEW_STEPS: u64 = 1;
+const TOTP_SECRET_BYTES: usize = 20;
+
+// Base32 encoded shared secret of an authenticator app
+#[derive(Clone, Debug, PartialEq)]
+pub struct TotpSecret(String);
+
+impl TotpSecret {
+    pub fn parse(secret: String) -> Result<Self> {
+        match Secret::Encoded(secret.clone()).to_bytes() {
+            Ok(bytes) if bytes.len() >= 16 => Ok(Self(secret)),
+            _ => Err(eyre!("Invalid TOTP secret")),
+        }
+    }
+
+    // otpauth:// URI that authenticator apps import, usually rendered as a QR code
+    pub fn otpauth_uri(&self, issuer: &str, email: &Email) -> Result<String> {
+        Ok(self.totp(Some(issuer.to_owned()), email.as_ref().to_owned())?.get_url())
+    }
+
+    // Returns the time step the code belongs to, so callers can reject codes that were already used
+    pub fn verify(&self, code: &TwoFACode, now: u64) -> Result<Option<u64>> {
+        let totp = self.totp(None, String::new())?;
+        let current_step = now / TOTP_STEP_SECONDS;
+
+        let step = (current_step.saturating_sub(TOTP_SKEW_STEPS)..=current_step + TOTP_SKEW_STEPS)
+            .find(|step| totp.check(code.as_ref(), step * TOTP_STEP_SECONDS));
+
+        Ok(step)
+    }
+
+    fn totp(&self, issuer: Option<String>, account_name: String) -> Result<TOTP> {
+        let secret = Secret::Encoded(self.0.clone())
+            .to_bytes()
+            .map_err(|e| eyre!("Invalid TOTP secret: {:?}", e))?;
+
+        // Skew is handled in verify, every check below is against one exact time step
+        TOTP::new(Algorithm::SHA1, TOTP_DIGITS, 0, TOTP_STEP_SECONDS, secret, issuer, account_name)
+            .map_err(|e| eyre!("Failed to create TOTP: {}", e))
+    }
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        let mut bytes = [0u8; TOTP_SECRET_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        match Secret::Raw(bytes.to_vec()).to_encoded() {
+            Secret::Encoded(secret) => Self(secret),
+            Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+        }
+    }
+}
+
+impl AsRef<str> for TotpSecret {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_code(secret: &TotpSecret, time: u64) -> TwoFACode {
+        let totp = secret.totp(None, String::new()).unwrap();
+        TwoFACode::parse(totp.generate(time)).unwrap()
+    }
+
+    #[test]
+    fn invalid_secret_is_rejected() {
+        assert!(TotpSecret::parse("not base32!".to_string()).is_err());
+        assert!(TotpSecret::parse("GEZDGNBV".to_string()).is_err());
+    }
+
+    #[test]
+    fn auto_generated_secret_is_accepted() {
+        let secret = TotpSecret::default();
+        assert!(TotpSecret::parse(secret.as_ref().to_string()).is_ok());
+        assert_ne!(secret, TotpSecret::default());
+    }
+
+    #[test]
+    fn otpauth_uri_contains_secret_and_issuer() {
+        let secret = TotpSecret::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let uri = secret.otpauth_uri("AuthService", &email).unwrap();
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret.as_ref())));
+        assert!(uri.contains("issuer=AuthService"));
+    }
+
+    #[test]
+    fn code_within_skew_window_is_accepted() {
+        let secret = TotpSecret::default();
+        let now = 1_700_000_000;
+        let step = now / TOTP_STEP_SECONDS;
+
+        let code = generate_code(&secret, now);
+        assert_eq!(secret.verify(&code, now).unwrap(), Some(step));
+
+        let code = generate_code(&secret, now - TOTP_STEP_SECONDS);
+        assert_eq!(secret.verify(&code, now).unwrap(), Some(step - 1));
+
+        let code = generate_code(&secret, now + TOTP_STEP_SECONDS);
+        assert_eq!(secret.verify(&code, now).unwrap(), Some(step + 1));
+    }
+
+    #[test]
+    fn code_outside_skew_window_is_rejected() {
+        let secret = TotpSecret::default();
+        let now = 1_700_000_000;
+
+        let code = generate_code(&secret, now - 5 * TOTP_STEP_SECONDS);
+        assert_eq!(secret.verify(&code, now).unwrap(), None);
+    }
+}
@@ -1,14 +1,13 @@
 use rand::prelude::*;
-use color_eyre::eyre::{eyre, Context, Result};
+use color_eyre::eyre::{eyre, Result};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct TwoFACode(String);
 
 impl TwoFACode {
     pub fn parse(code: String) -> Result<Self> { // Updated!
-        let code_as_u32 = code.parse::<u32>().wrap_err("Invalid 2FA code")?; // Updated!
-
-        if (100_000..=999_999).contains(&code_as_u32) {
+        // Codes from authenticator apps may start with a zero, so any 6 digits are accepted
+        if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
             Ok(Self(code))
         } else {
             Err(eyre!("Invalid 2FA code")) // Updated!
@@ -60,6 +59,12 @@ mod tests {
         assert!(TwoFACode::parse(two_fa_code).is_ok());
     }
 
+    #[test]
+    fn code_with_leading_zero_is_accepted() {
+        let two_fa_code: String = "012345".to_string();
+        assert!(TwoFACode::parse(two_fa_code).is_ok());
+    }
+
     #[test]
     fn valid_auto_generated_code_is_accepted() {
         let two_fa_code= TwoFACode::default();
@@ -1,21 +1,27 @@
-use super::{Email, Password};
+use super::{Email, Role};
 
-// The User struct should contain 3 fields. email, which is a String; 
-// password, which is also a String; and requires_2fa, which is a boolean. 
+// verified is set once the user confirmed the email address.
+// roles are embedded in the user's JWTs and decide which endpoints the user may call.
+// disabled users are blocked by an admin and can not log in.
+// The password is not part of the user, stores keep it as a hash next to it.
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
     pub email: Email,
-    pub password: Password,
     pub requires_2fa: bool,
+    pub verified: bool,
+    pub roles: Vec<Role>,
+    pub disabled: bool,
 }
 
 impl User {
     // add a constructor function called `new`
-    pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
+    pub fn new(email: Email, requires_2fa: bool) -> Self {
         Self {
             email,
-            password,
-            requires_2fa
+            requires_2fa,
+            verified: false,
+            roles: vec![Role::User],
+            disabled: false,
         }
     }
-}
\ No newline at end of file
+}
@@ -1,18 +1,23 @@
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post},
     serve::Serve,
     Json, Router,
 };
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
 use app_state::AppState;
-use domain::AuthAPIError;
+use domain::{AuthAPIError, OidcError};
 use serde::{Deserialize, Serialize};
 use redis::{Client, RedisResult};
-use utils::tracing::{make_span_with_request_id, on_request, on_response};
+use utils::{
+    rate_limit::rate_limit,
+    tracing::{make_span_with_request_id, on_request, on_response},
+};
 
 pub mod routes;
 pub mod domain;
@@ -22,7 +27,8 @@ pub mod utils;
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<Router, Router>,
+    // Serving with connect info makes the client address available to the routes
+    server: Serve<IntoMakeServiceWithConnectInfo<Router, SocketAddr>, AddExtension<Router, ConnectInfo<SocketAddr>>>,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
@@ -38,8 +44,8 @@ impl Application {
         ];
   
         let cors = CorsLayer::new()
-        // Allow GET and POST requests
-        .allow_methods([Method::GET, Method::POST])
+        // Allow GET, POST and DELETE requests
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
         // Allow cookies to be included in requests
         .allow_credentials(true)
         .allow_origin(allowed_origins);
@@ -49,9 +55,41 @@ impl Application {
             .nest_service("/", ServeDir::new("assets"))
             .route("/signup", post(routes::signup))
             .route("/login", post(routes::login))
+            .route("/login/magic", post(routes::request_magic_login))
+            .route("/login/magic/verify", post(routes::verify_magic_login))
+            .route("/login/federated", get(routes::start_federated_login))
+            .route("/login/federated/callback", get(routes::federated_login_callback))
             .route("/logout", post(routes::logout))
+            .route("/logout-all", post(routes::logout_all))
             .route("/verify-2fa", post(routes::verify_2fa))
+            .route("/2fa/code", post(routes::send_two_fa_code))
+            .route("/2fa/enable", post(routes::enable_2fa))
+            .route("/2fa/disable", post(routes::disable_2fa))
             .route("/verify-token", post(routes::verify_token))
+            .route("/introspect", post(routes::introspect))
+            .route("/refresh", post(routes::refresh))
+            .route("/forgot-password", post(routes::forgot_password))
+            .route("/reset-password", post(routes::reset_password))
+            .route("/change-password", post(routes::change_password))
+            .route("/delete-account", post(routes::delete_account))
+            .route("/restore-account", post(routes::restore_account))
+            .route("/verify-email", get(routes::verify_email))
+            .route("/totp/enroll", post(routes::enroll_totp))
+            .route("/totp/confirm", post(routes::confirm_totp))
+            .route("/recovery-codes", post(routes::regenerate_recovery_codes))
+            .route("/.well-known/jwks.json", get(routes::jwks))
+            .route("/.well-known/openid-configuration", get(routes::openid_configuration))
+            .route("/oidc/clients", post(routes::register_oidc_client))
+            .route("/authorize", get(routes::authorize))
+            .route("/token", post(routes::token))
+            .route("/userinfo", get(routes::userinfo).post(routes::userinfo))
+            .route("/sessions", get(routes::list_sessions))
+            .route("/sessions/:session_id", delete(routes::revoke_session))
+            .route("/api-keys", post(routes::create_api_key).get(routes::list_api_keys))
+            .route("/api-keys/:key_id", delete(routes::revoke_api_key))
+            .nest("/admin", routes::admin_routes())
+            // Limits every route above per client IP, see RateLimits for the configuration
+            .route_layer(middleware::from_fn_with_state(app_state.clone(), rate_limit))
             .with_state(app_state)
             .layer(cors)
             .layer( // New!
@@ -66,7 +104,7 @@ impl Application {
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>());
 
         // Create a new Application instance and return it
         Ok(Application { server, address })
@@ -88,12 +126,28 @@ pub struct ErrorResponse {
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
-        let (status, error_message) = match self {
+        let retry_after = match &self {
+            AuthAPIError::TooManyRequests(seconds) => Some(*seconds),
+            _ => None,
+        };
+        let (status, error_message) = match &self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
             AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, "Incorrect credentials"),
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing token credentials"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "InvalidToken"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::AccountDisabled => (StatusCode::FORBIDDEN, "Account disabled"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::TwoFAAlreadyEnabled => (StatusCode::CONFLICT, "2FA already enabled"),
+            AuthAPIError::TwoFANotEnabled => (StatusCode::CONFLICT, "2FA not enabled"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::ApiKeyNotFound => (StatusCode::NOT_FOUND, "API key not found"),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::FederatedLoginNotConfigured => (StatusCode::NOT_FOUND, "Federated login not configured"),
+            AuthAPIError::PasswordPolicyViolation(message) => (StatusCode::BAD_REQUEST, message.as_str()),
+            AuthAPIError::TooManyRequests(_) => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
@@ -101,13 +155,53 @@ impl IntoResponse for AuthAPIError {
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
         });
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(seconds) = retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
     }
 
   
 }
 
 
+// Error body of the OpenID Connect endpoints, see RFC 6749 section 5.2
+#[derive(Serialize, Deserialize)]
+pub struct OidcErrorResponse {
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+impl IntoResponse for OidcError {
+    fn into_response(self) -> Response {
+        log_error_chain(&self);
+        let error = self.to_string();
+        // Resource servers have to tell the client how to authenticate, see RFC 6750 section 3
+        let bearer_challenge = matches!(self, OidcError::InvalidToken);
+        let (status, error_description) = match self {
+            OidcError::InvalidRequest(description) => (StatusCode::BAD_REQUEST, Some(description)),
+            OidcError::InvalidClient => (StatusCode::UNAUTHORIZED, None),
+            OidcError::InvalidGrant => (StatusCode::BAD_REQUEST, None),
+            OidcError::UnsupportedGrantType => (StatusCode::BAD_REQUEST, None),
+            OidcError::InvalidToken => (StatusCode::UNAUTHORIZED, None),
+            OidcError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, None),
+        };
+        let body = Json(OidcErrorResponse {
+            error,
+            error_description,
+        });
+        let mut response = (status, body).into_response();
+        if bearer_challenge {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Bearer error=\"invalid_token\""),
+            );
+        }
+        response
+    }
+}
 
 pub async fn get_postgres_pool(url: &str) -> Result<PgPool, sqlx::Error> {
     // Create a new PostgreSQL connection pool
@@ -1,18 +1,44 @@
-use std::sync::Arc;
+use std::{env as std_env, sync::Arc, time::Duration};
 use sqlx::PgPool;
 use tokio::sync::RwLock;
 use auth_service::{
-    app_state::AppState, get_postgres_pool, get_redis_client, 
+    app_state::{AppState, IdentityProviderType, UserStoreType},
+    domain::{PasswordPolicy, RateLimits}, get_postgres_pool, get_redis_client, 
     services::{
         //hashmap_two_fa_code_store::HashmapTwoFACodeStore, 
         //hashmap_user_store::HashmapUserStore, 
         //hashset_banned_token_store::HashsetBannedTokenStore, 
+        //hashmap_refresh_token_store::HashmapRefreshTokenStore,
+        //hashmap_password_reset_token_store::HashmapPasswordResetTokenStore,
+        //hashmap_totp_store::HashmapTotpStore,
+        //hashmap_login_throttle_store::HashmapLoginThrottleStore,
+        //hashmap_session_store::HashmapSessionStore,
+        //hashmap_recovery_code_store::HashmapRecoveryCodeStore,
+        //hashmap_oidc_client_store::HashmapOidcClientStore,
+        //hashmap_authorization_code_store::HashmapAuthorizationCodeStore,
+        //hashmap_federated_identity_store::HashmapFederatedIdentityStore,
+        //hashmap_api_key_store::HashmapApiKeyStore,
+        //hashmap_rate_limit_store::HashmapRateLimitStore,
         mock_email_client::MockEmailClient, 
         postgres_user_store::PostgresUserStore, 
+        argon2_password_hasher::Argon2PasswordHasher,
+        postgres_totp_store::PostgresTotpStore,
+        postgres_recovery_code_store::PostgresRecoveryCodeStore,
+        postgres_oidc_client_store::PostgresOidcClientStore,
+        postgres_federated_identity_store::PostgresFederatedIdentityStore,
+        postgres_api_key_store::PostgresApiKeyStore,
+        oidc_identity_provider::OidcIdentityProvider,
+        hibp_range_files::HibpRangeFiles,
         redis_banned_token_store::RedisBannedTokenStore, 
-        redis_two_fa_code_store::RedisTwoFACodeStore
+        redis_two_fa_code_store::RedisTwoFACodeStore,
+        redis_refresh_token_store::RedisRefreshTokenStore,
+        redis_password_reset_token_store::RedisPasswordResetTokenStore,
+        redis_login_throttle_store::RedisLoginThrottleStore,
+        redis_session_store::RedisSessionStore,
+        redis_authorization_code_store::RedisAuthorizationCodeStore,
+        redis_rate_limit_store::RedisRateLimitStore,
     }, 
-    utils::{constants::{prod, DATABASE_URL, REDIS_HOST_NAME}, tracing::init_tracing}, 
+    utils::{constants::{env, prod, DATABASE_URL, REDIS_HOST_NAME}, signing_keys::{reload_key_ring_on_sighup, KEY_RING}, tracing::init_tracing}, 
     Application
     
 };
@@ -23,23 +49,73 @@ async fn main() {
       // Start configuring a `fmt` subscriber
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Failed to initialize tracing"); 
+    // Load the signing key up front so a broken key file fails on startup instead of on the first login
+    lazy_static::initialize(&KEY_RING);
+    tokio::spawn(reload_key_ring_on_sighup());
     
     // We will use this PostgreSQL pool in the next task! 
     let pg_pool = configure_postgresql().await;
     let redis_conn = Arc::new(RwLock::new(configure_redis()));
     
-    //let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+    let password_hasher = configure_password_hasher();
+    //let user_store = Arc::new(RwLock::new(HashmapUserStore::new(password_hasher)));
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone(), password_hasher)));
     
     //let banned_token_store= Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
     let banned_token_store= Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
     
     //let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
-    let two_fa_code_store= Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+    let two_fa_code_store= Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
+    //let magic_login_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+    let magic_login_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::for_magic_login(redis_conn.clone())));
+    //let two_fa_confirmation_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+    let two_fa_confirmation_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::for_2fa_confirmation(redis_conn.clone())));
+
+    //let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+    let refresh_token_store = Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())));
+
+    //let password_reset_token_store = Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default()));
+    let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_conn.clone())));
+
+    //let login_throttle_store = Arc::new(RwLock::new(HashmapLoginThrottleStore::default()));
+    let login_throttle_store = Arc::new(RwLock::new(RedisLoginThrottleStore::new(redis_conn.clone())));
+
+    //let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+    let session_store = Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())));
+
+    //let authorization_code_store = Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default()));
+    let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(redis_conn.clone())));
+
+    // Limits have to hold across replicas, so the buckets live in Redis
+    //let rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
+    let rate_limit_store = Arc::new(RwLock::new(RedisRateLimitStore::new(redis_conn)));
     
+    //let totp_store = Arc::new(RwLock::new(HashmapTotpStore::default()));
+    let totp_store = Arc::new(RwLock::new(PostgresTotpStore::new(pg_pool.clone())));
+
+    //let recovery_code_store = Arc::new(RwLock::new(HashmapRecoveryCodeStore::default()));
+    let recovery_code_store = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+
+    //let oidc_client_store = Arc::new(RwLock::new(HashmapOidcClientStore::default()));
+    let oidc_client_store = Arc::new(RwLock::new(PostgresOidcClientStore::new(pg_pool.clone())));
+
+    //let federated_identity_store = Arc::new(RwLock::new(HashmapFederatedIdentityStore::default()));
+    let federated_identity_store = Arc::new(RwLock::new(PostgresFederatedIdentityStore::new(pg_pool.clone())));
+
+    //let api_key_store = Arc::new(RwLock::new(HashmapApiKeyStore::default()));
+    let api_key_store = Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool)));
+
+    let rate_limits = configure_rate_limits();
+
+    let password_policy = configure_password_policy();
+
+    let identity_provider = configure_identity_provider();
+
     let email_client = Arc::new(MockEmailClient);
+
+    tokio::spawn(purge_deleted_users(user_store.clone()));
     
-    let app_state = AppState::new(user_store,banned_token_store,two_fa_code_store, email_client);
+    let app_state = AppState::new(user_store,banned_token_store,two_fa_code_store, magic_login_code_store, two_fa_confirmation_code_store, email_client, refresh_token_store, password_reset_token_store, totp_store, login_throttle_store, session_store, recovery_code_store, oidc_client_store, authorization_code_store, federated_identity_store, api_key_store, rate_limit_store, rate_limits, password_policy, identity_provider);
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
@@ -47,6 +123,21 @@ async fn main() {
     app.run().await.expect("Failed to run app");
 }
 
+const DELETED_USERS_PURGE_INTERVAL_SECONDS: u64 = 60 * 60;
+
+// Soft deleted accounts are only kept for their grace period
+async fn purge_deleted_users(user_store: UserStoreType) {
+    let mut interval = tokio::time::interval(Duration::from_secs(DELETED_USERS_PURGE_INTERVAL_SECONDS));
+    loop {
+        interval.tick().await;
+        match user_store.write().await.purge_deleted_users().await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Purged {} deleted users", count),
+            Err(e) => tracing::error!("Failed to purge deleted users: {:?}", e),
+        }
+    }
+}
+
 async fn configure_postgresql() -> PgPool {
     // Create a new database connection pool
     let pg_pool = get_postgres_pool(&DATABASE_URL)
@@ -62,6 +153,71 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
+// RATE_LIMITS adjusts the default limits, ex: /login=5/60,*=300/60
+fn configure_rate_limits() -> RateLimits {
+    dotenvy::dotenv().ok();
+    match std_env::var(env::RATE_LIMITS_ENV_VAR) {
+        Ok(config) => RateLimits::parse(&config).expect("RATE_LIMITS must be comma separated <route>=<capacity>/<seconds> pairs."),
+        Err(_) => RateLimits::default(),
+    }
+}
+
+// The policy is tightened or relaxed with PASSWORD_MIN_LENGTH, PASSWORD_MAX_LENGTH and PASSWORD_MIN_STRENGTH,
+// breached passwords are rejected once BREACHED_PASSWORDS_DIR points at an offline copy of the HIBP range files
+fn configure_password_policy() -> PasswordPolicy {
+    dotenvy::dotenv().ok();
+    let mut policy = PasswordPolicy::default();
+
+    let min_length = match std_env::var(env::PASSWORD_MIN_LENGTH_ENV_VAR) {
+        Ok(length) => length.parse().expect("PASSWORD_MIN_LENGTH must be a number."),
+        Err(_) => policy.min_length(),
+    };
+    let max_length = match std_env::var(env::PASSWORD_MAX_LENGTH_ENV_VAR) {
+        Ok(length) => length.parse().expect("PASSWORD_MAX_LENGTH must be a number."),
+        Err(_) => policy.max_length(),
+    };
+    policy = policy
+        .with_length(min_length, max_length)
+        .expect("Invalid PASSWORD_MIN_LENGTH or PASSWORD_MAX_LENGTH");
+
+    if let Ok(min_strength) = std_env::var(env::PASSWORD_MIN_STRENGTH_ENV_VAR) {
+        policy = policy
+            .with_min_strength(min_strength.parse().expect("PASSWORD_MIN_STRENGTH must be a number."))
+            .expect("Invalid PASSWORD_MIN_STRENGTH");
+    }
+
+    if let Ok(dir) = std_env::var(env::BREACHED_PASSWORDS_DIR_ENV_VAR) {
+        let corpus = HibpRangeFiles::open(dir.into())
+            .expect("BREACHED_PASSWORDS_DIR must be a directory of breached password range files.");
+        if !corpus.is_complete() {
+            tracing::warn!("The breached password corpus is partial, passwords without a range file are allowed");
+        }
+        policy = policy.with_breached_passwords(Arc::new(corpus));
+    }
+
+    policy
+}
+
+// PASSWORD_PEPPER adds a server side secret to every password hash. Existing hashes keep working and pick up
+// the pepper with the next login, but once set it must never change or every peppered password stops verifying.
+fn configure_password_hasher() -> Arc<Argon2PasswordHasher> {
+    dotenvy::dotenv().ok();
+    let pepper = std_env::var(env::PASSWORD_PEPPER_ENV_VAR).ok().map(String::into_bytes);
+    Arc::new(Argon2PasswordHasher::new(pepper).expect("PASSWORD_PEPPER must not be empty."))
+}
+
+// Federated login is only offered when an external OpenID Connect provider is configured
+fn configure_identity_provider() -> Option<IdentityProviderType> {
+    dotenvy::dotenv().ok();
+    let issuer = std_env::var(env::FEDERATED_LOGIN_ISSUER_ENV_VAR).ok()?;
+    let client_id = std_env::var(env::FEDERATED_LOGIN_CLIENT_ID_ENV_VAR)
+        .expect("FEDERATED_LOGIN_CLIENT_ID must be set when FEDERATED_LOGIN_ISSUER is set.");
+    let client_secret = std_env::var(env::FEDERATED_LOGIN_CLIENT_SECRET_ENV_VAR)
+        .expect("FEDERATED_LOGIN_CLIENT_SECRET must be set when FEDERATED_LOGIN_ISSUER is set.");
+
+    Some(Arc::new(OidcIdentityProvider::new(issuer, client_id, client_secret)))
+}
+
 fn configure_redis() -> redis::Connection {
     get_redis_client(REDIS_HOST_NAME.to_owned())
         .expect("Failed to get Redis client")
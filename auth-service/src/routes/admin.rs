@@ -0,0 +1,257 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, Role, User, UserStoreError},
+    routes::send_reset_token,
+    utils::{
+        auth::revoke_user_tokens,
+        authorization::{AdminRole, RequireRole},
+    },
+};
+
+const DEFAULT_PAGE_SIZE: u64 = 20;
+const MAX_PAGE_SIZE: u64 = 100;
+
+// User management for support staff. Every handler requires the admin role.
+pub fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/users", get(list_users))
+        .route("/users/:email", get(get_user))
+        .route("/users/:email/disable", post(disable_user))
+        .route("/users/:email/enable", post(enable_user))
+        .route("/users/:email/require-2fa", post(require_2fa))
+        .route("/users/:email/password-reset", post(trigger_password_reset))
+        .route("/users/:email/revoke-sessions", post(revoke_sessions))
+}
+
+#[tracing::instrument(name = "Admin list users", skip_all)]
+pub async fn list_users(
+    RequireRole(_, _): RequireRole<AdminRole>,
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_PAGE_SIZE);
+    if page == 0 || per_page == 0 || per_page > MAX_PAGE_SIZE {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let search = query.search.as_deref().filter(|search| !search.is_empty());
+    let offset = (page - 1).saturating_mul(per_page);
+
+    let (users, total) = state
+        .user_store
+        .read()
+        .await
+        .list_users(search, offset, per_page)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(ListUsersResponse {
+        users: users.into_iter().map(AdminUserResponse::from).collect(),
+        total,
+        page,
+        per_page,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Admin get user", skip_all)]
+pub async fn get_user(
+    RequireRole(_, _): RequireRole<AdminRole>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user = find_user(&email, &state).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+#[tracing::instrument(name = "Admin disable user", skip_all)]
+pub async fn disable_user(
+    RequireRole(claims, _): RequireRole<AdminRole>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_disabled(&email, true)
+        .await
+        .map_err(user_store_error)?;
+
+    // A disabled user is logged out everywhere right away
+    revoke_user_tokens(
+        &email,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    tracing::info!(admin = %claims.sub, user = %email.as_ref(), "disabled user");
+
+    let user = find_user(&email, &state).await?;
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+#[tracing::instrument(name = "Admin enable user", skip_all)]
+pub async fn enable_user(
+    RequireRole(claims, _): RequireRole<AdminRole>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_disabled(&email, false)
+        .await
+        .map_err(user_store_error)?;
+
+    tracing::info!(admin = %claims.sub, user = %email.as_ref(), "enabled user");
+
+    let user = find_user(&email, &state).await?;
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+#[tracing::instrument(name = "Admin require 2FA", skip_all)]
+pub async fn require_2fa(
+    RequireRole(claims, _): RequireRole<AdminRole>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_requires_2fa(&email, true)
+        .await
+        .map_err(user_store_error)?;
+
+    tracing::info!(admin = %claims.sub, user = %email.as_ref(), "forced 2FA");
+
+    let user = find_user(&email, &state).await?;
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+#[tracing::instrument(name = "Admin trigger password reset", skip_all)]
+pub async fn trigger_password_reset(
+    RequireRole(claims, _): RequireRole<AdminRole>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    find_user(&email, &state).await?;
+    send_reset_token(&email, &state).await?;
+
+    tracing::info!(admin = %claims.sub, user = %email.as_ref(), "triggered password reset");
+
+    let response = Json(AdminActionResponse {
+        message: "Password reset email sent".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Admin revoke sessions", skip_all)]
+pub async fn revoke_sessions(
+    RequireRole(claims, _): RequireRole<AdminRole>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    find_user(&email, &state).await?;
+    revoke_user_tokens(
+        &email,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    tracing::info!(admin = %claims.sub, user = %email.as_ref(), "revoked sessions");
+
+    let response = Json(AdminActionResponse {
+        message: "All sessions revoked".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+async fn find_user(email: &Email, state: &AppState) -> Result<User, AuthAPIError> {
+    state.user_store.read().await.get_user(email).await.map_err(user_store_error)
+}
+
+fn user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListUsersQuery {
+    pub search: Option<String>,
+    pub page: Option<u64>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ListUsersResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub total: u64,
+    pub page: u64,
+    #[serde(rename = "perPage")]
+    pub per_page: u64,
+}
+
+// Everything support staff may see about a user, the password hash is never included
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AdminUserResponse {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    pub verified: bool,
+    pub disabled: bool,
+    pub roles: Vec<Role>,
+}
+
+impl From<User> for AdminUserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            email: user.email.as_ref().to_owned(),
+            requires_2fa: user.requires_2fa,
+            verified: user.verified,
+            disabled: user.disabled,
+            roles: user.roles,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AdminActionResponse {
+    pub message: String,
+}
@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{ApiKey, ApiKeyStoreError, AuthAPIError},
+    utils::auth::authenticate,
+};
+
+// API keys can only be managed with a login session, a leaked key can not mint further keys
+#[tracing::instrument(name = "Create API key", skip_all)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    let (api_key, secret) = ApiKey::generate(
+        email,
+        request.name,
+        request.scopes.unwrap_or_default(),
+        request.expires_in_days,
+    )
+    .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if let Err(e) = state.api_key_store.write().await.add_key(api_key.clone()).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let response = Json(CreateApiKeyResponse {
+        key: secret,
+        api_key: ApiKeyResponse::from(api_key),
+    });
+
+    Ok((StatusCode::CREATED, response))
+}
+
+#[tracing::instrument(name = "List API keys", skip_all)]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    let api_keys = state
+        .api_key_store
+        .read()
+        .await
+        .get_user_keys(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(ListApiKeysResponse {
+        api_keys: api_keys.into_iter().map(ApiKeyResponse::from).collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Revoke API key", skip_all)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(key_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    match state.api_key_store.write().await.remove_key(&email, &key_id).await {
+        Ok(()) => {}
+        Err(ApiKeyStoreError::KeyNotFound) => return Err(AuthAPIError::ApiKeyNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(RevokeApiKeyResponse {
+        message: "API key revoked successfully!".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Option<Vec<String>>,
+    // Keys without a lifetime stay valid until they are revoked
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CreateApiKeyResponse {
+    // The only time the key is shown, it can not be recovered later
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ListApiKeysResponse {
+    #[serde(rename = "apiKeys")]
+    pub api_keys: Vec<ApiKeyResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    // RFC 3339 timestamps
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<String>,
+    pub expired: bool,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(api_key: ApiKey) -> Self {
+        let to_rfc3339 = |timestamp: i64| {
+            DateTime::from_timestamp(timestamp, 0)
+                .map(|timestamp| timestamp.to_rfc3339())
+                .unwrap_or_default()
+        };
+
+        Self {
+            expired: api_key.is_expired(),
+            id: api_key.id,
+            name: api_key.name,
+            scopes: api_key.scopes,
+            created_at: to_rfc3339(api_key.created_at),
+            expires_at: api_key.expires_at.map(to_rfc3339),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RevokeApiKeyResponse {
+    pub message: String,
+}
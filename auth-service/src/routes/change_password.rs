@@ -0,0 +1,125 @@
+use std::net::SocketAddr;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Password, Session, UserStoreError},
+    utils::{
+        auth::{authenticate, revoke_user_tokens, start_session, user_agent},
+        login_throttle::{check_login_throttle, clear_login_throttle, record_login_failure},
+    },
+};
+
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let current_password = match Password::parse(request.current_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+    if let Err(e) = state.password_policy.check(&request.new_password, &[email.as_ref()]).await {
+        return (jar, Err(e.into()));
+    }
+    let new_password = match Password::parse(request.new_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let client_ip = addr.ip();
+
+    // A hijacked session must not become a way around the lockout of /login
+    if let Err(e) = check_login_throttle(&email, client_ip, state.login_throttle_store.clone()).await {
+        return (jar, Err(e));
+    }
+
+    let roles = {
+        let mut user_store = state.user_store.write().await;
+
+        match user_store.validate_user(&email, &current_password).await {
+            Ok(()) => {}
+            Err(UserStoreError::InvalidCredentials) | Err(UserStoreError::UserNotFound) => {
+                if let Err(e) = record_login_failure(&email, client_ip, state.login_throttle_store.clone()).await {
+                    return (jar, Err(e));
+                }
+                return (jar, Err(AuthAPIError::IncorrectCredentials));
+            }
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+
+        if let Err(e) = user_store.update_password(&email, new_password).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+
+        match user_store.get_user(&email).await {
+            Ok(user) => user.roles,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+    };
+
+    if let Err(e) = clear_login_throttle(&email, state.login_throttle_store.clone()).await {
+        return (jar, Err(e));
+    }
+
+    // Sessions on other devices may belong to whoever knew the old password
+    if let Err(e) = revoke_user_tokens(
+        &email,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    // The revocation also hit the current session, so the client continues in a new one
+    let session = Session::new(email, addr.ip(), user_agent(&headers));
+    let (auth_cookie, refresh_cookie) = match start_session(
+        session,
+        &roles,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+    let jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password changed successfully!".to_string(),
+    });
+
+    (jar, Ok((StatusCode::OK, response)))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
@@ -0,0 +1,174 @@
+use std::net::SocketAddr;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::{cookie, CookieJar};
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, Password, TwoFACodeStoreError, UserStoreError},
+    utils::{
+        auth::{authenticate, revoke_user_tokens},
+        constants::{ACCOUNT_DELETION_GRACE_PERIOD_SECONDS, JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+        login_throttle::{check_login_throttle, clear_login_throttle, record_login_failure},
+    },
+};
+
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    jar: CookieJar,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let password = match Password::parse(request.password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let client_ip = addr.ip();
+
+    // A stolen session alone is not enough to close the account, nor to guess its password unthrottled
+    if let Err(e) = check_login_throttle(&email, client_ip, state.login_throttle_store.clone()).await {
+        return (jar, Err(e));
+    }
+
+    // The slow password hash is checked under the read lock, so other requests are not held up behind it
+    let validation = state.user_store.read().await.validate_user(&email, &password).await;
+    match validation {
+        Ok(()) => {}
+        Err(UserStoreError::InvalidCredentials) | Err(UserStoreError::UserNotFound) => {
+            if let Err(e) = record_login_failure(&email, client_ip, state.login_throttle_store.clone()).await {
+                return (jar, Err(e));
+            }
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    if let Err(e) = clear_login_throttle(&email, state.login_throttle_store.clone()).await {
+        return (jar, Err(e));
+    }
+
+    {
+        let mut user_store = state.user_store.write().await;
+
+        let grace_period_seconds = *ACCOUNT_DELETION_GRACE_PERIOD_SECONDS;
+        let result = if grace_period_seconds == 0 {
+            user_store.delete_user(&email).await
+        } else {
+            user_store.soft_delete_user(&email, grace_period_seconds).await
+        };
+        if let Err(e) = result {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+    }
+
+    // Pending codes of every kind go with the account
+    for code_store in [
+        &state.two_fa_code_store,
+        &state.magic_login_code_store,
+        &state.two_fa_confirmation_code_store,
+    ] {
+        match code_store.write().await.remove_code(&email).await {
+            Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+    }
+
+    if let Some(cookie) = jar.get(JWT_COOKIE_NAME) {
+        if let Err(e) = state
+            .banned_token_store
+            .write()
+            .await
+            .add_token_to_banned_store(cookie.value().to_owned())
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+    }
+
+    // Sessions on other devices must not outlive the account either
+    if let Err(e) = revoke_user_tokens(
+        &email,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_COOKIE_NAME));
+
+    let response = Json(DeleteAccountResponse {
+        message: "Account deleted successfully!".to_string(),
+    });
+
+    (jar, Ok((StatusCode::OK, response)))
+}
+
+// Brings back a soft deleted account while its grace period lasts, the user logs in again afterwards
+#[tracing::instrument(name = "Restore account", skip_all)]
+pub async fn restore_account(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(request): Json<RestoreAccountRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let password = Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let client_ip = addr.ip();
+
+    // Guessing the password here is no easier than guessing it on /login
+    check_login_throttle(&email, client_ip, state.login_throttle_store.clone()).await?;
+
+    match state.user_store.write().await.restore_user(&email, &password).await {
+        Ok(()) => {}
+        Err(UserStoreError::InvalidCredentials) | Err(UserStoreError::UserNotFound) => {
+            record_login_failure(&email, client_ip, state.login_throttle_store.clone()).await?;
+            return Err(AuthAPIError::IncorrectCredentials);
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    clear_login_throttle(&email, state.login_throttle_store.clone()).await?;
+
+    let response = Json(RestoreAccountResponse {
+        message: "Account restored successfully!".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DeleteAccountResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreAccountRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RestoreAccountResponse {
+    pub message: String,
+}
@@ -0,0 +1,296 @@
+use std::net::SocketAddr;
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+use crate::{
+    app_state::AppState,
+    domain::{
+        generate_code_verifier, pkce_challenge, AuthAPIError, Email, ExternalIdentity, FederatedIdentityStoreError,
+        Password, Session, TotpStoreError, User, UserStoreError,
+    },
+    routes::handle_2fa,
+    utils::{
+        auth::{
+            authenticate, generate_federated_login_token, start_session, user_agent, validate_federated_login_token,
+        },
+        constants::{AUTH_SERVICE_URL, FEDERATED_LOGIN_COOKIE_NAME},
+    },
+};
+
+const FEDERATED_LOGIN_COOKIE_PATH: &str = "/login/federated";
+const GENERATED_PASSWORD_LENGTH: usize = 64;
+
+// Sends the browser to the external identity provider. State, nonce and PKCE verifier of the login
+// travel along in a signed cookie until the provider sends the user back to the callback.
+#[tracing::instrument(name = "Start federated login", skip_all)]
+pub async fn start_federated_login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(query): Query<FederatedLoginQuery>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let Some(identity_provider) = state.identity_provider.clone() else {
+        return (jar, Err(AuthAPIError::FederatedLoginNotConfigured));
+    };
+
+    let login_state = uuid::Uuid::new_v4().to_string();
+    let nonce = uuid::Uuid::new_v4().to_string();
+    let code_verifier = generate_code_verifier();
+
+    let authorization_url = match identity_provider
+        .authorization_url(&callback_uri(), &login_state, &nonce, &pkce_challenge(&code_verifier))
+        .await
+    {
+        Ok(authorization_url) => authorization_url,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    // Anything but a pending authorization request would turn return_to into an open redirect
+    let return_to = query
+        .return_to
+        .filter(|return_to| return_to.starts_with("/authorize?"));
+
+    let token = match generate_federated_login_token(login_state, nonce, code_verifier, return_to) {
+        Ok(token) => token,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let cookie = Cookie::build((FEDERATED_LOGIN_COOKIE_NAME, token))
+        .path(FEDERATED_LOGIN_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build();
+
+    (jar.add(cookie), Ok(Redirect::to(&authorization_url)))
+}
+
+// Completes the login once the provider sent the user back. The provider replaces the password only,
+// users with 2FA finish the login at /verify-2fa like after a password login.
+#[tracing::instrument(name = "Federated login callback", skip_all)]
+pub async fn federated_login_callback(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Query(query): Query<FederatedLoginCallbackQuery>,
+) -> (CookieJar, Result<Response, AuthAPIError>) {
+    let Some(identity_provider) = state.identity_provider.clone() else {
+        return (jar, Err(AuthAPIError::FederatedLoginNotConfigured));
+    };
+
+    let login = match jar.get(FEDERATED_LOGIN_COOKIE_NAME) {
+        Some(cookie) => validate_federated_login_token(cookie.value()),
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    // Every login state is used once, whatever the outcome
+    let jar = jar.remove(Cookie::build(FEDERATED_LOGIN_COOKIE_NAME).path(FEDERATED_LOGIN_COOKIE_PATH));
+
+    let login = match login {
+        Ok(login) => login,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // A different state means the callback was not started by this browser
+    if query.state.as_deref() != Some(login.state.as_str()) {
+        return (jar, Err(AuthAPIError::InvalidToken));
+    }
+
+    let Some(code) = query.code else {
+        // ex: the user cancelled the login at the provider
+        tracing::warn!(error = ?query.error, "provider returned no authorization code");
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    };
+
+    let identity = match identity_provider
+        .exchange_code(&callback_uri(), &code, &login.code_verifier, &login.nonce)
+        .await
+    {
+        Ok(identity) => identity,
+        Err(e) => {
+            tracing::warn!("federated login failed: {:?}", e);
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+    };
+
+    let user = match find_or_create_user(&identity, &jar, &state).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    if user.disabled {
+        return (jar, Err(AuthAPIError::AccountDisabled));
+    }
+
+    let totp_enabled = match state.totp_store.read().await.get_secret(&user.email).await {
+        Ok((_, enabled)) => enabled,
+        Err(TotpStoreError::SecretNotFound) => false,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if user.requires_2fa || totp_enabled {
+        let (jar, result) = handle_2fa(&user.email, &state, jar, !totp_enabled).await;
+        return (jar, result.map(IntoResponse::into_response));
+    }
+
+    let session = Session::new(user.email.clone(), addr.ip(), user_agent(&headers));
+    let (auth_cookie, refresh_cookie) = match start_session(
+        session,
+        &user.roles,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let return_to = login.return_to.unwrap_or_else(|| "/".to_owned());
+
+    (jar.add(auth_cookie).add(refresh_cookie), Ok(Redirect::to(&return_to).into_response()))
+}
+
+// Once linked, the provider's subject decides the user. Otherwise the identity is linked to the user with
+// the same email address, who is created on the fly if there is none yet. An existing account is only linked
+// from a browser that is logged in to it, controlling the provider account alone does not prove ownership.
+async fn find_or_create_user(
+    identity: &ExternalIdentity,
+    jar: &CookieJar,
+    state: &AppState,
+) -> Result<User, AuthAPIError> {
+    let linked_email = state
+        .federated_identity_store
+        .read()
+        .await
+        .get_linked_email(&identity.issuer, &identity.subject)
+        .await;
+
+    match linked_email {
+        Ok(email) => {
+            return match state.user_store.read().await.get_user(&email).await {
+                Ok(user) => Ok(user),
+                Err(UserStoreError::UserNotFound) => Err(AuthAPIError::IncorrectCredentials),
+                Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+            }
+        }
+        Err(FederatedIdentityStoreError::IdentityNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // Only an address the provider has verified may be linked to an account
+    if !identity.email_verified {
+        return Err(AuthAPIError::EmailNotVerified);
+    }
+
+    let existing_user = state.user_store.read().await.get_user(&identity.email).await;
+    let user = match existing_user {
+        Ok(user) if user.verified => {
+            let session_email = authenticate(jar, state.banned_token_store.clone(), state.session_store.clone())
+                .await
+                .ok();
+            if session_email.as_ref() != Some(&user.email) {
+                return Err(AuthAPIError::UserAlreadyExists);
+            }
+            user
+        }
+        Ok(user) => claim_unverified_user(&user.email, state).await?,
+        Err(UserStoreError::UserNotFound) => create_user(&identity.email, state).await?,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if let Err(e) = state
+        .federated_identity_store
+        .write()
+        .await
+        .link_identity(&identity.issuer, &identity.subject, &user.email)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    tracing::info!(user = %user.email.as_ref(), issuer = %identity.issuer, "linked federated identity");
+
+    Ok(user)
+}
+
+// Federated users get a random password, they can set their own one with /forgot-password
+async fn create_user(email: &Email, state: &AppState) -> Result<User, AuthAPIError> {
+    let password = generate_password()?;
+
+    let mut user_store = state.user_store.write().await;
+
+    match user_store.add_user(User::new(email.clone(), false), password).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The provider already verified the address
+    user_store
+        .set_verified(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    user_store
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Anyone can sign up with an address they do not own and leave it unverified. The provider has verified the
+// address, so its owner takes the account over and the password of whoever created it stops working.
+async fn claim_unverified_user(email: &Email, state: &AppState) -> Result<User, AuthAPIError> {
+    let password = generate_password()?;
+
+    let mut user_store = state.user_store.write().await;
+
+    user_store
+        .update_password(email, password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    user_store
+        .set_verified(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    tracing::info!(user = %email.as_ref(), "federated login claimed unverified account");
+
+    user_store
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+fn generate_password() -> Result<Password, AuthAPIError> {
+    let password: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(GENERATED_PASSWORD_LENGTH)
+        .map(char::from)
+        .collect();
+    Password::parse(password).map_err(AuthAPIError::UnexpectedError)
+}
+
+fn callback_uri() -> String {
+    format!("{}/login/federated/callback", AUTH_SERVICE_URL.as_str())
+}
+
+#[derive(Deserialize)]
+pub struct FederatedLoginQuery {
+    pub return_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FederatedLoginCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
@@ -0,0 +1,62 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, PasswordResetToken, UserStoreError},
+};
+
+#[tracing::instrument(name = "Forgot password", skip_all)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Unknown emails get the same response, so the route can not be used to discover registered users
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(_) => send_reset_token(&email, &state).await?,
+        Err(UserStoreError::UserNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(ForgotPasswordResponse {
+        message: "If the account exists, a password reset email has been sent".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Send reset token", skip_all)]
+pub(crate) async fn send_reset_token(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    let token = PasswordResetToken::default();
+
+    if let Err(e) = state
+        .password_reset_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    if let Err(e) = state
+        .email_client
+        .send_email(email, "Password reset", token.as_ref())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ForgotPasswordResponse {
+    pub message: String,
+}
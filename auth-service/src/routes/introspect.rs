@@ -0,0 +1,114 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Form, Json,
+};
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{ApiKey, AuthAPIError, Role},
+    utils::auth::{validate_api_key, validate_oidc_access_token, validate_token, AccessTokenClaims, Claims},
+};
+
+// Token introspection, see RFC 7662. Unlike /verify-token it answers 200 for every token it can not accept,
+// banned, expired and unknown tokens are simply reported as inactive.
+#[tracing::instrument(name = "Introspect token", skip_all)]
+pub async fn introspect(
+    State(state): State<AppState>,
+    Form(request): Form<IntrospectionRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if request.token.is_empty() {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    // The token_type_hint is optional, the format of the token tells the kinds apart anyway
+    let response = if ApiKey::is_api_key(&request.token) {
+        match validate_api_key(&request.token, state.api_key_store, state.user_store).await {
+            Ok(api_key) => IntrospectionResponse::from(api_key),
+            Err(_) => IntrospectionResponse::inactive(),
+        }
+    } else if let Ok(claims) =
+        validate_token(&request.token, state.banned_token_store.clone(), state.session_store).await
+    {
+        IntrospectionResponse::from(claims)
+    } else if let Ok(claims) = validate_oidc_access_token(&request.token, state.banned_token_store).await {
+        IntrospectionResponse::from(claims)
+    } else {
+        IntrospectionResponse::inactive()
+    };
+
+    Ok((StatusCode::OK, [(header::CACHE_CONTROL, "no-store")], Json(response)))
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+}
+
+// Everything but `active` is left out for inactive tokens
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub iat: Option<i64>,
+    // Space separated, as in OAuth 2.0
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub client_id: Option<String>,
+    // Roles of login tokens, an extension of the RFC
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub roles: Option<Vec<Role>>,
+}
+
+impl IntrospectionResponse {
+    pub fn inactive() -> Self {
+        Self::default()
+    }
+}
+
+impl From<Claims> for IntrospectionResponse {
+    fn from(claims: Claims) -> Self {
+        Self {
+            active: true,
+            sub: Some(claims.sub),
+            exp: Some(claims.exp as i64),
+            iat: (claims.iat > 0).then_some(claims.iat as i64),
+            roles: Some(claims.roles),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<AccessTokenClaims> for IntrospectionResponse {
+    fn from(claims: AccessTokenClaims) -> Self {
+        Self {
+            active: true,
+            sub: Some(claims.sub),
+            exp: Some(claims.exp as i64),
+            iat: Some(claims.iat as i64),
+            scope: Some(claims.scope),
+            client_id: Some(claims.client_id),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<ApiKey> for IntrospectionResponse {
+    fn from(api_key: ApiKey) -> Self {
+        Self {
+            active: true,
+            sub: Some(api_key.email.as_ref().to_owned()),
+            exp: api_key.expires_at,
+            iat: Some(api_key.created_at),
+            scope: Some(api_key.scopes.join(" ")),
+            ..Self::default()
+        }
+    }
+}
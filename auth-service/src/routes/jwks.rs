@@ -0,0 +1,17 @@
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+
+use crate::utils::signing_keys::key_ring;
+
+// Lets other services fetch our public keys and verify tokens without calling /verify-token
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(key_ring().jwks()),
+    )
+}
@@ -1,17 +1,28 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use std::net::SocketAddr;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use serde::{Deserialize, Serialize};
 use axum_extra::extract::CookieJar;
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, User, LoginAttemptId, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{AuthAPIError, Email, Password, User, LoginAttemptId, Role, Session, TotpStoreError, TwoFACode},
+    utils::{
+        auth::{start_session, user_agent},
+        login_throttle::{check_login_throttle, clear_login_throttle, record_login_failure},
+    },
 };
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     // Use Axum's state extractor to pass in AppState
     State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     jar: CookieJar,
+    headers: HeaderMap,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     // Create a new `User` instance using data in the `request`
@@ -24,24 +35,57 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
     }; 
 
+    let client_ip = addr.ip();
+
+    // Locked out accounts and clients are rejected before the password is even checked
+    if let Err(e) = check_login_throttle(&email, client_ip, state.login_throttle_store.clone()).await {
+        return (jar, Err(e));
+    }
+
     let user_store = state.user_store.read().await;
 
     // early return AuthAPIError::UserAlreadyExists if email exists in user_store.
     if user_store.validate_user(&email,&password).await.is_err() {
+        if let Err(e) = record_login_failure(&email, client_ip, state.login_throttle_store.clone()).await {
+            return (jar, Err(e));
+        }
         return (jar,Err(AuthAPIError::IncorrectCredentials));
     }
+
+    if let Err(e) = clear_login_throttle(&email, state.login_throttle_store.clone()).await {
+        return (jar, Err(e));
+    }
  
     let user: User = match user_store.get_user(&email).await{
         Ok(user) => user,
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
+    if user.disabled {
+        return (jar, Err(AuthAPIError::AccountDisabled));
+    }
+
+    if !user.verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
 
 
   
-    match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+    // Users with a confirmed authenticator app always need a second factor
+    let totp_enabled = match state.totp_store.read().await.get_secret(&user.email).await {
+        Ok((_, enabled)) => enabled,
+        Err(TotpStoreError::SecretNotFound) => false,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    match (user.requires_2fa, totp_enabled) {
+        (_, true) => handle_2fa(&user.email, &state, jar, false).await,
+        (true, false) => handle_2fa(&user.email, &state, jar, true).await,
+        (false, false) => {
+            let session = Session::new(user.email.clone(), client_ip, user_agent(&headers));
+            handle_no_2fa(session, &user.roles, &state, jar).await
+        }
     }
 
    }
@@ -73,10 +117,11 @@ pub struct TwoFactorAuthResponse {
 }
 
 #[tracing::instrument(name = "Handle 2 factor", skip_all)]
-async fn handle_2fa(
+pub(crate) async fn handle_2fa(
     email: &Email,
     state: &AppState, 
     jar: CookieJar,
+    send_code: bool,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
@@ -99,6 +144,11 @@ async fn handle_2fa(
 
     // send 2FA code via the email client. Return `AuthAPIError::UnexpectedError` if the operation fails.
 
+    // TOTP users read the code from their authenticator app instead
+    if !send_code {
+        return (jar, Ok(two_factor_auth_response(&login_attempt_id)));
+    }
+
     if let Err(e) = state
         .email_client
         .send_email(email, "2FA Code", two_fa_code.as_ref())
@@ -108,27 +158,41 @@ async fn handle_2fa(
     }
 
 
+    (jar, Ok(two_factor_auth_response(&login_attempt_id)))
+}
+
+fn two_factor_auth_response(login_attempt_id: &LoginAttemptId) -> (StatusCode, Json<LoginResponse>) {
     let response = 
         Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse { 
             message: "2FA required".to_string(),
             login_attempt_id: login_attempt_id.as_ref().to_string()
         }));
-    (jar, Ok((StatusCode::PARTIAL_CONTENT,response)))
+    (StatusCode::PARTIAL_CONTENT, response)
 }
 
 #[tracing::instrument(name = "Handle no 2 factor", skip_all)]
-async fn handle_no_2fa(
-    email: &Email,
+pub(crate) async fn handle_no_2fa(
+    session: Session,
+    roles: &[Role],
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    let (auth_cookie, refresh_cookie) = match start_session(
+        session,
+        roles,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
     (updated_jar, Ok((StatusCode::OK,Json(LoginResponse::RegularAuth))))
 
 }
\ No newline at end of file
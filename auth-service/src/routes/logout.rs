@@ -3,7 +3,9 @@ use axum_extra::extract::{cookie,CookieJar};
 
 
 use crate::{
-    app_state::AppState, domain::AuthAPIError, utils::{auth::validate_token, constants::JWT_COOKIE_NAME}
+    app_state::AppState,
+    domain::{AuthAPIError, RefreshToken, RefreshTokenStoreError, SessionStoreError},
+    utils::{auth::validate_token, constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME}}
 };
 
 pub async fn logout(
@@ -19,7 +21,7 @@ pub async fn logout(
 
     // Validate token
     let token = cookie.value().to_owned();
-    let _ = match validate_token(&token, state.banned_token_store.clone()).await {
+    let claims = match validate_token(&token, state.banned_token_store.clone(), state.session_store.clone()).await {
         Ok(claims) => claims,
         Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
     };
@@ -35,8 +37,34 @@ pub async fn logout(
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    // Remove jwt cookie
-    let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
+    // Revoke the refresh token family so the session cannot be resumed through /refresh
+    if let Some(refresh_token) = jar
+        .get(REFRESH_COOKIE_NAME)
+        .and_then(|cookie| RefreshToken::parse(cookie.value().to_owned()).ok())
+    {
+        let mut refresh_token_store = state.refresh_token_store.write().await;
+        match refresh_token_store.use_token(&refresh_token).await {
+            Ok((_, family_id)) => {
+                if let Err(e) = refresh_token_store.revoke_family(&family_id).await {
+                    return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+                }
+            }
+            Err(RefreshTokenStoreError::TokenNotFound) | Err(RefreshTokenStoreError::TokenReused) => {}
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+    }
+
+    if let Some(session_id) = &claims.sid {
+        match state.session_store.write().await.remove_session(session_id).await {
+            Ok(()) | Err(SessionStoreError::SessionNotFound) => {}
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+    }
+
+    // Remove jwt and refresh cookies
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_COOKIE_NAME));
 
     (jar, Ok(StatusCode::OK))
 }
\ No newline at end of file
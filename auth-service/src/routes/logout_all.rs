@@ -0,0 +1,49 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::{cookie, CookieJar};
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{
+        auth::{authenticate, revoke_user_tokens},
+        constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    },
+};
+
+// Bumps the user's token generation, so every JWT issued so far is rejected on all devices
+#[tracing::instrument(name = "Logout all", skip_all)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    if let Err(e) = revoke_user_tokens(
+        &email,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_COOKIE_NAME));
+
+    let response = Json(LogoutAllResponse {
+        message: "Logged out of all sessions".to_string(),
+    });
+
+    (jar, Ok((StatusCode::OK, response)))
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct LogoutAllResponse {
+    pub message: String,
+}
@@ -0,0 +1,214 @@
+use std::net::SocketAddr;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, LoginAttemptId, Session, TotpStoreError, TwoFACode, TwoFACodeStoreError, UserStoreError},
+    routes::{handle_2fa, handle_incorrect_code, handle_no_2fa, LoginResponse},
+    utils::{
+        auth::{generate_magic_login_token, user_agent, validate_magic_login_token},
+        constants::AUTH_SERVICE_URL,
+        login_throttle::check_magic_login_rate,
+    },
+};
+
+// Emails a one-time login code or link. Both belong to a login attempt, like the codes of the 2FA login,
+// but have a store of their own, so a new attempt only replaces a pending magic login.
+#[tracing::instrument(name = "Request magic login", skip_all)]
+pub async fn request_magic_login(
+    State(state): State<AppState>,
+    Json(request): Json<MagicLoginRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    check_magic_login_rate(&email, state.login_throttle_store.clone()).await?;
+
+    let login_attempt_id = LoginAttemptId::default();
+
+    // Unknown, unverified and disabled accounts get the same response without an email,
+    // so the route can not be used to discover registered users
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => Some(user),
+        Err(UserStoreError::UserNotFound) => None,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if let Some(user) = user.filter(|user| user.verified && !user.disabled) {
+        send_magic_login(&user.email, &login_attempt_id, request.method, &state).await?;
+    }
+
+    let response = Json(MagicLoginResponse {
+        message: "If the account exists, a login email has been sent".to_string(),
+        login_attempt_id: login_attempt_id.as_ref().to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Send magic login", skip_all)]
+async fn send_magic_login(
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+    method: MagicLoginMethod,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    // Link logins store a code as well, it is simply never sent
+    let code = TwoFACode::default();
+
+    if let Err(e) = state
+        .magic_login_code_store
+        .write()
+        .await
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let (subject, content) = match method {
+        MagicLoginMethod::Code => ("Login code", code.as_ref().to_owned()),
+        MagicLoginMethod::Link => {
+            let token =
+                generate_magic_login_token(email, login_attempt_id).map_err(AuthAPIError::UnexpectedError)?;
+            ("Login link", format!("{}/?magic_token={}", AUTH_SERVICE_URL.as_str(), token))
+        }
+    };
+
+    state
+        .email_client
+        .send_email(email, subject, &content)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+// Redeems a magic login link or code. It replaces the password, so users with an authenticator app still
+// have to finish the login at /verify-2fa, while emailed 2FA codes are skipped as they prove nothing new.
+#[tracing::instrument(name = "Verify magic login", skip_all)]
+pub async fn verify_magic_login(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(request): Json<VerifyMagicLoginRequest>,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
+    let email = match redeem_magic_login(request, &state).await {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    // The account may have been disabled while the login was pending
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if user.disabled => return (jar, Err(AuthAPIError::AccountDisabled)),
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let totp_enabled = match state.totp_store.read().await.get_secret(&user.email).await {
+        Ok((_, enabled)) => enabled,
+        Err(TotpStoreError::SecretNotFound) => false,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if totp_enabled {
+        return handle_2fa(&user.email, &state, jar, false).await;
+    }
+
+    let session = Session::new(user.email.clone(), addr.ip(), user_agent(&headers));
+    handle_no_2fa(session, &user.roles, &state, jar).await
+}
+
+// Checks the link or code and consumes its login attempt, returning the user it was sent to
+async fn redeem_magic_login(request: VerifyMagicLoginRequest, state: &AppState) -> Result<Email, AuthAPIError> {
+    let (email, login_attempt_id) = match request {
+        VerifyMagicLoginRequest::Link { token } => {
+            validate_magic_login_token(&token).map_err(|_| AuthAPIError::InvalidToken)?
+        }
+        VerifyMagicLoginRequest::Code {
+            email,
+            login_attempt_id,
+            code,
+        } => {
+            let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+            let login_attempt_id =
+                LoginAttemptId::parse(login_attempt_id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+            let code = TwoFACode::parse(code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+            let (stored_attempt_id, stored_code) = state
+                .magic_login_code_store
+                .read()
+                .await
+                .get_code(&email)
+                .await
+                .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+            if stored_attempt_id != login_attempt_id {
+                return Err(AuthAPIError::IncorrectCredentials);
+            }
+            if stored_code != code {
+                return Err(handle_incorrect_code(&email, &login_attempt_id, &state.magic_login_code_store).await);
+            }
+
+            (email, login_attempt_id)
+        }
+    };
+
+    // Only the request that actually removes the code may log in, so every link and code works once
+    match state
+        .magic_login_code_store
+        .write()
+        .await
+        .consume_code(&email, &login_attempt_id)
+        .await
+    {
+        Ok(()) => Ok(email),
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MagicLoginRequest {
+    pub email: String,
+    #[serde(default)]
+    pub method: MagicLoginMethod,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MagicLoginMethod {
+    #[default]
+    Code,
+    Link,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct MagicLoginResponse {
+    pub message: String,
+    // Sent back together with the emailed code, links carry it themselves
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum VerifyMagicLoginRequest {
+    Link {
+        token: String,
+    },
+    Code {
+        email: String,
+        #[serde(rename = "loginAttemptId")]
+        login_attempt_id: String,
+        code: String,
+    },
+}
@@ -1,12 +1,48 @@
+mod admin;
+mod api_keys;
+mod change_password;
+mod delete_account;
+mod federated_login;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod magic_login;
+mod oidc;
+mod recovery_codes;
+mod refresh;
+mod forgot_password;
+mod reset_password;
+mod sessions;
 mod signup;
+mod totp;
+mod two_fa;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
 
 // re-export items from sub-modules
+pub use admin::*;
+pub use api_keys::*;
+pub use change_password::*;
+pub use delete_account::*;
+pub use federated_login::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use logout_all::*;
+pub use magic_login::*;
+pub use oidc::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use forgot_password::*;
+pub use reset_password::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
+pub use two_fa::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
\ No newline at end of file
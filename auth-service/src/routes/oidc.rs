@@ -0,0 +1,427 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Form, Json,
+};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, AuthorizationCode, AuthorizationCodeStoreError, AuthorizationGrant, Email, OidcClient,
+        OidcClientStoreError, OidcError, UserStoreError,
+    },
+    utils::{
+        auth::{
+            authenticate_claims, generate_id_token, generate_oidc_access_token, validate_oidc_access_token,
+            TOKEN_TTL_SECONDS,
+        },
+        authorization::{AdminRole, RequireRole},
+        constants::AUTH_SERVICE_URL,
+        signing_keys::key_ring,
+    },
+};
+
+const SUPPORTED_SCOPES: [&str; 2] = ["openid", "email"];
+
+// Registers a relying party, only admins may do so
+#[tracing::instrument(name = "Register OIDC client", skip_all)]
+pub async fn register_oidc_client(
+    RequireRole(claims, _): RequireRole<AdminRole>,
+    State(state): State<AppState>,
+    Json(request): Json<RegisterClientRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (client, client_secret) =
+        OidcClient::register(request.client_name, request.redirect_uris, request.confidential)
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if let Err(e) = state.oidc_client_store.write().await.add_client(client.clone()).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    tracing::info!(admin = %claims.sub, client_id = %client.client_id, "registered OIDC client");
+
+    let response = Json(RegisterClientResponse {
+        client_id: client.client_id,
+        client_secret,
+        client_name: client.name,
+        redirect_uris: client.redirect_uris,
+    });
+
+    Ok((StatusCode::CREATED, response))
+}
+
+// Starts the authorization code flow. Users without a session are sent to the login page and come back
+// once they are logged in, so the usual login, 2FA and throttling rules apply.
+#[tracing::instrument(name = "Authorize", skip_all)]
+pub async fn authorize(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    uri: Uri,
+    Query(request): Query<AuthorizeRequest>,
+) -> Result<Response, OidcError> {
+    let client_id = request
+        .client_id
+        .as_deref()
+        .ok_or(OidcError::InvalidRequest("client_id is missing".to_owned()))?;
+    let client = match state.oidc_client_store.read().await.get_client(client_id).await {
+        Ok(client) => client,
+        Err(OidcClientStoreError::ClientNotFound) => {
+            return Err(OidcError::InvalidRequest("unknown client_id".to_owned()))
+        }
+        Err(e) => return Err(OidcError::UnexpectedError(e.into())),
+    };
+
+    // Errors are only sent back to registered redirect URIs, see RFC 6749 section 4.1.2.1
+    let redirect_uri = match request.redirect_uri.as_deref() {
+        Some(redirect_uri) if client.allows_redirect_uri(redirect_uri) => redirect_uri,
+        _ => {
+            return Err(OidcError::InvalidRequest(
+                "redirect_uri is not registered for the client".to_owned(),
+            ))
+        }
+    };
+    let client_state = request.state.as_deref();
+
+    if request.response_type.as_deref() != Some("code") {
+        return redirect_to_client(redirect_uri, &[("error", "unsupported_response_type")], client_state);
+    }
+
+    let Some(scope) = granted_scope(request.scope.as_deref()) else {
+        return redirect_to_client(redirect_uri, &[("error", "invalid_scope")], client_state);
+    };
+
+    // PKCE is required from every client, confidential ones included
+    let code_challenge = match (request.code_challenge.as_deref(), request.code_challenge_method.as_deref()) {
+        (Some(code_challenge), Some("S256")) if !code_challenge.is_empty() => code_challenge,
+        _ => {
+            return redirect_to_client(
+                redirect_uri,
+                &[("error", "invalid_request"), ("error_description", "PKCE with S256 is required")],
+                client_state,
+            )
+        }
+    };
+
+    let claims =
+        match authenticate_claims(&jar, state.banned_token_store.clone(), state.session_store.clone()).await {
+            Ok(claims) => claims,
+            Err(_) => return Ok(redirect_to_login(&uri)),
+        };
+    let email = Email::parse(claims.sub).map_err(OidcError::UnexpectedError)?;
+
+    // Tokens issued before sessions were introduced carry no sid, their login time is unknown
+    let auth_time = match &claims.sid {
+        Some(session_id) => {
+            state
+                .session_store
+                .read()
+                .await
+                .get_session(session_id)
+                .await
+                .map_err(|e| OidcError::UnexpectedError(e.into()))?
+                .created_at
+        }
+        None => Utc::now().timestamp(),
+    };
+
+    let code = AuthorizationCode::default();
+    let grant = AuthorizationGrant {
+        client_id: client.client_id,
+        redirect_uri: redirect_uri.to_owned(),
+        email,
+        scope,
+        nonce: request.nonce.clone(),
+        code_challenge: code_challenge.to_owned(),
+        auth_time,
+    };
+
+    if let Err(e) = state
+        .authorization_code_store
+        .write()
+        .await
+        .add_code(code.clone(), grant)
+        .await
+    {
+        return Err(OidcError::UnexpectedError(e.into()));
+    }
+
+    redirect_to_client(redirect_uri, &[("code", code.as_ref())], client_state)
+}
+
+// Redeems an authorization code for an ID token and an access token
+#[tracing::instrument(name = "Token", skip_all)]
+pub async fn token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> Result<impl IntoResponse, OidcError> {
+    let (client, client_secret) = authenticate_client(&headers, &request, &state).await?;
+
+    if request.grant_type.as_deref() != Some("authorization_code") {
+        return Err(OidcError::UnsupportedGrantType);
+    }
+
+    let code = request
+        .code
+        .clone()
+        .and_then(|code| AuthorizationCode::parse(code).ok())
+        .ok_or(OidcError::InvalidGrant)?;
+
+    let grant = match state.authorization_code_store.write().await.consume_code(&code).await {
+        Ok(grant) => grant,
+        Err(AuthorizationCodeStoreError::CodeNotFound) => return Err(OidcError::InvalidGrant),
+        Err(e) => return Err(OidcError::UnexpectedError(e.into())),
+    };
+
+    // The code is consumed either way, so a stolen code cannot be retried with guessed verifiers
+    let code_verifier = request.code_verifier.as_deref().unwrap_or_default();
+    if grant.client_id != client.client_id
+        || request.redirect_uri.as_deref() != Some(grant.redirect_uri.as_str())
+        || !grant.verify_code_verifier(code_verifier)
+    {
+        return Err(OidcError::InvalidGrant);
+    }
+
+    let user = match state.user_store.read().await.get_user(&grant.email).await {
+        Ok(user) if !user.disabled => user,
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Err(OidcError::InvalidGrant),
+        Err(e) => return Err(OidcError::UnexpectedError(e.into())),
+    };
+
+    let id_token =
+        generate_id_token(&grant, user.verified, client_secret.as_deref()).map_err(OidcError::UnexpectedError)?;
+    let access_token = generate_oidc_access_token(&grant, state.banned_token_store.clone())
+        .await
+        .map_err(OidcError::UnexpectedError)?;
+
+    let response = Json(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        id_token,
+        scope: grant.scope,
+    });
+
+    // Token responses must not be cached, see RFC 6749 section 5.1
+    Ok((StatusCode::OK, [(header::CACHE_CONTROL, "no-store")], response))
+}
+
+// Returns the claims of the user an access token was issued for
+#[tracing::instrument(name = "Userinfo", skip_all)]
+pub async fn userinfo(State(state): State<AppState>, headers: HeaderMap) -> Result<impl IntoResponse, OidcError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(OidcError::InvalidToken)?;
+
+    let claims = validate_oidc_access_token(token, state.banned_token_store.clone())
+        .await
+        .map_err(|_| OidcError::InvalidToken)?;
+    let email = Email::parse(claims.sub).map_err(|_| OidcError::InvalidToken)?;
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if !user.disabled => user,
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Err(OidcError::InvalidToken),
+        Err(e) => return Err(OidcError::UnexpectedError(e.into())),
+    };
+
+    let include_email = claims.scope.split_whitespace().any(|scope| scope == "email");
+    let response = Json(UserInfoResponse {
+        sub: user.email.as_ref().to_owned(),
+        email: include_email.then(|| user.email.as_ref().to_owned()),
+        email_verified: include_email.then_some(user.verified),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Discovery document, see OpenID Connect Discovery 1.0 section 3.
+// Relying parties verify RS256 and EdDSA ID tokens with the JWKS and HS256 ones with their client secret,
+// so public clients are only supported with an RS256 or EdDSA key.
+#[tracing::instrument(name = "OpenID configuration", skip_all)]
+pub async fn openid_configuration() -> impl IntoResponse {
+    let issuer = AUTH_SERVICE_URL.as_str();
+    let to_strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+
+    let response = Json(OpenIdConfiguration {
+        issuer: issuer.to_owned(),
+        authorization_endpoint: format!("{}/authorize", issuer),
+        token_endpoint: format!("{}/token", issuer),
+        userinfo_endpoint: format!("{}/userinfo", issuer),
+        jwks_uri: format!("{}/.well-known/jwks.json", issuer),
+        introspection_endpoint: format!("{}/introspect", issuer),
+        scopes_supported: to_strings(&SUPPORTED_SCOPES),
+        response_types_supported: to_strings(&["code"]),
+        grant_types_supported: to_strings(&["authorization_code"]),
+        subject_types_supported: to_strings(&["public"]),
+        id_token_signing_alg_values_supported: vec![key_ring().algorithm()],
+        token_endpoint_auth_methods_supported: to_strings(&["client_secret_basic", "client_secret_post", "none"]),
+        code_challenge_methods_supported: to_strings(&["S256"]),
+        claims_supported: to_strings(&["iss", "sub", "aud", "exp", "iat", "auth_time", "nonce", "email", "email_verified"]),
+    });
+
+    (StatusCode::OK, [(header::CACHE_CONTROL, "public, max-age=300")], response)
+}
+
+// Keeps the scopes we support, a request without openid is not an OpenID Connect request
+fn granted_scope(requested: Option<&str>) -> Option<String> {
+    let mut scopes: Vec<&str> = Vec::new();
+    for scope in requested?.split_whitespace() {
+        if SUPPORTED_SCOPES.contains(&scope) && !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+
+    scopes.contains(&"openid").then(|| scopes.join(" "))
+}
+
+fn redirect_to_client(
+    redirect_uri: &str,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+) -> Result<Response, OidcError> {
+    let mut url = Url::parse(redirect_uri).map_err(|e| OidcError::UnexpectedError(e.into()))?;
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+
+    Ok(Redirect::to(url.as_str()).into_response())
+}
+
+// The login page sends the user back to `return_to` once the login (and 2FA) succeeded
+fn redirect_to_login(uri: &Uri) -> Response {
+    let return_to: String = form_urlencoded::byte_serialize(uri.to_string().as_bytes()).collect();
+    Redirect::to(&format!("/?return_to={}", return_to)).into_response()
+}
+
+// Clients authenticate either with HTTP Basic or with client_id and client_secret in the form body.
+// Public clients only send their client_id.
+// Returns the client together with the secret it authenticated with, None for public clients
+async fn authenticate_client(
+    headers: &HeaderMap,
+    request: &TokenRequest,
+    state: &AppState,
+) -> Result<(OidcClient, Option<String>), OidcError> {
+    let (client_id, client_secret) = match basic_credentials(headers) {
+        Some((client_id, client_secret)) => (client_id, Some(client_secret)),
+        None => (
+            request.client_id.clone().ok_or(OidcError::InvalidClient)?,
+            request.client_secret.clone(),
+        ),
+    };
+
+    let client = match state.oidc_client_store.read().await.get_client(&client_id).await {
+        Ok(client) => client,
+        Err(OidcClientStoreError::ClientNotFound) => return Err(OidcError::InvalidClient),
+        Err(e) => return Err(OidcError::UnexpectedError(e.into())),
+    };
+
+    if !client.verify_secret(client_secret.as_deref()) {
+        return Err(OidcError::InvalidClient);
+    }
+
+    let client_secret = client_secret.filter(|_| client.is_confidential());
+    Ok((client, client_secret))
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let decoded = STANDARD.decode(value.strip_prefix("Basic ")?).ok()?;
+    let (client_id, client_secret) = std::str::from_utf8(&decoded).ok()?.split_once(':')?;
+
+    Some((client_id.to_owned(), client_secret.to_owned()))
+}
+
+#[derive(Deserialize)]
+pub struct RegisterClientRequest {
+    #[serde(rename = "clientName")]
+    pub client_name: String,
+    #[serde(rename = "redirectUris")]
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RegisterClientResponse {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    // Only returned for confidential clients, and only this once
+    #[serde(rename = "clientSecret", skip_serializing_if = "Option::is_none", default)]
+    pub client_secret: Option<String>,
+    #[serde(rename = "clientName")]
+    pub client_name: String,
+    #[serde(rename = "redirectUris")]
+    pub redirect_uris: Vec<String>,
+}
+
+// The OAuth endpoints use the parameter names of the specifications instead of our camelCase
+#[derive(Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub id_token: String,
+    pub scope: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub email_verified: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    // RFC 8414 metadata, OpenID Connect Discovery has no field for it
+    pub introspection_endpoint: String,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
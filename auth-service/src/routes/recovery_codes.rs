@@ -0,0 +1,41 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, RecoveryCode},
+    utils::auth::authenticate,
+};
+
+// Replaces the user's recovery codes, the old set stops working right away
+#[tracing::instrument(name = "Regenerate recovery codes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    let recovery_codes = issue_recovery_codes(&email, &state).await?;
+
+    let response = Json(RecoveryCodesResponse { recovery_codes });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Generates and stores a new set of codes. The plain codes are returned only here, the store keeps their hashes.
+#[tracing::instrument(name = "Issue recovery codes", skip_all)]
+pub(crate) async fn issue_recovery_codes(email: &Email, state: &AppState) -> Result<Vec<String>, AuthAPIError> {
+    let codes = RecoveryCode::generate_set();
+
+    if let Err(e) = state.recovery_code_store.write().await.set_codes(email, &codes).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    Ok(codes.iter().map(|code| code.as_ref().to_owned()).collect())
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
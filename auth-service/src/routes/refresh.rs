@@ -0,0 +1,69 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, RefreshToken, RefreshTokenStoreError, SessionStoreError, UserStoreError},
+    utils::{
+        auth::{generate_auth_cookie, rotate_refresh_cookie},
+        constants::REFRESH_COOKIE_NAME,
+    },
+};
+
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let cookie = match jar.get(REFRESH_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = match RefreshToken::parse(cookie.value().to_owned()) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // Every refresh token can be used exactly once, reusing one revokes its whole family
+    let (email, family_id) = match state.refresh_token_store.write().await.use_token(&token).await {
+        Ok(record) => record,
+        Err(RefreshTokenStoreError::TokenNotFound) | Err(RefreshTokenStoreError::TokenReused) => {
+            return (jar, Err(AuthAPIError::InvalidToken))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // The family id is the session id, a revoked session can not be resumed
+    let session = match state.session_store.read().await.get_session(&family_id).await {
+        Ok(session) if session.email == email => session,
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // Refreshing keeps the session alive as long as its refresh tokens
+    if let Err(e) = state.session_store.write().await.add_session(session).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // Roles are read again, so changes reach the user with the next refresh
+    let roles = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if !user.disabled => user.roles,
+        Ok(_) | Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let auth_cookie = match generate_auth_cookie(&email, &family_id, &roles, state.banned_token_store.clone()).await {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let refresh_cookie = match rotate_refresh_cookie(&email, family_id, state.refresh_token_store.clone()).await {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
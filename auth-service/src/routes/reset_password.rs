@@ -0,0 +1,64 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Password, PasswordResetToken, PasswordResetTokenStoreError, UserStoreError},
+    utils::{auth::revoke_user_tokens, login_throttle::clear_login_throttle},
+};
+
+#[tracing::instrument(name = "Reset password", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = PasswordResetToken::parse(request.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    // Checked before the token is used up, so the same link still works with a better password
+    state.password_policy.check(&request.new_password, &[]).await?;
+    let password = Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let email = match state.password_reset_token_store.write().await.consume_token(&token).await {
+        Ok(email) => email,
+        Err(PasswordResetTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match state.user_store.write().await.update_password(&email, password).await {
+        Ok(()) => {}
+        // The account was deleted after the reset was requested
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // Whoever knew the old password may still hold a session, so every outstanding token is revoked
+    if let Err(e) = revoke_user_tokens(
+        &email,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    // The owner proved access to the mailbox, so the account is no longer locked out
+    clear_login_throttle(&email, state.login_throttle_store.clone()).await?;
+
+    let response = Json(ResetPasswordResponse {
+        message: "Password reset successfully!".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ResetPasswordResponse {
+    pub message: String,
+}
@@ -0,0 +1,118 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, Session, SessionStoreError},
+    utils::auth::{authenticate, authenticate_claims},
+};
+
+#[tracing::instrument(name = "List sessions", skip_all)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = authenticate_claims(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+    let email = Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_user_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(ListSessionsResponse {
+        sessions: sessions
+            .into_iter()
+            .map(|session| {
+                let current = claims.sid.as_ref() == Some(&session.id);
+                SessionResponse::new(session, current)
+            })
+            .collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Logs out a single device, its JWT stops working right away and its refresh token family is revoked
+#[tracing::instrument(name = "Revoke session", skip_all)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    {
+        let mut session_store = state.session_store.write().await;
+
+        // Sessions of other users are reported as missing, so session ids can not be probed
+        match session_store.get_session(&session_id).await {
+            Ok(session) if session.email == email => {}
+            Ok(_) | Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::SessionNotFound),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+
+        match session_store.remove_session(&session_id).await {
+            Ok(()) | Err(SessionStoreError::SessionNotFound) => {}
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+
+    if let Err(e) = state.refresh_token_store.write().await.revoke_family(&session_id).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let response = Json(RevokeSessionResponse {
+        message: "Session revoked successfully!".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ListSessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SessionResponse {
+    pub id: String,
+    // RFC 3339 timestamp of the login
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    pub ip: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    // Whether this is the session making the request
+    pub current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: Session, current: bool) -> Self {
+        let created_at = DateTime::from_timestamp(session.created_at, 0)
+            .map(|created_at| created_at.to_rfc3339())
+            .unwrap_or_default();
+
+        Self {
+            id: session.id,
+            created_at,
+            ip: session.ip,
+            user_agent: session.user_agent,
+            current,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RevokeSessionResponse {
+    pub message: String,
+}
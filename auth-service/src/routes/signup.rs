@@ -2,7 +2,9 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde::{Deserialize, Serialize};
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, User}
+    domain::{AuthAPIError, Email, Password, User, UserStoreError},
+    routes::issue_recovery_codes,
+    utils::{auth::generate_email_verification_token, constants::AUTH_SERVICE_URL},
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
@@ -13,10 +15,11 @@ pub async fn signup(
 ) -> impl IntoResponse {
     // Create a new `User` instance using data in the `request`
     let email = Email::parse(request.email.clone()).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    state.password_policy.check(&request.password, &[email.as_ref()]).await?;
     let password = Password::parse(request.password.clone()).map_err(|_| AuthAPIError::InvalidCredentials)?;
     
     
-    let user = User::new(email, password, request.requires_2fa);
+    let user = User::new(email, request.requires_2fa);
 
 
 
@@ -28,17 +31,46 @@ pub async fn signup(
         return Err(AuthAPIError::UserAlreadyExists);
     }
     //  instead of using unwrap, early return AuthAPIError::UnexpectedError if add_user() fails.
-    if let Err(e) = user_store.add_user(user).await {
-        return Err(AuthAPIError::UnexpectedError(e.into())); // Updated!
+    let email = user.email.clone();
+    match user_store.add_user(user, password).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())), // Updated!
     }
 
+    send_verification_email(&email, &state).await?;
+
+    // 2FA users get their recovery codes right away, in case they lose access to their mailbox
+    let recovery_codes = if request.requires_2fa {
+        Some(issue_recovery_codes(&email, &state).await?)
+    } else {
+        None
+    };
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes,
     });
     
     Ok((StatusCode::CREATED, response))
 }
 
+#[tracing::instrument(name = "Send verification email", skip_all)]
+async fn send_verification_email(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    let token = generate_email_verification_token(email).map_err(AuthAPIError::UnexpectedError)?;
+    let link = format!("{}/verify-email?token={}", AUTH_SERVICE_URL.as_str(), token);
+
+    if let Err(e) = state
+        .email_client
+        .send_email(email, "Verify your email", &link)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    Ok(())
+}
+
 #[derive(Deserialize)]
 pub struct SignupRequest {
     pub email: String,
@@ -50,4 +82,6 @@ pub struct SignupRequest {
 #[derive(Serialize,Deserialize,Debug,PartialEq)]
 pub struct SignupResponse {
     pub message: String,
+    #[serde(rename = "recoveryCodes", default, skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
 }
\ No newline at end of file
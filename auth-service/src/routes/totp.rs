@@ -0,0 +1,120 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, TotpSecret, TotpStoreError, TwoFACode},
+    routes::issue_recovery_codes,
+    utils::{auth::authenticate, constants::TOTP_ISSUER},
+};
+
+#[tracing::instrument(name = "Enroll TOTP", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    let mut totp_store = state.totp_store.write().await;
+
+    // An active authenticator can not be silently replaced
+    match totp_store.get_secret(&email).await {
+        Ok((_, true)) => return Err(AuthAPIError::TotpAlreadyEnabled),
+        Ok((_, false)) | Err(TotpStoreError::SecretNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let secret = TotpSecret::default();
+    let otpauth_uri = secret
+        .otpauth_uri(TOTP_ISSUER, &email)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    if let Err(e) = totp_store.set_secret(email, secret.clone()).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let response = Json(EnrollTotpResponse {
+        otpauth_uri,
+        secret: secret.as_ref().to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Confirm TOTP", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let secret = match state.totp_store.read().await.get_secret(&email).await {
+        Ok((_, true)) => return Err(AuthAPIError::TotpAlreadyEnabled),
+        Ok((secret, false)) => secret,
+        Err(TotpStoreError::SecretNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    check_totp_code(&email, &secret, &code, &state).await?;
+
+    if let Err(e) = state.totp_store.write().await.enable(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let recovery_codes = issue_recovery_codes(&email, &state).await?;
+
+    let response = Json(ConfirmTotpResponse {
+        message: "TOTP enabled successfully!".to_string(),
+        recovery_codes,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Accepts the code only once, even within the skew window
+#[tracing::instrument(name = "Check TOTP code", skip_all)]
+pub(crate) async fn check_totp_code(
+    email: &Email,
+    secret: &TotpSecret,
+    code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let now = u64::try_from(Utc::now().timestamp()).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let time_step = match secret.verify(code, now) {
+        Ok(Some(time_step)) => time_step,
+        Ok(None) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    match state.totp_store.write().await.use_time_step(email, time_step).await {
+        Ok(()) => Ok(()),
+        Err(TotpStoreError::CodeReused) | Err(TotpStoreError::SecretNotFound) => {
+            Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EnrollTotpResponse {
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+    pub secret: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ConfirmTotpResponse {
+    pub message: String,
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,243 @@
+use std::net::SocketAddr;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, LoginAttemptId, Password, TotpSecret, TotpStoreError, TwoFACode, TwoFACodeStoreError,
+        UserStoreError,
+    },
+    routes::{check_totp_code, handle_incorrect_code, issue_recovery_codes},
+    utils::{
+        auth::authenticate,
+        login_throttle::{
+            check_login_throttle, check_two_fa_throttle, clear_login_throttle, record_login_failure,
+            record_two_fa_failure,
+        },
+    },
+};
+
+// Emails a fresh code to the logged in user, it is needed to enable or disable 2FA.
+// It has a store of its own, so a pending login attempt of the user is left alone.
+#[tracing::instrument(name = "Send 2FA code", skip_all)]
+pub async fn send_two_fa_code(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    let two_fa_code = TwoFACode::default();
+
+    if let Err(e) = state
+        .two_fa_confirmation_code_store
+        .write()
+        .await
+        .add_code(email.clone(), LoginAttemptId::default(), two_fa_code.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    if let Err(e) = state
+        .email_client
+        .send_email(&email, "2FA Code", two_fa_code.as_ref())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    let response = Json(TwoFAResponse {
+        message: "2FA code sent".to_string(),
+    });
+
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+#[tracing::instrument(name = "Enable 2FA", skip_all)]
+pub async fn enable_2fa(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<Enable2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    let two_fa_code = TwoFACode::parse(request.two_fa_code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if requires_2fa(&email, &state).await? {
+        return Err(AuthAPIError::TwoFAAlreadyEnabled);
+    }
+
+    // Proves the user can actually receive codes before logins start depending on it
+    confirm_emailed_code(&email, &two_fa_code, &state).await?;
+
+    set_requires_2fa(&email, true, &state).await?;
+
+    let recovery_codes = issue_recovery_codes(&email, &state).await?;
+
+    let response = Json(Enable2FAResponse {
+        message: "2FA enabled successfully!".to_string(),
+        recovery_codes,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Disable 2FA", skip_all)]
+pub async fn disable_2fa(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    jar: CookieJar,
+    Json(request): Json<Disable2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+    let password = Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let two_fa_code = TwoFACode::parse(request.two_fa_code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Emailed codes and an authenticator app are both 2FA, whichever is enabled is turned off
+    let email_2fa = requires_2fa(&email, &state).await?;
+    let totp_secret = enabled_totp_secret(&email, &state).await?;
+    if !email_2fa && totp_secret.is_none() {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    let client_ip = addr.ip();
+
+    // A stolen session alone is not enough to weaken the account, nor to guess its password unthrottled
+    check_login_throttle(&email, client_ip, state.login_throttle_store.clone()).await?;
+
+    let validation = state.user_store.read().await.validate_user(&email, &password).await;
+    match validation {
+        Ok(()) => {}
+        Err(UserStoreError::InvalidCredentials) | Err(UserStoreError::UserNotFound) => {
+            record_login_failure(&email, client_ip, state.login_throttle_store.clone()).await?;
+            return Err(AuthAPIError::IncorrectCredentials);
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    clear_login_throttle(&email, state.login_throttle_store.clone()).await?;
+
+    // Users enrolled in TOTP confirm with their authenticator app, as they do on /verify-2fa
+    match &totp_secret {
+        Some(secret) => confirm_totp_code(&email, secret, &two_fa_code, &state).await?,
+        None => confirm_emailed_code(&email, &two_fa_code, &state).await?,
+    }
+
+    if email_2fa {
+        set_requires_2fa(&email, false, &state).await?;
+    }
+    if totp_secret.is_some() {
+        match state.totp_store.write().await.remove_secret(&email).await {
+            Ok(()) | Err(TotpStoreError::SecretNotFound) => {}
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+
+    let response = Json(TwoFAResponse {
+        message: "2FA disabled successfully!".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+async fn requires_2fa(email: &Email, state: &AppState) -> Result<bool, AuthAPIError> {
+    match state.user_store.read().await.get_user(email).await {
+        Ok(user) => Ok(user.requires_2fa),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+async fn set_requires_2fa(email: &Email, requires_2fa: bool, state: &AppState) -> Result<(), AuthAPIError> {
+    match state.user_store.write().await.set_requires_2fa(email, requires_2fa).await {
+        Ok(()) => Ok(()),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+async fn enabled_totp_secret(email: &Email, state: &AppState) -> Result<Option<TotpSecret>, AuthAPIError> {
+    match state.totp_store.read().await.get_secret(email).await {
+        Ok((secret, true)) => Ok(Some(secret)),
+        Ok((_, false)) | Err(TotpStoreError::SecretNotFound) => Ok(None),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Wrong authenticator codes count towards the same per account limit as on /verify-2fa
+#[tracing::instrument(name = "Confirm TOTP code", skip_all)]
+async fn confirm_totp_code(
+    email: &Email,
+    secret: &TotpSecret,
+    two_fa_code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    check_two_fa_throttle(email, state.login_throttle_store.clone()).await?;
+
+    match check_totp_code(email, secret, two_fa_code, state).await {
+        Err(AuthAPIError::IncorrectCredentials) => {
+            record_two_fa_failure(email, state.login_throttle_store.clone()).await?;
+            Err(AuthAPIError::IncorrectCredentials)
+        }
+        result => result,
+    }
+}
+
+// Checks the code sent by `send_two_fa_code` and consumes it.
+// Wrong codes count towards the attempt limit just like on /verify-2fa.
+#[tracing::instrument(name = "Confirm emailed 2FA code", skip_all)]
+async fn confirm_emailed_code(email: &Email, two_fa_code: &TwoFACode, state: &AppState) -> Result<(), AuthAPIError> {
+    let (login_attempt_id, stored_code) = match state.two_fa_confirmation_code_store.read().await.get_code(email).await {
+        Ok(code_tuple) => code_tuple,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if !stored_code.eq(two_fa_code) {
+        return Err(handle_incorrect_code(email, &login_attempt_id, &state.two_fa_confirmation_code_store).await);
+    }
+
+    match state
+        .two_fa_confirmation_code_store
+        .write()
+        .await
+        .consume_code(email, &login_attempt_id)
+        .await
+    {
+        Ok(()) => Ok(()),
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Enable2FARequest {
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: String,
+}
+
+#[derive(Deserialize)]
+pub struct Disable2FARequest {
+    pub password: String,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TwoFAResponse {
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Enable2FAResponse {
+    pub message: String,
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
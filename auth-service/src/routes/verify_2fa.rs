@@ -1,16 +1,31 @@
-use axum::{extract::State, response::IntoResponse, Json};
+use std::net::SocketAddr;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    response::IntoResponse,
+    Json,
+};
 use serde::{Serialize,Deserialize};
 use axum_extra::extract::CookieJar;
 use crate::{
-    app_state::AppState, 
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode}, 
-    utils::auth::generate_auth_cookie,
+    app_state::{AppState, TwoFACodeStoreType}, 
+    domain::{
+        AuthAPIError, Email, LoginAttemptId, RecoveryCode, RecoveryCodeStoreError, Session, TotpStoreError,
+        TwoFACode, TwoFACodeStoreError, UserStoreError,
+    },
+    routes::check_totp_code,
+    utils::{
+        auth::{start_session, user_agent, MAX_TWO_FA_ATTEMPTS},
+        login_throttle::{check_two_fa_throttle, clear_two_fa_throttle, record_two_fa_failure},
+    },
 };
 
 #[tracing::instrument(name = "Verify 2fa", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     jar: CookieJar,
+    headers: HeaderMap,
     Json(request): Json<Verify2FARequest>,
 ) ->  (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(request.email.clone()) {
@@ -23,36 +38,147 @@ pub async fn verify_2fa(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let two_fa_code = match TwoFACode::parse(request.two_fa_code) {
-        Ok(two_fa_code) => two_fa_code,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    // A recovery code can be sent instead of the 2FA code, the two formats never overlap
+    let submitted_code = match TwoFACode::parse(request.two_fa_code.clone()) {
+        Ok(two_fa_code) => SubmittedCode::TwoFA(two_fa_code),
+        Err(_) => match RecoveryCode::parse(request.two_fa_code) {
+            Ok(recovery_code) => SubmittedCode::Recovery(recovery_code),
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        },
     };
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    if let Err(e) = check_two_fa_throttle(&email, state.login_throttle_store.clone()).await {
+        return (jar, Err(e));
+    }
 
-    let code_tuple = match two_fa_code_store.get_code(&email).await {
+    let code_tuple = match state.two_fa_code_store.read().await.get_code(&email).await {
         Ok(code_tuple) => code_tuple,
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
-    if !code_tuple.0.eq(&login_attempt_id) || !code_tuple.1.eq(&two_fa_code) {
+    if !code_tuple.0.eq(&login_attempt_id) {
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
-    if let Err(e) = two_fa_code_store.remove_code(&email).await {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    let code_check = match submitted_code {
+        SubmittedCode::TwoFA(two_fa_code) => check_two_fa_code(&email, &code_tuple.1, &two_fa_code, &state).await,
+        SubmittedCode::Recovery(recovery_code) => {
+            match state.recovery_code_store.write().await.use_code(&email, &recovery_code).await {
+                Ok(()) => Ok(()),
+                Err(RecoveryCodeStoreError::CodeNotFound) => Err(AuthAPIError::IncorrectCredentials),
+                Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+            }
+        }
+    };
+
+    match code_check {
+        Ok(()) => {}
+        Err(AuthAPIError::IncorrectCredentials) => {
+            // Counted per account as well, a new password login would otherwise bring a fresh set of attempts
+            if let Err(e) = record_two_fa_failure(&email, state.login_throttle_store.clone()).await {
+                return (jar, Err(e));
+            }
+            return (jar, Err(handle_incorrect_code(&email, &login_attempt_id, &state.two_fa_code_store).await));
+        }
+        Err(e) => return (jar, Err(e)),
+    }
+
+    // Only the request that actually removes the code may log in
+    match state
+        .two_fa_code_store
+        .write()
+        .await
+        .consume_code(&email, &login_attempt_id)
+        .await
+    {
+        Ok(()) => {}
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return (jar, Err(AuthAPIError::IncorrectCredentials))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    if let Err(e) = clear_two_fa_throttle(&email, state.login_throttle_store.clone()).await {
+        return (jar, Err(e));
     }
 
-    let cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    // The account may have been disabled while the code was pending
+    let roles = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if user.disabled => return (jar, Err(AuthAPIError::AccountDisabled)),
+        Ok(user) => user.roles,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let session = Session::new(email, addr.ip(), user_agent(&headers));
+    let (cookie, refresh_cookie) = match start_session(
+        session,
+        &roles,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(cookie);
+    let updated_jar = jar.add(cookie).add(refresh_cookie);
 
     (updated_jar, Ok(()))
 }
 
+enum SubmittedCode {
+    TwoFA(TwoFACode),
+    Recovery(RecoveryCode),
+}
+
+#[tracing::instrument(name = "Check 2FA code", skip_all)]
+async fn check_two_fa_code(
+    email: &Email,
+    stored_code: &TwoFACode,
+    two_fa_code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    // Users enrolled in TOTP have to use their authenticator app, the stored code is never sent to them
+    let totp_secret = match state.totp_store.read().await.get_secret(email).await {
+        Ok((secret, true)) => Some(secret),
+        Ok((_, false)) | Err(TotpStoreError::SecretNotFound) => None,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match totp_secret {
+        Some(secret) => check_totp_code(email, &secret, two_fa_code, state).await,
+        None if stored_code.eq(two_fa_code) => Ok(()),
+        None => Err(AuthAPIError::IncorrectCredentials),
+    }
+}
+
+// Counts the wrong code and invalidates the login attempt once it ran out of attempts,
+// so the code can not be brute forced within its lifetime
+#[tracing::instrument(name = "Handle incorrect 2FA code", skip_all)]
+pub(crate) async fn handle_incorrect_code(
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+    code_store: &TwoFACodeStoreType,
+) -> AuthAPIError {
+    let mut code_store = code_store.write().await;
+
+    let attempts = match code_store.record_failed_attempt(login_attempt_id).await {
+        Ok(attempts) => attempts,
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+    };
+
+    if attempts >= MAX_TWO_FA_ATTEMPTS {
+        match code_store.consume_code(email, login_attempt_id).await {
+            Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+            Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+        }
+    }
+
+    AuthAPIError::IncorrectCredentials
+}
 
 // implement the Verify2FARequest struct. See the verify-2fa route contract in step 1 for the expected JSON body.
 #[derive(Deserialize,Debug)]
@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, UserStoreError},
+    utils::auth::validate_email_verification_token,
+};
+
+#[tracing::instrument(name = "Verify email", skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Query(params): Query<VerifyEmailParams>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = validate_email_verification_token(&params.token)
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    match state.user_store.write().await.set_verified(&email).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email verified successfully!".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailParams {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
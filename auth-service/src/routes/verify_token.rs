@@ -1,7 +1,7 @@
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::auth::validate_token,
+    domain::{ApiKey, AuthAPIError},
+    utils::auth::{validate_api_key, validate_token},
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 
@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 
 
 
+// Accepts JWTs as well as API keys and tells the caller which of the two it was
 pub async fn verify_token(
     // Use Axum's state extractor to pass in AppState
     State(state): State<AppState>,
@@ -20,11 +21,29 @@ pub async fn verify_token(
         return Err(AuthAPIError::InvalidCredentials)
     }
 
-    if validate_token(&request.token,state.banned_token_store).await.is_err() {
-        return Err(AuthAPIError::InvalidToken)
-    }
+    let response = if ApiKey::is_api_key(&request.token) {
+        let api_key = validate_api_key(&request.token, state.api_key_store, state.user_store)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        VerifyResponse {
+            kind: CredentialKind::ApiKey,
+            sub: api_key.email.as_ref().to_owned(),
+            scopes: Some(api_key.scopes),
+        }
+    } else {
+        let claims = validate_token(&request.token, state.banned_token_store, state.session_store)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
 
-    Ok(StatusCode::OK)
+        VerifyResponse {
+            kind: CredentialKind::Jwt,
+            sub: claims.sub,
+            scopes: None,
+        }
+    };
+
+    Ok((StatusCode::OK, Json(response)))
 }
 
 #[derive(Deserialize)]
@@ -34,5 +53,16 @@ pub struct VerifyRequest {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct VerifyResponse {
-    pub message: String,
-}
\ No newline at end of file
+    pub kind: CredentialKind,
+    pub sub: String,
+    // Only API keys are limited to scopes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CredentialKind {
+    Jwt,
+    ApiKey,
+}
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+use argon2::{
+    password_hash::{self, SaltString},
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, PasswordHasher as _, PasswordVerifier, Version,
+};
+use color_eyre::eyre::{eyre, Result};
+use ring::digest::{digest, SHA256};
+use tokio::task;
+
+use crate::domain::{Password, PasswordHash, PasswordHasher};
+
+// Raising the cost is safe, user stores rehash older passwords with the next successful login
+const ARGON2_MEMORY_COST_KIB: u32 = 15000;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+// Bytes of the pepper's SHA-256 digest recorded in each hash
+const PEPPER_KEY_ID_LEN: usize = 4;
+
+// Argon2id with an optional pepper: a server side secret mixed into every hash, so a leaked database alone
+// is not enough to guess passwords. Hashes name the pepper by its key id, which lets hashes made before the
+// pepper was configured still verify until they are rehashed. The pepper can not be rotated, changing it
+// invalidates every hash made with the old one.
+#[derive(Clone)]
+pub struct Argon2PasswordHasher {
+    params: Params,
+    pepper: Option<Arc<[u8]>>,
+}
+
+impl Argon2PasswordHasher {
+    pub fn new(pepper: Option<Vec<u8>>) -> Result<Self> {
+        let mut params = ParamsBuilder::new();
+        params
+            .m_cost(ARGON2_MEMORY_COST_KIB)
+            .t_cost(ARGON2_ITERATIONS)
+            .p_cost(ARGON2_PARALLELISM);
+
+        let pepper = match pepper {
+            Some(pepper) if pepper.is_empty() => return Err(eyre!("Password pepper must not be empty")),
+            Some(pepper) => {
+                params.keyid(
+                    KeyId::new(&digest(&SHA256, &pepper).as_ref()[..PEPPER_KEY_ID_LEN]).map_err(|e| eyre!(e))?,
+                );
+                // Fails early for peppers Argon2 does not accept
+                Argon2::new_with_secret(&pepper, Algorithm::Argon2id, Version::V0x13, Params::default())
+                    .map_err(|e| eyre!(e))?;
+                Some(Arc::from(pepper))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            params: params.build().map_err(|e| eyre!(e))?,
+            pepper,
+        })
+    }
+
+    // Hashes carrying a key id need the pepper it names, hashes without one were made before any pepper
+    fn argon2(&self, key_id: &[u8]) -> Result<Argon2<'_>> {
+        match &self.pepper {
+            _ if key_id.is_empty() => Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())),
+            Some(pepper) if key_id == self.params.keyid() => {
+                Argon2::new_with_secret(pepper, Algorithm::Argon2id, Version::V0x13, self.params.clone())
+                    .map_err(|e| eyre!(e))
+            }
+            _ => Err(eyre!("Password hash was made with an unknown pepper")),
+        }
+    }
+}
+
+impl Default for Argon2PasswordHasher {
+    fn default() -> Self {
+        Self::new(None).expect("Default Argon2 parameters are valid")
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordHasher for Argon2PasswordHasher {
+    // Hashing is a CPU-intensive operation. To avoid blocking other async tasks,
+    // it runs on a separate thread pool using tokio::task::spawn_blocking.
+    #[tracing::instrument(name = "Computing password hash", skip_all)]
+    async fn hash(&self, password: &Password) -> Result<PasswordHash> {
+        let hasher = self.clone();
+        let password = password.clone();
+        let current_span: tracing::Span = tracing::Span::current();
+
+        task::spawn_blocking(move || {
+            current_span.in_scope(|| {
+                let salt = SaltString::generate(&mut rand::thread_rng());
+                let password_hash = hasher
+                    .argon2(hasher.params.keyid())?
+                    .hash_password(password.as_ref().as_bytes(), &salt)
+                    .map_err(|e| eyre!(e))?
+                    .to_string();
+
+                PasswordHash::parse(password_hash)
+            })
+        })
+        .await?
+    }
+
+    #[tracing::instrument(name = "Verify password hash", skip_all)]
+    async fn verify(&self, password: &Password, password_hash: &PasswordHash) -> Result<bool> {
+        let hasher = self.clone();
+        let password = password.clone();
+        let password_hash = password_hash.clone();
+        let current_span: tracing::Span = tracing::Span::current();
+
+        task::spawn_blocking(move || {
+            current_span.in_scope(|| {
+                let expected_hash = password_hash::PasswordHash::new(password_hash.as_ref()).map_err(|e| eyre!(e))?;
+                let params = Params::try_from(&expected_hash).map_err(|e| eyre!(e))?;
+
+                // The cost parameters are taken from the hash, so hashes with outdated settings still verify
+                match hasher
+                    .argon2(params.keyid())?
+                    .verify_password(password.as_ref().as_bytes(), &expected_hash)
+                {
+                    Ok(()) => Ok(true),
+                    Err(password_hash::Error::Password) => Ok(false),
+                    Err(e) => Err(eyre!(e)),
+                }
+            })
+        })
+        .await?
+    }
+
+    fn needs_rehash(&self, password_hash: &PasswordHash) -> bool {
+        let Ok(hash) = password_hash::PasswordHash::new(password_hash.as_ref()) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&hash) else {
+            return true;
+        };
+
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+            || params.keyid() != self.params.keyid()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn password(password: &str) -> Password {
+        Password::parse(password.to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hash_and_verify() {
+        let hasher = Argon2PasswordHasher::default();
+        let password_hash = hasher.hash(&password("password")).await.unwrap();
+
+        assert!(password_hash.as_ref().starts_with("$argon2id$v=19$m=15000,t=2,p=1$"));
+        assert!(hasher.verify(&password("password"), &password_hash).await.unwrap());
+        assert!(!hasher.verify(&password("wrongpassword"), &password_hash).await.unwrap());
+        assert!(!hasher.needs_rehash(&password_hash));
+
+        // Every hash gets a salt of its own
+        assert_ne!(hasher.hash(&password("password")).await.unwrap(), password_hash);
+    }
+
+    #[tokio::test]
+    async fn test_pepper() {
+        let plain = Argon2PasswordHasher::default();
+        let peppered = Argon2PasswordHasher::new(Some(b"pepper".to_vec())).unwrap();
+        let other_pepper = Argon2PasswordHasher::new(Some(b"other pepper".to_vec())).unwrap();
+
+        let password_hash = peppered.hash(&password("password")).await.unwrap();
+        assert!(password_hash.as_ref().contains(",keyid="));
+        assert!(peppered.verify(&password("password"), &password_hash).await.unwrap());
+        assert!(!peppered.verify(&password("wrongpassword"), &password_hash).await.unwrap());
+        assert!(!peppered.needs_rehash(&password_hash));
+
+        // Without the pepper the hash is useless
+        assert!(plain.verify(&password("password"), &password_hash).await.is_err());
+        assert!(other_pepper.verify(&password("password"), &password_hash).await.is_err());
+
+        // Hashes from before the pepper keep working until they are rehashed
+        let old_hash = plain.hash(&password("password")).await.unwrap();
+        assert!(peppered.verify(&password("password"), &old_hash).await.unwrap());
+        assert!(peppered.needs_rehash(&old_hash));
+
+        assert!(Argon2PasswordHasher::new(Some(Vec::new())).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_needs_rehash() {
+        let hasher = Argon2PasswordHasher::default();
+        let outdated_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(4096, 1, 1, None).unwrap())
+            .hash_password(b"password", &SaltString::generate(&mut rand::thread_rng()))
+            .unwrap()
+            .to_string();
+        let outdated_hash = PasswordHash::parse(outdated_hash).unwrap();
+
+        assert!(hasher.verify(&password("password"), &outdated_hash).await.unwrap());
+        assert!(hasher.needs_rehash(&outdated_hash));
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, Email,
+};
+
+#[derive(Default)]
+pub struct HashmapApiKeyStore {
+    // Keyed by key id
+    keys: HashMap<String, ApiKey>,
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for HashmapApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        self.keys.insert(key.id.clone(), key);
+        Ok(())
+    }
+
+    async fn get_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, ApiKeyStoreError> {
+        self.keys
+            .values()
+            .find(|key| key.key_hash == key_hash)
+            .cloned()
+            .ok_or(ApiKeyStoreError::KeyNotFound)
+    }
+
+    async fn get_user_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let mut keys: Vec<ApiKey> = self
+            .keys
+            .values()
+            .filter(|key| key.email == *email)
+            .cloned()
+            .collect();
+        keys.sort_by_key(|key| key.created_at);
+        Ok(keys)
+    }
+
+    async fn remove_key(&mut self, email: &Email, id: &str) -> Result<(), ApiKeyStoreError> {
+        match self.keys.get(id) {
+            Some(key) if key.email == *email => {
+                self.keys.remove(id);
+                Ok(())
+            }
+            _ => Err(ApiKeyStoreError::KeyNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_get_and_remove_key() {
+        let mut store = HashmapApiKeyStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other_email = Email::parse("other@example.com".to_owned()).unwrap();
+        let (key, secret) = ApiKey::generate(email.clone(), "CI".to_owned(), vec![], None).unwrap();
+
+        assert_eq!(store.add_key(key.clone()).await, Ok(()));
+        assert_eq!(store.get_key_by_hash(&ApiKey::hash(&secret)).await, Ok(key.clone()));
+        assert_eq!(store.get_user_keys(&email).await, Ok(vec![key.clone()]));
+        assert_eq!(store.get_user_keys(&other_email).await, Ok(vec![]));
+
+        // Only the owner can remove a key
+        assert_eq!(
+            store.remove_key(&other_email, &key.id).await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+        assert_eq!(store.remove_key(&email, &key.id).await, Ok(()));
+        assert_eq!(
+            store.get_key_by_hash(&key.key_hash).await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+    }
+}
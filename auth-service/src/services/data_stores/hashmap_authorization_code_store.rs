@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use chrono::Utc;
+
+use crate::{
+    domain::{
+        data_stores::{AuthorizationCodeStore, AuthorizationCodeStoreError},
+        AuthorizationCode, AuthorizationGrant,
+    },
+    utils::auth::AUTHORIZATION_CODE_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashmapAuthorizationCodeStore {
+    // Each code is stored together with its grant and expiry timestamp
+    codes: HashMap<AuthorizationCode, (AuthorizationGrant, i64)>,
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for HashmapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let expires_at = Utc::now().timestamp() + AUTHORIZATION_CODE_TTL_SECONDS;
+        self.codes.insert(code, (grant, expires_at));
+        Ok(())
+    }
+
+    async fn consume_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        match self.codes.remove(code) {
+            Some((grant, expires_at)) if expires_at > Utc::now().timestamp() => Ok(grant),
+            _ => Err(AuthorizationCodeStoreError::CodeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+
+    fn grant() -> AuthorizationGrant {
+        AuthorizationGrant {
+            client_id: "client".to_owned(),
+            redirect_uri: "https://app.example.com/callback".to_owned(),
+            email: Email::parse("test@example.com".to_owned()).unwrap(),
+            scope: "openid".to_owned(),
+            nonce: Some("nonce".to_owned()),
+            code_challenge: "challenge".to_owned(),
+            auth_time: 1_700_000_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_consume_code() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+
+        let result = store.add_code(code.clone(), grant()).await;
+        assert!(result.is_ok());
+
+        let result = store.consume_code(&code).await;
+        assert_eq!(result.unwrap(), grant());
+
+        // An authorization code can only be redeemed once
+        let result = store.consume_code(&code).await;
+        assert_eq!(result, Err(AuthorizationCodeStoreError::CodeNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_consume_expired_code() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+
+        store.codes.insert(code.clone(), (grant(), Utc::now().timestamp() - 1));
+
+        let result = store.consume_code(&code).await;
+        assert_eq!(result, Err(AuthorizationCodeStoreError::CodeNotFound));
+    }
+}
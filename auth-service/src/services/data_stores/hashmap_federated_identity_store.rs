@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{FederatedIdentityStore, FederatedIdentityStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapFederatedIdentityStore {
+    // Keyed by issuer and subject
+    identities: HashMap<(String, String), Email>,
+}
+
+#[async_trait::async_trait]
+impl FederatedIdentityStore for HashmapFederatedIdentityStore {
+    async fn link_identity(
+        &mut self,
+        issuer: &str,
+        subject: &str,
+        email: &Email,
+    ) -> Result<(), FederatedIdentityStoreError> {
+        self.identities
+            .insert((issuer.to_owned(), subject.to_owned()), email.clone());
+        Ok(())
+    }
+
+    async fn get_linked_email(&self, issuer: &str, subject: &str) -> Result<Email, FederatedIdentityStoreError> {
+        self.identities
+            .get(&(issuer.to_owned(), subject.to_owned()))
+            .cloned()
+            .ok_or(FederatedIdentityStoreError::IdentityNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_link_identity() {
+        let mut store = HashmapFederatedIdentityStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        let result = store.link_identity("https://idp.example.com", "subject", &email).await;
+        assert!(result.is_ok());
+
+        let result = store.get_linked_email("https://idp.example.com", "subject").await;
+        assert_eq!(result.unwrap(), email);
+
+        // Subjects are only unique per issuer
+        let result = store.get_linked_email("https://other.example.com", "subject").await;
+        assert_eq!(result, Err(FederatedIdentityStoreError::IdentityNotFound));
+    }
+}
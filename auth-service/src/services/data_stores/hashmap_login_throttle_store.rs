@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use chrono::Utc;
+
+use crate::domain::data_stores::{LoginThrottleStore, LoginThrottleStoreError};
+
+#[derive(Default)]
+pub struct HashmapLoginThrottleStore {
+    // Failure count and the timestamp the counter expires at
+    failures: HashMap<String, (u32, i64)>,
+    // Timestamp the lockout ends at
+    lockouts: HashMap<String, i64>,
+}
+
+#[async_trait::async_trait]
+impl LoginThrottleStore for HashmapLoginThrottleStore {
+    async fn record_failure(&mut self, key: &str, window_seconds: u64) -> Result<u32, LoginThrottleStoreError> {
+        let now = Utc::now().timestamp();
+        let expires_at = now + window_seconds as i64;
+
+        let failures = match self.failures.get(key) {
+            Some((count, counter_expires_at)) if *counter_expires_at > now => count + 1,
+            _ => 1,
+        };
+
+        self.failures.insert(key.to_owned(), (failures, expires_at));
+        Ok(failures)
+    }
+
+    async fn lock(&mut self, key: &str, seconds: u64) -> Result<(), LoginThrottleStoreError> {
+        let locked_until = Utc::now().timestamp() + seconds as i64;
+        self.lockouts.insert(key.to_owned(), locked_until);
+        Ok(())
+    }
+
+    async fn get_lockout(&self, key: &str) -> Result<Option<u64>, LoginThrottleStoreError> {
+        let now = Utc::now().timestamp();
+        match self.lockouts.get(key) {
+            Some(locked_until) if *locked_until > now => Ok(Some((locked_until - now) as u64)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn clear(&mut self, key: &str) -> Result<(), LoginThrottleStoreError> {
+        self.failures.remove(key);
+        self.lockouts.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_failure() {
+        let mut store = HashmapLoginThrottleStore::default();
+
+        assert_eq!(store.record_failure("email:test@example.com", 60).await, Ok(1));
+        assert_eq!(store.record_failure("email:test@example.com", 60).await, Ok(2));
+        assert_eq!(store.record_failure("ip:127.0.0.1", 60).await, Ok(1));
+
+        // An expired counter starts over
+        store.failures.insert("ip:127.0.0.1".to_owned(), (10, Utc::now().timestamp() - 1));
+        assert_eq!(store.record_failure("ip:127.0.0.1", 60).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_lock_and_clear() {
+        let mut store = HashmapLoginThrottleStore::default();
+        let key = "email:test@example.com";
+
+        assert_eq!(store.get_lockout(key).await, Ok(None));
+
+        store.record_failure(key, 60).await.unwrap();
+        store.lock(key, 30).await.unwrap();
+        let lockout = store.get_lockout(key).await.unwrap().expect("Key should be locked");
+        assert!(lockout > 0 && lockout <= 30);
+
+        store.clear(key).await.unwrap();
+        assert_eq!(store.get_lockout(key).await, Ok(None));
+        assert_eq!(store.record_failure(key, 60).await, Ok(1));
+    }
+}
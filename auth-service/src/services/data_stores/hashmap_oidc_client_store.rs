@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{OidcClientStore, OidcClientStoreError},
+    OidcClient,
+};
+
+#[derive(Default)]
+pub struct HashmapOidcClientStore {
+    clients: HashMap<String, OidcClient>,
+}
+
+#[async_trait::async_trait]
+impl OidcClientStore for HashmapOidcClientStore {
+    async fn add_client(&mut self, client: OidcClient) -> Result<(), OidcClientStoreError> {
+        self.clients.insert(client.client_id.clone(), client);
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OidcClient, OidcClientStoreError> {
+        self.clients
+            .get(client_id)
+            .cloned()
+            .ok_or(OidcClientStoreError::ClientNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashmapOidcClientStore::default();
+        let (client, _) = OidcClient::register(
+            "Test app".to_owned(),
+            vec!["https://app.example.com/callback".to_owned()],
+            true,
+        )
+        .unwrap();
+
+        let result = store.add_client(client.clone()).await;
+        assert!(result.is_ok());
+
+        let result = store.get_client(&client.client_id).await;
+        assert_eq!(result.unwrap(), client);
+
+        let result = store.get_client("unknown-client").await;
+        assert_eq!(result, Err(OidcClientStoreError::ClientNotFound));
+    }
+}
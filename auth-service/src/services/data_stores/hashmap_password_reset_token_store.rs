@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use chrono::Utc;
+
+use crate::{
+    domain::{
+        data_stores::{PasswordResetTokenStore, PasswordResetTokenStoreError},
+        Email, PasswordResetToken,
+    },
+    utils::auth::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashmapPasswordResetTokenStore {
+    // Each token is stored together with its owner and expiry timestamp
+    tokens: HashMap<PasswordResetToken, (Email, i64)>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let expires_at = Utc::now().timestamp() + PASSWORD_RESET_TOKEN_TTL_SECONDS;
+        self.tokens.insert(token, (email, expires_at));
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        match self.tokens.remove(token) {
+            Some((email, expires_at)) if expires_at > Utc::now().timestamp() => Ok(email),
+            _ => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_consume_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = PasswordResetToken::default();
+
+        let result = store.add_token(email.clone(), token.clone()).await;
+        assert!(result.is_ok());
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(result.unwrap(), email);
+
+        // A reset token can only be used once
+        let result = store.consume_token(&token).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_consume_expired_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = PasswordResetToken::default();
+
+        store.tokens.insert(token.clone(), (email, Utc::now().timestamp() - 1));
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+}
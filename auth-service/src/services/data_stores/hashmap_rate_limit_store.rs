@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use chrono::Utc;
+
+use crate::domain::{
+    data_stores::{RateLimitStore, RateLimitStoreError},
+    RateLimit, TokenBucket,
+};
+
+// In memory fallback for tests, buckets only live in this process so replicas would each allow the full limit
+#[derive(Default)]
+pub struct HashmapRateLimitStore {
+    buckets: HashMap<String, TokenBucket>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn take_token(&mut self, key: &str, limit: RateLimit) -> Result<Option<u64>, RateLimitStoreError> {
+        let now_ms = Utc::now().timestamp_millis();
+
+        let (bucket, retry_after) = limit.take_token(self.buckets.get(key).copied(), now_ms);
+        self.buckets.insert(key.to_owned(), bucket);
+
+        Ok(retry_after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_take_token() {
+        let mut store = HashmapRateLimitStore::default();
+        let limit = RateLimit::new(2, 60).unwrap();
+
+        assert_eq!(store.take_token("/login:127.0.0.1", limit).await, Ok(None));
+        assert_eq!(store.take_token("/login:127.0.0.1", limit).await, Ok(None));
+        let retry_after = store
+            .take_token("/login:127.0.0.1", limit)
+            .await
+            .unwrap()
+            .expect("Bucket should be empty");
+        assert!(retry_after > 0 && retry_after <= 30);
+
+        // Every client and route has a bucket of its own
+        assert_eq!(store.take_token("/login:10.0.0.1", limit).await, Ok(None));
+        assert_eq!(store.take_token("/signup:127.0.0.1", limit).await, Ok(None));
+    }
+}
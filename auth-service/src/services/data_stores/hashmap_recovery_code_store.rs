@@ -0,0 +1,79 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::{
+    data_stores::{RecoveryCodeStore, RecoveryCodeStoreError},
+    Email, RecoveryCode,
+};
+
+#[derive(Default)]
+pub struct HashmapRecoveryCodeStore {
+    // Hashes of the unused codes of every user
+    codes: HashMap<Email, HashSet<String>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn set_codes(&mut self, email: &Email, codes: &[RecoveryCode]) -> Result<(), RecoveryCodeStoreError> {
+        let hashes = codes.iter().map(RecoveryCode::hash).collect();
+        self.codes.insert(email.clone(), hashes);
+        Ok(())
+    }
+
+    async fn use_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), RecoveryCodeStoreError> {
+        let removed = self
+            .codes
+            .get_mut(email)
+            .is_some_and(|hashes| hashes.remove(&code.hash()));
+
+        if removed {
+            Ok(())
+        } else {
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_use_code() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let codes = RecoveryCode::generate_set();
+
+        assert_eq!(store.set_codes(&email, &codes).await, Ok(()));
+        assert_eq!(store.use_code(&email, &codes[0]).await, Ok(()));
+
+        // Every code works only once and only for its owner
+        assert_eq!(
+            store.use_code(&email, &codes[0]).await,
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        );
+        assert_eq!(
+            store
+                .use_code(&Email::parse("other@example.com".to_owned()).unwrap(), &codes[1])
+                .await,
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        );
+        assert_eq!(store.use_code(&email, &codes[1]).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_set_codes_replaces_old_codes() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let old_codes = RecoveryCode::generate_set();
+        let new_codes = RecoveryCode::generate_set();
+
+        store.set_codes(&email, &old_codes).await.unwrap();
+        store.set_codes(&email, &new_codes).await.unwrap();
+
+        assert_eq!(
+            store.use_code(&email, &old_codes[0]).await,
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        );
+        assert_eq!(store.use_code(&email, &new_codes[0]).await, Ok(()));
+    }
+}
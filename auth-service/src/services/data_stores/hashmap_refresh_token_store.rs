@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+use chrono::Utc;
+
+use crate::{
+    domain::{
+        data_stores::{RefreshTokenStore, RefreshTokenStoreError},
+        Email, RefreshToken,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct RefreshTokenRecord {
+    email: Email,
+    family_id: String,
+    used: bool,
+    expires_at: i64,
+}
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    tokens: HashMap<RefreshToken, RefreshTokenRecord>,
+    revoked_families: HashSet<String>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        family_id: String,
+        token: RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let expires_at = Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECONDS;
+        self.tokens.insert(token, RefreshTokenRecord { email, family_id, used: false, expires_at });
+        Ok(())
+    }
+
+    async fn use_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, String), RefreshTokenStoreError> {
+        // Expired tokens are gone, just like the keys Redis expires
+        let record = match self.tokens.get_mut(token) {
+            Some(record) if record.expires_at > Utc::now().timestamp() => record,
+            Some(_) => {
+                self.tokens.remove(token);
+                return Err(RefreshTokenStoreError::TokenNotFound);
+            }
+            None => return Err(RefreshTokenStoreError::TokenNotFound),
+        };
+
+        if self.revoked_families.contains(&record.family_id) {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        }
+
+        if record.used {
+            self.revoked_families.insert(record.family_id.clone());
+            return Err(RefreshTokenStoreError::TokenReused);
+        }
+
+        record.used = true;
+        Ok((record.email.clone(), record.family_id.clone()))
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        self.revoked_families.insert(family_id.to_owned());
+        Ok(())
+    }
+
+    async fn revoke_user_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let families = self
+            .tokens
+            .values()
+            .filter(|record| record.email.eq(email))
+            .map(|record| record.family_id.clone());
+        self.revoked_families.extend(families);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = RefreshToken::default();
+
+        let result = store
+            .add_token(email.clone(), "family".to_owned(), token.clone())
+            .await;
+
+        assert!(result.is_ok());
+        let record = store.tokens.get(&token).unwrap();
+        assert_eq!((&record.email, record.family_id.as_str(), record.used), (&email, "family", false));
+        assert!(record.expires_at > Utc::now().timestamp());
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_rejected() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = RefreshToken::default();
+
+        store.add_token(email, "family".to_owned(), token.clone()).await.unwrap();
+        store.tokens.get_mut(&token).unwrap().expires_at = Utc::now().timestamp();
+
+        let result = store.use_token(&token).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+        assert!(!store.tokens.contains_key(&token));
+    }
+
+    #[tokio::test]
+    async fn test_use_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = RefreshToken::default();
+
+        store.add_token(email.clone(), "family".to_owned(), token.clone()).await.unwrap();
+
+        let result = store.use_token(&token).await;
+        assert_eq!(result.unwrap(), (email, "family".to_owned()));
+
+        let result = store.use_token(&RefreshToken::default()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_reused_token_revokes_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = RefreshToken::default();
+        let rotated_token = RefreshToken::default();
+
+        store.add_token(email.clone(), "family".to_owned(), token.clone()).await.unwrap();
+        store.use_token(&token).await.unwrap();
+        store.add_token(email, "family".to_owned(), rotated_token.clone()).await.unwrap();
+
+        let result = store.use_token(&token).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenReused));
+
+        let result = store.use_token(&rotated_token).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = RefreshToken::default();
+
+        store.add_token(email, "family".to_owned(), token.clone()).await.unwrap();
+        store.revoke_family("family").await.unwrap();
+
+        let result = store.use_token(&token).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_user_families() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other_email = Email::parse("other@example.com".to_owned()).unwrap();
+        let token = RefreshToken::default();
+        let other_token = RefreshToken::default();
+
+        store.add_token(email.clone(), "family".to_owned(), token.clone()).await.unwrap();
+        store.add_token(other_email, "other_family".to_owned(), other_token.clone()).await.unwrap();
+        store.revoke_user_families(&email).await.unwrap();
+
+        let result = store.use_token(&token).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+
+        let result = store.use_token(&other_token).await;
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use chrono::Utc;
+
+use crate::{
+    domain::{
+        data_stores::{SessionStore, SessionStoreError},
+        Email, Session,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    // Sessions and the timestamp they expire at
+    sessions: HashMap<String, (Session, i64)>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let expires_at = Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECONDS;
+        self.sessions.insert(session.id.clone(), (session, expires_at));
+        Ok(())
+    }
+
+    async fn get_session(&self, session_id: &str) -> Result<Session, SessionStoreError> {
+        match self.sessions.get(session_id) {
+            Some((session, expires_at)) if *expires_at > Utc::now().timestamp() => Ok(session.clone()),
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let now = Utc::now().timestamp();
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|(session, expires_at)| session.email == *email && *expires_at > now)
+            .map(|(session, _)| session.clone())
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    async fn remove_session(&mut self, session_id: &str) -> Result<(), SessionStoreError> {
+        match self.sessions.remove(session_id) {
+            Some(_) => Ok(()),
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        self.sessions.retain(|_, (session, _)| session.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn session(email: &Email) -> Session {
+        Session::new(email.clone(), IpAddr::V4(Ipv4Addr::LOCALHOST), Some("test-agent".to_owned()))
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_session() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let session = session(&email);
+
+        assert_eq!(store.add_session(session.clone()).await, Ok(()));
+        assert_eq!(store.get_session(&session.id).await, Ok(session.clone()));
+        assert_eq!(store.get_user_sessions(&email).await, Ok(vec![session]));
+
+        assert_eq!(
+            store.get_session("unknown").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_session() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let first = session(&email);
+        let second = session(&email);
+
+        store.add_session(first.clone()).await.unwrap();
+        store.add_session(second.clone()).await.unwrap();
+
+        assert_eq!(store.remove_session(&first.id).await, Ok(()));
+        assert_eq!(store.get_session(&first.id).await, Err(SessionStoreError::SessionNotFound));
+        assert_eq!(store.get_user_sessions(&email).await, Ok(vec![second]));
+
+        assert_eq!(
+            store.remove_session(&first.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other_email = Email::parse("other@example.com".to_owned()).unwrap();
+        let other_session = session(&other_email);
+
+        store.add_session(session(&email)).await.unwrap();
+        store.add_session(session(&email)).await.unwrap();
+        store.add_session(other_session.clone()).await.unwrap();
+
+        assert_eq!(store.remove_user_sessions(&email).await, Ok(()));
+        assert_eq!(store.get_user_sessions(&email).await, Ok(vec![]));
+        assert_eq!(store.get_user_sessions(&other_email).await, Ok(vec![other_session]));
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{TotpStore, TotpStoreError},
+    Email, TotpSecret,
+};
+
+struct TotpRecord {
+    secret: TotpSecret,
+    enabled: bool,
+    last_used_step: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct HashmapTotpStore {
+    secrets: HashMap<Email, TotpRecord>,
+}
+
+#[async_trait::async_trait]
+impl TotpStore for HashmapTotpStore {
+    async fn set_secret(&mut self, email: Email, secret: TotpSecret) -> Result<(), TotpStoreError> {
+        let record = TotpRecord {
+            secret,
+            enabled: false,
+            last_used_step: None,
+        };
+        self.secrets.insert(email, record);
+        Ok(())
+    }
+
+    async fn get_secret(&self, email: &Email) -> Result<(TotpSecret, bool), TotpStoreError> {
+        match self.secrets.get(email) {
+            Some(record) => Ok((record.secret.clone(), record.enabled)),
+            None => Err(TotpStoreError::SecretNotFound),
+        }
+    }
+
+    async fn enable(&mut self, email: &Email) -> Result<(), TotpStoreError> {
+        match self.secrets.get_mut(email) {
+            Some(record) => {
+                record.enabled = true;
+                Ok(())
+            }
+            None => Err(TotpStoreError::SecretNotFound),
+        }
+    }
+
+    async fn use_time_step(&mut self, email: &Email, time_step: u64) -> Result<(), TotpStoreError> {
+        let record = self
+            .secrets
+            .get_mut(email)
+            .ok_or(TotpStoreError::SecretNotFound)?;
+
+        if record.last_used_step.is_some_and(|last| last >= time_step) {
+            return Err(TotpStoreError::CodeReused);
+        }
+
+        record.last_used_step = Some(time_step);
+        Ok(())
+    }
+
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpStoreError> {
+        match self.secrets.remove(email) {
+            Some(_) => Ok(()),
+            None => Err(TotpStoreError::SecretNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_and_get_secret() {
+        let mut store = HashmapTotpStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let secret = TotpSecret::default();
+
+        let result = store.get_secret(&email).await;
+        assert_eq!(result, Err(TotpStoreError::SecretNotFound));
+
+        store.set_secret(email.clone(), secret.clone()).await.unwrap();
+        assert_eq!(store.get_secret(&email).await, Ok((secret.clone(), false)));
+
+        store.enable(&email).await.unwrap();
+        assert_eq!(store.get_secret(&email).await, Ok((secret, true)));
+
+        // Enrolling again replaces the secret and requires a new confirmation
+        let new_secret = TotpSecret::default();
+        store.set_secret(email.clone(), new_secret.clone()).await.unwrap();
+        assert_eq!(store.get_secret(&email).await, Ok((new_secret, false)));
+    }
+
+    #[tokio::test]
+    async fn test_use_time_step() {
+        let mut store = HashmapTotpStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        let result = store.use_time_step(&email, 10).await;
+        assert_eq!(result, Err(TotpStoreError::SecretNotFound));
+
+        store.set_secret(email.clone(), TotpSecret::default()).await.unwrap();
+
+        assert_eq!(store.use_time_step(&email, 10).await, Ok(()));
+        assert_eq!(store.use_time_step(&email, 10).await, Err(TotpStoreError::CodeReused));
+        assert_eq!(store.use_time_step(&email, 9).await, Err(TotpStoreError::CodeReused));
+        assert_eq!(store.use_time_step(&email, 11).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_remove_secret() {
+        let mut store = HashmapTotpStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        store.set_secret(email.clone(), TotpSecret::default()).await.unwrap();
+        store.enable(&email).await.unwrap();
+
+        assert_eq!(store.remove_secret(&email).await, Ok(()));
+        assert_eq!(store.get_secret(&email).await, Err(TotpStoreError::SecretNotFound));
+        assert_eq!(store.remove_secret(&email).await, Err(TotpStoreError::SecretNotFound));
+    }
+}
@@ -10,6 +10,7 @@ use crate::domain::{
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
     codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    failed_attempts: HashMap<LoginAttemptId, u32>,
 }
 
 // implement TwoFACodeStore for HashmapTwoFACodeStore
@@ -21,8 +22,10 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        
-        self.codes.insert(email,(login_attempt_id,code));
+        // The counter of a replaced login attempt can never be used again
+        if let Some((replaced_id, _)) = self.codes.insert(email, (login_attempt_id, code)) {
+            self.failed_attempts.remove(&replaced_id);
+        }
         Ok(())
     }
 
@@ -40,11 +43,38 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
 
    
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.remove(&email) {
-            Some(_) => Ok(()),
+        match self.codes.remove(email) {
+            Some((login_attempt_id, _)) => {
+                self.failed_attempts.remove(&login_attempt_id);
+                Ok(())
+            }
             None    => Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
         }
     }
+
+    async fn consume_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        match self.codes.get(email) {
+            Some((stored_id, _)) if stored_id == login_attempt_id => {
+                self.codes.remove(email);
+                self.failed_attempts.remove(login_attempt_id);
+                Ok(())
+            }
+            _ => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
+    }
+
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let attempts = self.failed_attempts.entry(login_attempt_id.clone()).or_insert(0);
+        *attempts += 1;
+        Ok(*attempts)
+    }
 }
 
 
@@ -94,4 +124,53 @@ mod tests {
         let result = two_fa_store.get_code(&code.0).await;        
         assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound ));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_consume_code() {
+        let mut two_fa_store = HashmapTwoFACodeStore::default();
+        let email = Email::parse("test@xy.com".to_owned()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+
+        two_fa_store.add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default()).await.unwrap();
+
+        // A code of another login attempt is left alone
+        let result = two_fa_store.consume_code(&email, &LoginAttemptId::default()).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+        let result = two_fa_store.consume_code(&email, &login_attempt_id).await;
+        assert_eq!(result, Ok(()));
+
+        // A code can only be consumed once
+        let result = two_fa_store.consume_code(&email, &login_attempt_id).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_record_failed_attempt() {
+        let mut two_fa_store = HashmapTwoFACodeStore::default();
+        let login_attempt_id = LoginAttemptId::default();
+
+        assert_eq!(two_fa_store.record_failed_attempt(&login_attempt_id).await, Ok(1));
+        assert_eq!(two_fa_store.record_failed_attempt(&login_attempt_id).await, Ok(2));
+        assert_eq!(two_fa_store.record_failed_attempt(&LoginAttemptId::default()).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_failed_attempts_are_dropped_with_their_code() {
+        let mut two_fa_store = HashmapTwoFACodeStore::default();
+        let email = Email::parse("test@xy.com".to_owned()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+
+        two_fa_store.add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default()).await.unwrap();
+        two_fa_store.record_failed_attempt(&login_attempt_id).await.unwrap();
+
+        // A new login attempt replaces the code, the abandoned attempt's counter goes with it
+        let next_attempt_id = LoginAttemptId::default();
+        two_fa_store.add_code(email.clone(), next_attempt_id.clone(), TwoFACode::default()).await.unwrap();
+        assert!(!two_fa_store.failed_attempts.contains_key(&login_attempt_id));
+
+        two_fa_store.record_failed_attempt(&next_attempt_id).await.unwrap();
+        two_fa_store.remove_code(&email).await.unwrap();
+        assert!(two_fa_store.failed_attempts.is_empty());
+    }
+}
@@ -1,25 +1,61 @@
-use std::collections::HashMap;
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use std::{collections::HashMap, sync::Arc};
+use chrono::Utc;
+use crate::{
+    domain::{Email, Password, PasswordHash, PasswordHasherType, Role, User, UserStore, UserStoreError},
+    services::argon2_password_hasher::Argon2PasswordHasher,
+};
 
 
 
 //  Create a new struct called `HashmapUserStore` containing a `users` field
 // which stores a `HashMap`` of email `String`s mapped to `User` objects.
-// Derive the `Default` trait for `HashmapUserStore`.
-#[derive(Default)]
+// Passwords are hashed just like in the database, so tests and demos exercise the real thing.
+// Hashes never outlive the process and can not fall behind the hasher's settings, so none are rehashed.
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: HashMap<Email, (User, PasswordHash)>,
+    // Soft deleted users and the timestamp they can be restored until
+    deleted_users: HashMap<Email, (User, PasswordHash, i64)>,
+    password_hasher: PasswordHasherType,
+}
+
+impl HashmapUserStore {
+    pub fn new(password_hasher: PasswordHasherType) -> Self {
+        Self {
+            users: HashMap::new(),
+            deleted_users: HashMap::new(),
+            password_hasher,
+        }
+    }
+
+    async fn verify_password(&self, password: &Password, password_hash: &PasswordHash) -> Result<(), UserStoreError> {
+        match self.password_hasher.verify(password, password_hash).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(UserStoreError::InvalidCredentials),
+            Err(e) => Err(UserStoreError::UnexpectedError(e)),
+        }
+    }
+}
+
+impl Default for HashmapUserStore {
+    fn default() -> Self {
+        Self::new(Arc::new(Argon2PasswordHasher::default()))
+    }
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&mut self, user: User, password: Password) -> Result<(), UserStoreError> {
         // Return `UserStoreError::UserAlreadyExists` if the user already exists,
         // otherwise insert the user into the hashmap and return `Ok(())`.
-        if self.users.contains_key(&user.email) {
+        if self.users.contains_key(&user.email) || self.deleted_users.contains_key(&user.email) {
             return Err(UserStoreError::UserAlreadyExists);
         }
-        self.users.insert(user.email.clone(), user);
+        let password_hash = self
+            .password_hasher
+            .hash(&password)
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+        self.users.insert(user.email.clone(), (user, password_hash));
         Ok(())
     }
 
@@ -31,7 +67,7 @@ impl UserStore for HashmapUserStore {
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         match self.users.get(email) {
-            Some(user) => Ok(user.clone()),
+            Some((user, _)) => Ok(user.clone()),
             None => Err(UserStoreError::UserNotFound),
         }
     }
@@ -44,16 +80,131 @@ impl UserStore for HashmapUserStore {
     // Return `UserStoreError::InvalidCredentials` if the password is incorrect.
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError> {
         match self.users.get(email) {
-            Some(user) => {
-                if user.password.eq(password) {
-                    Ok(())
-                } else {
-                    Err(UserStoreError::InvalidCredentials)
-                }
+            Some((_, password_hash)) => self.verify_password(password, password_hash).await,
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let password_hash = self
+            .password_hasher
+            .hash(&password)
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        match self.users.get_mut(email) {
+            Some((_, stored_hash)) => {
+                *stored_hash = password_hash;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some((user, _)) => {
+                user.verified = true;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some((user, _)) => {
+                user.requires_2fa = requires_2fa;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_roles(&mut self, email: &Email, roles: Vec<Role>) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some((user, _)) => {
+                user.roles = roles;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some((user, _)) => {
+                user.disabled = disabled;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn list_users(&self, search: Option<&str>, offset: u64, limit: u64) -> Result<(Vec<User>, u64), UserStoreError> {
+        let search = search.map(str::to_lowercase);
+        let mut users: Vec<&User> = self
+            .users
+            .values()
+            .map(|(user, _)| user)
+            .filter(|user| match &search {
+                Some(search) => user.email.as_ref().to_lowercase().contains(search),
+                None => true,
+            })
+            .collect();
+        users.sort_by(|a, b| a.email.as_ref().cmp(b.email.as_ref()));
+
+        let total = users.len() as u64;
+        let page = users
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+        Ok((page, total))
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.remove(email) {
+            Some(_) => Ok(()),
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn soft_delete_user(&mut self, email: &Email, grace_period_seconds: u64) -> Result<(), UserStoreError> {
+        match self.users.remove(email) {
+            Some((user, password)) => {
+                let restorable_until = Utc::now().timestamp() + grace_period_seconds as i64;
+                self.deleted_users.insert(email.clone(), (user, password, restorable_until));
+                Ok(())
             }
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn restore_user(&mut self, email: &Email, password: &Password) -> Result<(), UserStoreError> {
+        match self.deleted_users.get(email) {
+            Some((_, _, restorable_until)) if *restorable_until <= Utc::now().timestamp() => {
+                return Err(UserStoreError::UserNotFound)
+            }
+            Some((_, password_hash, _)) => self.verify_password(password, password_hash).await?,
+            None => return Err(UserStoreError::UserNotFound),
+        }
+
+        let (user, password_hash, _) = self.deleted_users.remove(email).expect("tombstone exists");
+        self.users.insert(email.clone(), (user, password_hash));
+        Ok(())
+    }
+
+    async fn purge_deleted_users(&mut self) -> Result<u64, UserStoreError> {
+        let now = Utc::now().timestamp();
+        let count = self.deleted_users.len();
+        self.deleted_users.retain(|_, (_, _, restorable_until)| *restorable_until > now);
+        Ok((count - self.deleted_users.len()) as u64)
+    }
 }
 
 //Add unit tests for your `HashmapUserStore` implementation
@@ -65,18 +216,21 @@ impl UserStore for HashmapUserStore {
         #[tokio::test]
         async fn test_add_user() {
             let mut user_store = HashmapUserStore::default();
+            let password = Password::parse("password".to_owned()).unwrap();
             let user = User {
                 email: Email::parse("test@example.com".to_owned()).unwrap(),
-                password: Password::parse("password".to_owned()).unwrap(),
                 requires_2fa: false,
+                verified: false,
+                roles: vec![Role::User],
+                disabled: false,
             };
     
             // Test adding a new user
-            let result = user_store.add_user(user.clone()).await;
+            let result = user_store.add_user(user.clone(), password.clone()).await;
             assert!(result.is_ok());
     
             // Test adding an existing user
-            let result = user_store.add_user(user).await;
+            let result = user_store.add_user(user, password).await;
             assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
         }
     
@@ -87,12 +241,14 @@ impl UserStore for HashmapUserStore {
     
             let user = User {
                 email: email.clone(),
-                password: Password::parse("password".to_owned()).unwrap(),
                 requires_2fa: false,
+                verified: false,
+                roles: vec![Role::User],
+                disabled: false,
             };
     
             // Test getting a user that exists
-            user_store.users.insert(email.clone(), user.clone());
+            user_store.add_user(user.clone(), Password::parse("password".to_owned()).unwrap()).await.unwrap();
             let result = user_store.get_user(&email).await;
             assert_eq!(result, Ok(user));
     
@@ -112,12 +268,14 @@ impl UserStore for HashmapUserStore {
     
             let user = User {
                 email: email.clone(),
-                password: password.clone(),
                 requires_2fa: false,
+                verified: false,
+                roles: vec![Role::User],
+                disabled: false,
             };
     
             // Test validating a user that exists with correct password
-            user_store.users.insert(email.clone(), user.clone());
+            user_store.add_user(user, password.clone()).await.unwrap();
             let result = user_store.validate_user(&email, &password).await;
             assert_eq!(result, Ok(()));
     
@@ -136,4 +294,235 @@ impl UserStore for HashmapUserStore {
     
             assert_eq!(result, Err(UserStoreError::UserNotFound));
         }
+
+        #[tokio::test]
+        async fn test_passwords_are_hashed() {
+            let mut user_store = HashmapUserStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let password = Password::parse("password".to_owned()).unwrap();
+
+            user_store.add_user(User::new(email.clone(), false), password.clone()).await.unwrap();
+
+            let (_, password_hash) = user_store.users.get(&email).unwrap();
+            assert!(password_hash.as_ref().starts_with("$argon2id$"));
+            assert!(!password_hash.as_ref().contains(password.as_ref()));
+        }
+
+        #[tokio::test]
+        async fn test_update_password() {
+            let mut user_store = HashmapUserStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let password = Password::parse("password".to_owned()).unwrap();
+            let new_password = Password::parse("newpassword".to_owned()).unwrap();
+
+            let user = User {
+                email: email.clone(),
+                requires_2fa: false,
+                verified: false,
+                roles: vec![Role::User],
+                disabled: false,
+            };
+            user_store.add_user(user, password.clone()).await.unwrap();
+
+            // Test updating the password of a user that exists
+            let result = user_store.update_password(&email, new_password.clone()).await;
+            assert_eq!(result, Ok(()));
+            assert_eq!(user_store.validate_user(&email, &new_password).await, Ok(()));
+            assert_eq!(
+                user_store.validate_user(&email, &password).await,
+                Err(UserStoreError::InvalidCredentials)
+            );
+
+            // Test updating the password of a user that doesn't exist
+            let result = user_store
+                .update_password(&Email::parse("nonexistent@example.com".to_owned()).unwrap(), new_password)
+                .await;
+            assert_eq!(result, Err(UserStoreError::UserNotFound));
+        }
+
+        #[tokio::test]
+        async fn test_set_verified() {
+            let mut user_store = HashmapUserStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let user = User::new(email.clone(), false);
+
+            user_store.add_user(user, Password::parse("password".to_owned()).unwrap()).await.unwrap();
+            assert!(!user_store.get_user(&email).await.unwrap().verified);
+
+            let result = user_store.set_verified(&email).await;
+            assert_eq!(result, Ok(()));
+            assert!(user_store.get_user(&email).await.unwrap().verified);
+
+            let result = user_store
+                .set_verified(&Email::parse("nonexistent@example.com".to_owned()).unwrap())
+                .await;
+            assert_eq!(result, Err(UserStoreError::UserNotFound));
+        }
+
+        #[tokio::test]
+        async fn test_set_requires_2fa() {
+            let mut user_store = HashmapUserStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let user = User::new(email.clone(), false);
+
+            user_store.add_user(user, Password::parse("password".to_owned()).unwrap()).await.unwrap();
+
+            assert_eq!(user_store.set_requires_2fa(&email, true).await, Ok(()));
+            assert!(user_store.get_user(&email).await.unwrap().requires_2fa);
+
+            assert_eq!(user_store.set_requires_2fa(&email, false).await, Ok(()));
+            assert!(!user_store.get_user(&email).await.unwrap().requires_2fa);
+
+            let result = user_store
+                .set_requires_2fa(&Email::parse("nonexistent@example.com".to_owned()).unwrap(), true)
+                .await;
+            assert_eq!(result, Err(UserStoreError::UserNotFound));
+        }
+
+        #[tokio::test]
+        async fn test_set_roles() {
+            let mut user_store = HashmapUserStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let user = User::new(email.clone(), false);
+
+            user_store.add_user(user, Password::parse("password".to_owned()).unwrap()).await.unwrap();
+            assert_eq!(user_store.get_user(&email).await.unwrap().roles, vec![Role::User]);
+
+            let result = user_store.set_roles(&email, vec![Role::User, Role::Admin]).await;
+            assert_eq!(result, Ok(()));
+            assert_eq!(
+                user_store.get_user(&email).await.unwrap().roles,
+                vec![Role::User, Role::Admin]
+            );
+
+            let result = user_store
+                .set_roles(&Email::parse("nonexistent@example.com".to_owned()).unwrap(), vec![Role::Admin])
+                .await;
+            assert_eq!(result, Err(UserStoreError::UserNotFound));
+        }
+
+        #[tokio::test]
+        async fn test_set_disabled() {
+            let mut user_store = HashmapUserStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let user = User::new(email.clone(), false);
+
+            user_store.add_user(user, Password::parse("password".to_owned()).unwrap()).await.unwrap();
+            assert!(!user_store.get_user(&email).await.unwrap().disabled);
+
+            assert_eq!(user_store.set_disabled(&email, true).await, Ok(()));
+            assert!(user_store.get_user(&email).await.unwrap().disabled);
+
+            assert_eq!(user_store.set_disabled(&email, false).await, Ok(()));
+            assert!(!user_store.get_user(&email).await.unwrap().disabled);
+
+            let result = user_store
+                .set_disabled(&Email::parse("nonexistent@example.com".to_owned()).unwrap(), true)
+                .await;
+            assert_eq!(result, Err(UserStoreError::UserNotFound));
+        }
+
+        #[tokio::test]
+        async fn test_list_users() {
+            let mut user_store = HashmapUserStore::default();
+            let password = Password::parse("password".to_owned()).unwrap();
+
+            for email in ["carol@example.com", "alice@example.com", "bob@test.com", "dave@Example.com"] {
+                let user = User::new(Email::parse(email.to_owned()).unwrap(), false);
+                user_store.add_user(user, password.clone()).await.unwrap();
+            }
+
+            let emails = |users: Vec<User>| -> Vec<String> {
+                users.into_iter().map(|user| user.email.as_ref().to_owned()).collect()
+            };
+
+            let (users, total) = user_store.list_users(None, 0, 2).await.unwrap();
+            assert_eq!(total, 4);
+            assert_eq!(emails(users), vec!["alice@example.com", "bob@test.com"]);
+
+            let (users, total) = user_store.list_users(None, 2, 2).await.unwrap();
+            assert_eq!(total, 4);
+            assert_eq!(emails(users), vec!["carol@example.com", "dave@Example.com"]);
+
+            // The search ignores case
+            let (users, total) = user_store.list_users(Some("EXAMPLE"), 1, 10).await.unwrap();
+            assert_eq!(total, 3);
+            assert_eq!(emails(users), vec!["carol@example.com", "dave@Example.com"]);
+
+            let (users, total) = user_store.list_users(Some("nobody"), 0, 10).await.unwrap();
+            assert_eq!(total, 0);
+            assert!(users.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_delete_user() {
+            let mut user_store = HashmapUserStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let password = Password::parse("password".to_owned()).unwrap();
+            let user = User::new(email.clone(), false);
+
+            user_store.add_user(user.clone(), password.clone()).await.unwrap();
+            assert_eq!(user_store.delete_user(&email).await, Ok(()));
+            assert_eq!(user_store.get_user(&email).await, Err(UserStoreError::UserNotFound));
+            assert_eq!(user_store.delete_user(&email).await, Err(UserStoreError::UserNotFound));
+
+            // The email address is free again
+            assert_eq!(user_store.add_user(user, password).await, Ok(()));
+        }
+
+        #[tokio::test]
+        async fn test_soft_delete_and_restore_user() {
+            let mut user_store = HashmapUserStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let password = Password::parse("password".to_owned()).unwrap();
+            let user = User::new(email.clone(), false);
+
+            user_store.add_user(user.clone(), password.clone()).await.unwrap();
+            assert_eq!(user_store.soft_delete_user(&email, 60).await, Ok(()));
+
+            // A deleted user is hidden but still holds on to the email address
+            assert_eq!(user_store.get_user(&email).await, Err(UserStoreError::UserNotFound));
+            assert_eq!(user_store.validate_user(&email, &password).await, Err(UserStoreError::UserNotFound));
+            assert_eq!(
+                user_store.add_user(user.clone(), password.clone()).await,
+                Err(UserStoreError::UserAlreadyExists)
+            );
+
+            let wrong_password = Password::parse("wrongpassword".to_owned()).unwrap();
+            assert_eq!(
+                user_store.restore_user(&email, &wrong_password).await,
+                Err(UserStoreError::InvalidCredentials)
+            );
+
+            assert_eq!(user_store.restore_user(&email, &password).await, Ok(()));
+            assert_eq!(user_store.get_user(&email).await, Ok(user));
+            assert_eq!(
+                user_store.restore_user(&email, &password).await,
+                Err(UserStoreError::UserNotFound)
+            );
+        }
+
+        #[tokio::test]
+        async fn test_purge_deleted_users() {
+            let mut user_store = HashmapUserStore::default();
+            let expired_email = Email::parse("expired@example.com".to_owned()).unwrap();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+            let password = Password::parse("password".to_owned()).unwrap();
+
+            for email in [&expired_email, &email] {
+                let user = User::new(email.clone(), false);
+                user_store.add_user(user, password.clone()).await.unwrap();
+            }
+            user_store.soft_delete_user(&expired_email, 0).await.unwrap();
+            user_store.soft_delete_user(&email, 60).await.unwrap();
+
+            // Tombstones past their grace period can not be restored anymore
+            assert_eq!(
+                user_store.restore_user(&expired_email, &password).await,
+                Err(UserStoreError::UserNotFound)
+            );
+
+            assert_eq!(user_store.purge_deleted_users().await, Ok(1));
+            assert_eq!(user_store.restore_user(&email, &password).await, Ok(()));
+        }
     }
\ No newline at end of file
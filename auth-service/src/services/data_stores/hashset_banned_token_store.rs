@@ -1,9 +1,10 @@
-use std::collections::HashSet;
-use crate::domain::{BannedTokenStore,BannedTokenStoreError};
+use std::collections::{HashMap, HashSet};
+use crate::domain::{BannedTokenStore,BannedTokenStoreError,Email};
 
 #[derive(Default)]
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>
+    tokens: HashSet<String>,
+    generations: HashMap<Email, u64>,
 }
 
 #[async_trait::async_trait]
@@ -17,6 +18,16 @@ impl BannedTokenStore for HashsetBannedTokenStore {
     async fn verify_token_in_banned_store(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
         Ok(self.tokens.contains(token))
     }
+
+    async fn get_token_generation(&self, email: &Email) -> Result<u64, BannedTokenStoreError> {
+        Ok(self.generations.get(email).copied().unwrap_or_default())
+    }
+
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<u64, BannedTokenStoreError> {
+        let generation = self.generations.entry(email.clone()).or_default();
+        *generation += 1;
+        Ok(*generation)
+    }
 }
 
 #[cfg(test)]
@@ -46,4 +57,19 @@ impl BannedTokenStore for HashsetBannedTokenStore {
             let result = banned_token_store.verify_token_in_banned_store(&token).await;
             assert!(result.is_ok());
         }
+
+        #[tokio::test]
+        async fn test_increment_token_generation() {
+            let mut banned_token_store = HashsetBannedTokenStore::default();
+            let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+            let result = banned_token_store.get_token_generation(&email).await;
+            assert_eq!(result.unwrap(), 0);
+
+            let result = banned_token_store.increment_token_generation(&email).await;
+            assert_eq!(result.unwrap(), 1);
+
+            let result = banned_token_store.get_token_generation(&email).await;
+            assert_eq!(result.unwrap(), 1);
+        }
     }
\ No newline at end of file
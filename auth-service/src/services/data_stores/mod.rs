@@ -1,6 +1,28 @@
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
 pub mod hashmap_two_fa_code_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_password_reset_token_store;
+pub mod hashmap_totp_store;
+pub mod hashmap_login_throttle_store;
+pub mod hashmap_session_store;
+pub mod hashmap_recovery_code_store;
+pub mod hashmap_oidc_client_store;
+pub mod hashmap_authorization_code_store;
+pub mod hashmap_federated_identity_store;
+pub mod hashmap_api_key_store;
+pub mod hashmap_rate_limit_store;
 pub mod postgres_user_store;
+pub mod postgres_totp_store;
+pub mod postgres_recovery_code_store;
+pub mod postgres_oidc_client_store;
+pub mod postgres_federated_identity_store;
+pub mod postgres_api_key_store;
 pub mod redis_banned_token_store;
 pub mod redis_two_fa_code_store;
+pub mod redis_refresh_token_store;
+pub mod redis_password_reset_token_store;
+pub mod redis_login_throttle_store;
+pub mod redis_session_store;
+pub mod redis_authorization_code_store;
+pub mod redis_rate_limit_store;
\ No newline at end of file
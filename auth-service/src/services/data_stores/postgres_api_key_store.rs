@@ -0,0 +1,121 @@
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, Email,
+};
+
+pub struct PostgresApiKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    #[tracing::instrument(name = "Adding API key to PostgreSQL", skip_all)]
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO api_keys (id, email, name, key_hash, scopes, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, to_timestamp($6::BIGINT), to_timestamp($7::BIGINT));
+            "#,
+            key.id,
+            key.email.as_ref(),
+            key.name,
+            key.key_hash,
+            &key.scopes,
+            key.created_at,
+            key.expires_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving API key from PostgreSQL", skip_all)]
+    async fn get_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, ApiKeyStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, email, name, key_hash, scopes,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!",
+                EXTRACT(EPOCH FROM expires_at)::BIGINT AS expires_at
+            FROM api_keys
+            WHERE key_hash = $1;
+            "#,
+            key_hash,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(ApiKeyStoreError::KeyNotFound)?;
+
+        Ok(ApiKey {
+            id: row.id,
+            email: Email::parse(row.email).map_err(ApiKeyStoreError::UnexpectedError)?,
+            name: row.name,
+            key_hash: row.key_hash,
+            scopes: row.scopes,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user API keys from PostgreSQL", skip_all)]
+    async fn get_user_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT id, name, key_hash, scopes,
+                EXTRACT(EPOCH FROM created_at)::BIGINT AS "created_at!",
+                EXTRACT(EPOCH FROM expires_at)::BIGINT AS expires_at
+            FROM api_keys
+            WHERE email = $1
+            ORDER BY created_at;
+            "#,
+            email.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| ApiKey {
+                    id: row.id,
+                    email: email.clone(),
+                    name: row.name,
+                    key_hash: row.key_hash,
+                    scopes: row.scopes,
+                    created_at: row.created_at,
+                    expires_at: row.expires_at,
+                })
+                .collect()
+        })
+    }
+
+    #[tracing::instrument(name = "Removing API key from PostgreSQL", skip_all)]
+    async fn remove_key(&mut self, email: &Email, id: &str) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM api_keys
+            WHERE id = $1 AND email = $2;
+            "#,
+            id,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyNotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{FederatedIdentityStore, FederatedIdentityStoreError},
+    Email,
+};
+
+pub struct PostgresFederatedIdentityStore {
+    pool: PgPool,
+}
+
+impl PostgresFederatedIdentityStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl FederatedIdentityStore for PostgresFederatedIdentityStore {
+    #[tracing::instrument(name = "Linking federated identity in PostgreSQL", skip_all)]
+    async fn link_identity(
+        &mut self,
+        issuer: &str,
+        subject: &str,
+        email: &Email,
+    ) -> Result<(), FederatedIdentityStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO federated_identities (issuer, subject, email)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (issuer, subject) DO UPDATE SET email = EXCLUDED.email;
+            "#,
+            issuer,
+            subject,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| FederatedIdentityStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving federated identity from PostgreSQL", skip_all)]
+    async fn get_linked_email(&self, issuer: &str, subject: &str) -> Result<Email, FederatedIdentityStoreError> {
+        let email = sqlx::query_scalar!(
+            r#"
+            SELECT email
+            FROM federated_identities
+            WHERE issuer = $1 AND subject = $2;
+            "#,
+            issuer,
+            subject,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| FederatedIdentityStoreError::UnexpectedError(e.into()))?
+        .ok_or(FederatedIdentityStoreError::IdentityNotFound)?;
+
+        Email::parse(email).map_err(FederatedIdentityStoreError::UnexpectedError)
+    }
+}
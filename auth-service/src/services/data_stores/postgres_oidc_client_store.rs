@@ -0,0 +1,60 @@
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{OidcClientStore, OidcClientStoreError},
+    OidcClient,
+};
+
+pub struct PostgresOidcClientStore {
+    pool: PgPool,
+}
+
+impl PostgresOidcClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OidcClientStore for PostgresOidcClientStore {
+    #[tracing::instrument(name = "Adding OIDC client to PostgreSQL", skip_all)]
+    async fn add_client(&mut self, client: OidcClient) -> Result<(), OidcClientStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO oidc_clients (client_id, name, redirect_uris, secret_hash)
+            VALUES ($1, $2, $3, $4);
+            "#,
+            client.client_id,
+            client.name,
+            &client.redirect_uris,
+            client.secret_hash,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OidcClientStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving OIDC client from PostgreSQL", skip_all)]
+    async fn get_client(&self, client_id: &str) -> Result<OidcClient, OidcClientStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT client_id, name, redirect_uris, secret_hash
+            FROM oidc_clients
+            WHERE client_id = $1;
+            "#,
+            client_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OidcClientStoreError::UnexpectedError(e.into()))?
+        .map(|row| OidcClient {
+            client_id: row.client_id,
+            name: row.name,
+            redirect_uris: row.redirect_uris,
+            secret_hash: row.secret_hash,
+        })
+        .ok_or(OidcClientStoreError::ClientNotFound)
+    }
+}
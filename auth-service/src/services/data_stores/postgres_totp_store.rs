@@ -0,0 +1,130 @@
+use color_eyre::eyre::{eyre, Context};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{TotpStore, TotpStoreError},
+    Email, TotpSecret,
+};
+
+pub struct PostgresTotpStore {
+    pool: PgPool,
+}
+
+impl PostgresTotpStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpStore for PostgresTotpStore {
+    #[tracing::instrument(name = "Adding TOTP secret to PostgreSQL", skip_all)]
+    async fn set_secret(&mut self, email: Email, secret: TotpSecret) -> Result<(), TotpStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO totp_secrets (email, secret, enabled, last_used_step)
+            VALUES ($1, $2, FALSE, NULL)
+            ON CONFLICT (email) DO UPDATE
+            SET secret = EXCLUDED.secret, enabled = FALSE, last_used_step = NULL;
+            "#,
+            email.as_ref(),
+            secret.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving TOTP secret from PostgreSQL", skip_all)]
+    async fn get_secret(&self, email: &Email) -> Result<(TotpSecret, bool), TotpStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT secret, enabled
+            FROM totp_secrets
+            WHERE email = $1;
+            "#,
+            email.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?
+        .map(|row| {
+            let secret = TotpSecret::parse(row.secret).map_err(|e| TotpStoreError::UnexpectedError(eyre!(e)))?;
+            Ok((secret, row.enabled))
+        })
+        .ok_or(TotpStoreError::SecretNotFound)?
+    }
+
+    #[tracing::instrument(name = "Enabling TOTP in PostgreSQL", skip_all)]
+    async fn enable(&mut self, email: &Email) -> Result<(), TotpStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets
+            SET enabled = TRUE
+            WHERE email = $1;
+            "#,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::SecretNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using TOTP time step in PostgreSQL", skip_all)]
+    async fn use_time_step(&mut self, email: &Email, time_step: u64) -> Result<(), TotpStoreError> {
+        let time_step: i64 = time_step
+            .try_into()
+            .wrap_err("failed to cast TOTP time step to i64")
+            .map_err(TotpStoreError::UnexpectedError)?;
+
+        // The conditional update is atomic, so two requests can never both use the same code
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets
+            SET last_used_step = $1
+            WHERE email = $2 AND (last_used_step IS NULL OR last_used_step < $1);
+            "#,
+            time_step,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            // Either the code was already used or the secret does not exist
+            self.get_secret(email).await?;
+            return Err(TotpStoreError::CodeReused);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing TOTP secret from PostgreSQL", skip_all)]
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM totp_secrets
+            WHERE email = $1;
+            "#,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::SecretNotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -1,22 +1,18 @@
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
 use sqlx::PgPool;
-use tokio::task;
-use color_eyre::eyre::{eyre, Result};
+use color_eyre::eyre::{eyre, Context, Result};
 use crate::domain::{
     data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+    Email, Password, PasswordHash, PasswordHasherType, Role, User,
 };
 
 pub struct PostgresUserStore {
     pool: PgPool,
+    password_hasher: PasswordHasherType,
 }
 
 impl PostgresUserStore {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, password_hasher: PasswordHasherType) -> Self {
+        Self { pool, password_hasher }
     }
 }
 
@@ -24,21 +20,27 @@ impl PostgresUserStore {
 impl UserStore for PostgresUserStore {
     //Implement all required methods. Note that you will need to make SQL queries against our PostgreSQL instance inside these methods.
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let password_hash = compute_password_hash(user.password.as_ref().to_string()).await.map_err(UserStoreError::UnexpectedError)?;
-        
+    async fn add_user(&mut self, user: User, password: Password) -> Result<(), UserStoreError> {
+        let password_hash = self.password_hasher.hash(&password).await.map_err(UserStoreError::UnexpectedError)?;
+
         sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa) 
-            VALUES ($1, $2, $3);
+            INSERT INTO users (email, password_hash, requires_2fa, verified, roles) 
+            VALUES ($1, $2, $3, $4, $5);
             "#,
             user.email.as_ref(),
-            &password_hash,
-            user.requires_2fa
+            password_hash.as_ref(),
+            user.requires_2fa,
+            user.verified,
+            &role_names(&user.roles),
         )
         .execute(&self.pool)
         .await
-        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        .map_err(|e| match e.as_database_error() {
+            // Soft deleted users keep their row, so the email may be taken without get_user finding it
+            Some(db_error) if db_error.is_unique_violation() => UserStoreError::UserAlreadyExists,
+            _ => UserStoreError::UnexpectedError(e.into()),
+        })?;
         
         Ok(())
     }
@@ -47,9 +49,9 @@ impl UserStore for PostgresUserStore {
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         sqlx::query!(
             r#"
-            SELECT email, password_hash, requires_2fa 
+            SELECT email, requires_2fa, verified, roles, disabled
             FROM users
-            WHERE email =  $1;
+            WHERE email = $1 AND restorable_until IS NULL;
             "#,
             email.as_ref(),
         )
@@ -57,11 +59,13 @@ impl UserStore for PostgresUserStore {
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
         .map(|row| {
-            Ok(User {
-                email: Email::parse(row.email).map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: Password::parse(row.password_hash).map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: row.requires_2fa,
-            })
+            user_from_row(
+                row.email,
+                row.requires_2fa,
+                row.verified,
+                row.roles,
+                row.disabled,
+            )
         })
         .ok_or(UserStoreError::UserNotFound)?
         
@@ -70,60 +74,354 @@ impl UserStore for PostgresUserStore {
    
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError> {
-        let user = self.get_user(email).await?;
+        let password_hash = sqlx::query_scalar!(
+            r#"
+            SELECT password_hash
+            FROM users
+            WHERE email = $1 AND restorable_until IS NULL;
+            "#,
+            email.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+        let password_hash = PasswordHash::parse(password_hash).map_err(UserStoreError::UnexpectedError)?;
+
+        self.verify_password(password, &password_hash).await?;
 
-        verify_password_hash(user.password.as_ref().to_owned(), password.as_ref().to_owned()).await.map_err(|_| UserStoreError::InvalidCredentials)       
+        // The user is logged in regardless, a failed upgrade is retried with the next login
+        if self.password_hasher.needs_rehash(&password_hash) {
+            if let Err(e) = self.rehash_password(email, &password_hash, password.clone()).await {
+                tracing::warn!("Failed to rehash password: {:?}", e);
+            }
+        }
+
+        Ok(())
     }
 
-}
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError> {
+        let password_hash = self.password_hasher.hash(&password).await.map_err(UserStoreError::UnexpectedError)?;
 
-// Helper function to verify if a given password matches an expected hash
-// Hashing is a CPU-intensive operation. To avoid blocking
-// other async tasks, update this function to perform hashing on a
-// separate thread pool using tokio::task::spawn_blocking. Note that you
-// will need to update the input parameters to be String types instead of &str
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: String,
-    password_candidate: String,
-) -> Result<()> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let expected_password_hash: PasswordHash<'_> = PasswordHash::new(&expected_password_hash)?;
-   
-            Argon2::default()
-                .verify_password(password_candidate.as_bytes(), &expected_password_hash)
-                .map_err(|e| e.into())
-        })
-    }).await;
-    
-    result?
-}
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $1
+            WHERE email = $2 AND restorable_until IS NULL;
+            "#,
+            password_hash.as_ref(),
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking user as verified in PostgreSQL", skip_all)]
+    async fn set_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET verified = TRUE
+            WHERE email = $1 AND restorable_until IS NULL;
+            "#,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating 2FA requirement in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = $1
+            WHERE email = $2 AND restorable_until IS NULL;
+            "#,
+            requires_2fa,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user roles in PostgreSQL", skip_all)]
+    async fn set_roles(&mut self, email: &Email, roles: Vec<Role>) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET roles = $1
+            WHERE email = $2 AND restorable_until IS NULL;
+            "#,
+            &role_names(&roles),
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating disabled flag in PostgreSQL", skip_all)]
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET disabled = $1
+            WHERE email = $2 AND restorable_until IS NULL;
+            "#,
+            disabled,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing users from PostgreSQL", skip_all)]
+    async fn list_users(&self, search: Option<&str>, offset: u64, limit: u64) -> Result<(Vec<User>, u64), UserStoreError> {
+        let pattern = format!("%{}%", escape_like(search.unwrap_or_default()));
+        let offset: i64 = offset
+            .try_into()
+            .wrap_err("failed to cast offset to i64")
+            .map_err(UserStoreError::UnexpectedError)?;
+        let limit: i64 = limit
+            .try_into()
+            .wrap_err("failed to cast limit to i64")
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM users
+            WHERE restorable_until IS NULL AND email ILIKE $1;
+            "#,
+            &pattern,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-// Helper function to hash passwords before persisting them in the database.
-// Hashing is a CPU-intensive operation. To avoid blocking
-// other async tasks, update this function to perform hashing on a
-// separate thread pool using tokio::task::spawn_blocking. Note that you
-// will need to update the input parameters to be String types instead of &str
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: String) -> Result<String> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = task::spawn_blocking( move || {
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
+        let users = sqlx::query!(
+            r#"
+            SELECT email, requires_2fa, verified, roles, disabled
+            FROM users
+            WHERE restorable_until IS NULL AND email ILIKE $1
+            ORDER BY email
+            OFFSET $2
+            LIMIT $3;
+            "#,
+            &pattern,
+            offset,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            user_from_row(
+                row.email,
+                row.requires_2fa,
+                row.verified,
+                row.roles,
+                row.disabled,
             )
-            .hash_password(password.as_bytes(), &salt)?
-            .to_string();
-        
-            Ok(password_hash)
-            //Err(eyre!("oh no!"))
         })
-    }).await;
-    
-    result?
+        .collect::<Result<Vec<User>, UserStoreError>>()?;
+
+        Ok((users, total as u64))
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE email = $1 AND restorable_until IS NULL;
+            "#,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Soft deleting user in PostgreSQL", skip_all)]
+    async fn soft_delete_user(&mut self, email: &Email, grace_period_seconds: u64) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET restorable_until = NOW() + make_interval(secs => $1)
+            WHERE email = $2 AND restorable_until IS NULL;
+            "#,
+            grace_period_seconds as f64,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Restoring user in PostgreSQL", skip_all)]
+    async fn restore_user(&mut self, email: &Email, password: &Password) -> Result<(), UserStoreError> {
+        let password_hash = sqlx::query_scalar!(
+            r#"
+            SELECT password_hash
+            FROM users
+            WHERE email = $1 AND restorable_until > NOW();
+            "#,
+            email.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+        let password_hash = PasswordHash::parse(password_hash).map_err(UserStoreError::UnexpectedError)?;
+
+        self.verify_password(password, &password_hash).await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET restorable_until = NULL
+            WHERE email = $1 AND restorable_until > NOW();
+            "#,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Purging deleted users from PostgreSQL", skip_all)]
+    async fn purge_deleted_users(&mut self) -> Result<u64, UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE restorable_until <= NOW();
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+impl PostgresUserStore {
+    async fn verify_password(&self, password: &Password, password_hash: &PasswordHash) -> Result<(), UserStoreError> {
+        match self.password_hasher.verify(password, password_hash).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(UserStoreError::InvalidCredentials),
+            Err(e) => Err(UserStoreError::UnexpectedError(e)),
+        }
+    }
+
+    // Only replaces the hash the password was checked against, so a concurrent password change wins
+    #[tracing::instrument(name = "Rehashing user password in PostgreSQL", skip_all)]
+    async fn rehash_password(&self, email: &Email, old_hash: &PasswordHash, password: Password) -> Result<()> {
+        let password_hash = self.password_hasher.hash(&password).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $1
+            WHERE email = $2 AND password_hash = $3 AND restorable_until IS NULL;
+            "#,
+            password_hash.as_ref(),
+            email.as_ref(),
+            old_hash.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to store rehashed password")?;
+
+        Ok(())
+    }
+}
+
+// Roles are stored by name in a TEXT[] column
+fn role_names(roles: &[Role]) -> Vec<String> {
+    roles.iter().map(|role| role.as_ref().to_owned()).collect()
+}
+
+fn user_from_row(
+    email: String,
+    requires_2fa: bool,
+    verified: bool,
+    roles: Vec<String>,
+    disabled: bool,
+) -> Result<User, UserStoreError> {
+    Ok(User {
+        email: Email::parse(email).map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+        requires_2fa,
+        verified,
+        roles: roles
+            .iter()
+            .map(|role| Role::parse(role))
+            .collect::<Result<_>>()
+            .map_err(UserStoreError::UnexpectedError)?,
+        disabled,
+    })
+}
+
+// The search is matched literally, so `%` and `_` in it are no wildcards
+fn escape_like(search: &str) -> String {
+    search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use color_eyre::eyre::Context;
+
+use crate::{
+    domain::{
+        data_stores::{AuthorizationCodeStore, AuthorizationCodeStoreError},
+        AuthorizationCode, AuthorizationGrant, Email,
+    },
+    utils::auth::AUTHORIZATION_CODE_TTL_SECONDS,
+};
+
+pub struct RedisAuthorizationCodeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisAuthorizationCodeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for RedisAuthorizationCodeStore {
+    #[tracing::instrument(name = "Adding authorization code to Redis", skip_all)]
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let key = get_key(&code);
+        let ttl: u64 = AUTHORIZATION_CODE_TTL_SECONDS
+            .try_into()
+            .wrap_err("failed to cast AUTHORIZATION_CODE_TTL_SECONDS to u64")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+        let serialized_grant = serde_json::to_string(&GrantRecord::from(&grant))
+            .wrap_err("failed to serialize authorization grant")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, serialized_grant, ttl)
+            .wrap_err("failed to set authorization code in Redis")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consuming authorization code from Redis", skip_all)]
+    async fn consume_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        let key = get_key(code);
+
+        // GETDEL reads and removes the code in one step, so it can never be redeemed twice
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("failed to consume authorization code from Redis")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        match value {
+            Some(value) => parse_grant(&value),
+            None => Err(AuthorizationCodeStoreError::CodeNotFound),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GrantRecord {
+    client_id: String,
+    redirect_uri: String,
+    email: String,
+    scope: String,
+    nonce: Option<String>,
+    code_challenge: String,
+    auth_time: i64,
+}
+
+impl From<&AuthorizationGrant> for GrantRecord {
+    fn from(grant: &AuthorizationGrant) -> Self {
+        Self {
+            client_id: grant.client_id.clone(),
+            redirect_uri: grant.redirect_uri.clone(),
+            email: grant.email.as_ref().to_owned(),
+            scope: grant.scope.clone(),
+            nonce: grant.nonce.clone(),
+            code_challenge: grant.code_challenge.clone(),
+            auth_time: grant.auth_time,
+        }
+    }
+}
+
+fn parse_grant(value: &str) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+    let record: GrantRecord = serde_json::from_str(value)
+        .wrap_err("failed to deserialize authorization grant")
+        .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+    Ok(AuthorizationGrant {
+        client_id: record.client_id,
+        redirect_uri: record.redirect_uri,
+        email: Email::parse(record.email).map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+        scope: record.scope,
+        nonce: record.nonce,
+        code_challenge: record.code_challenge,
+        auth_time: record.auth_time,
+    })
+}
+
+const AUTHORIZATION_CODE_PREFIX: &str = "authorization_code:";
+
+fn get_key(code: &AuthorizationCode) -> String {
+    format!("{}{}", AUTHORIZATION_CODE_PREFIX, code.as_ref())
+}
@@ -5,7 +5,7 @@ use redis::{Commands, Connection};
 use tokio::sync::RwLock;
 
 use crate::{
-    domain::data_stores::{BannedTokenStore, BannedTokenStoreError}, utils::auth::TOKEN_TTL_SECONDS
+    domain::{data_stores::{BannedTokenStore, BannedTokenStoreError}, Email}, utils::auth::TOKEN_TTL_SECONDS
 };
 
 pub struct RedisBannedTokenStore {
@@ -60,11 +60,45 @@ impl BannedTokenStore for RedisBannedTokenStore {
         
         Ok(result)
     }
+
+    #[tracing::instrument(name = "Getting token generation from Redis", skip_all)]
+    async fn get_token_generation(&self, email: &Email) -> Result<u64, BannedTokenStoreError> {
+        let key = get_generation_key(email);
+        let result: Option<u64> = self
+            .conn
+            .write()
+            .await
+            .get(&key)
+            .wrap_err("failed to get token generation from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(result.unwrap_or_default())
+    }
+
+    #[tracing::instrument(name = "Incrementing token generation in Redis", skip_all)]
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<u64, BannedTokenStoreError> {
+        // The generation key never expires, otherwise a later increment could reuse an old generation
+        let key = get_generation_key(email);
+        let result = self
+            .conn
+            .write()
+            .await
+            .incr(&key, 1)
+            .wrap_err("failed to increment token generation in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(result)
+    }
 }
 
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const TOKEN_GENERATION_KEY_PREFIX: &str = "token_generation:";
 
 fn get_key(token: &str) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
 }
+
+fn get_generation_key(email: &Email) -> String {
+    format!("{}{}", TOKEN_GENERATION_KEY_PREFIX, email.as_ref())
+}
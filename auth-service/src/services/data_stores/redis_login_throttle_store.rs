@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+use color_eyre::eyre::Context;
+
+use crate::domain::data_stores::{LoginThrottleStore, LoginThrottleStoreError};
+
+pub struct RedisLoginThrottleStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisLoginThrottleStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginThrottleStore for RedisLoginThrottleStore {
+    #[tracing::instrument(name = "Recording login failure in Redis", skip_all)]
+    async fn record_failure(&mut self, key: &str, window_seconds: u64) -> Result<u32, LoginThrottleStoreError> {
+        let key = get_failures_key(key);
+        let mut conn = self.conn.write().await;
+
+        // INCR is atomic, so concurrent attempts are all counted
+        let failures: u32 = conn
+            .incr(&key, 1)
+            .wrap_err("failed to increment login failures in Redis")
+            .map_err(LoginThrottleStoreError::UnexpectedError)?;
+
+        let _: () = conn
+            .expire(&key, window_seconds as i64)
+            .wrap_err("failed to set expiry of login failures in Redis")
+            .map_err(LoginThrottleStoreError::UnexpectedError)?;
+
+        Ok(failures)
+    }
+
+    #[tracing::instrument(name = "Locking login in Redis", skip_all)]
+    async fn lock(&mut self, key: &str, seconds: u64) -> Result<(), LoginThrottleStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_lockout_key(key), true, seconds)
+            .wrap_err("failed to set login lockout in Redis")
+            .map_err(LoginThrottleStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting login lockout from Redis", skip_all)]
+    async fn get_lockout(&self, key: &str) -> Result<Option<u64>, LoginThrottleStoreError> {
+        // TTL returns -2 for missing keys and -1 for keys without expiry
+        let ttl: i64 = self
+            .conn
+            .write()
+            .await
+            .ttl(get_lockout_key(key))
+            .wrap_err("failed to get login lockout from Redis")
+            .map_err(LoginThrottleStoreError::UnexpectedError)?;
+
+        Ok((ttl > 0).then_some(ttl as u64))
+    }
+
+    #[tracing::instrument(name = "Clearing login throttle in Redis", skip_all)]
+    async fn clear(&mut self, key: &str) -> Result<(), LoginThrottleStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(&[get_failures_key(key), get_lockout_key(key)])
+            .wrap_err("failed to clear login throttle in Redis")
+            .map_err(LoginThrottleStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+const LOGIN_FAILURES_PREFIX: &str = "login_failures:";
+const LOGIN_LOCKOUT_PREFIX: &str = "login_lockout:";
+
+fn get_failures_key(key: &str) -> String {
+    format!("{}{}", LOGIN_FAILURES_PREFIX, key)
+}
+
+fn get_lockout_key(key: &str) -> String {
+    format!("{}{}", LOGIN_LOCKOUT_PREFIX, key)
+}
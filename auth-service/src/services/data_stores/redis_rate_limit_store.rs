@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use redis::{Connection, Script};
+use tokio::sync::RwLock;
+use color_eyre::eyre::Context;
+
+use crate::domain::{
+    data_stores::{RateLimitStore, RateLimitStoreError},
+    RateLimit,
+};
+
+pub struct RedisRateLimitStore {
+    conn: Arc<RwLock<Connection>>,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self {
+            conn,
+            script: Script::new(TAKE_TOKEN_SCRIPT),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    #[tracing::instrument(name = "Taking rate limit token from Redis", skip_all)]
+    async fn take_token(&mut self, key: &str, limit: RateLimit) -> Result<Option<u64>, RateLimitStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let wait_ms: u64 = self
+            .script
+            .key(get_key(key))
+            .arg(limit.capacity)
+            .arg(limit.period_ms())
+            .invoke(&mut *conn)
+            .wrap_err("failed to take rate limit token in Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        Ok((wait_ms > 0).then(|| wait_ms.div_ceil(1000)))
+    }
+}
+
+// Same bucket as RateLimit::take_token. The script runs atomically and uses the clock of Redis,
+// so all replicas share one bucket per key. Returns the milliseconds until the next token, 0 if one was taken.
+const TAKE_TOKEN_SCRIPT: &str = r"
+local capacity = tonumber(ARGV[1])
+local period_ms = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now_ms = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local tokens = capacity
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at_ms')
+if bucket[1] and bucket[2] then
+    local elapsed_ms = math.max(0, now_ms - tonumber(bucket[2]))
+    tokens = math.min(capacity, tonumber(bucket[1]) + elapsed_ms * capacity / period_ms)
+end
+
+local wait_ms = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    wait_ms = math.ceil((1 - tokens) * period_ms / capacity)
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at_ms', tostring(now_ms))
+redis.call('PEXPIRE', KEYS[1], period_ms)
+return wait_ms
+";
+
+const RATE_LIMIT_PREFIX: &str = "rate_limit:";
+
+fn get_key(key: &str) -> String {
+    format!("{}{}", RATE_LIMIT_PREFIX, key)
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use color_eyre::eyre::Context;
+
+use crate::{
+    domain::{
+        data_stores::{RefreshTokenStore, RefreshTokenStoreError}, Email, RefreshToken,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "Adding refresh token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        family_id: String,
+        token: RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let key = get_token_key(&token);
+
+        let data = RefreshTokenTuple(email.as_ref().to_owned(), family_id);
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize refresh token tuple")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let ttl = get_ttl()?;
+        let user_key = get_user_families_key(&email);
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .set_ex(&key, serialized_data, ttl)
+            .wrap_err("failed to set refresh token in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        // Keep an index of the user's families so all of them can be revoked at once
+        let _: () = conn
+            .sadd(&user_key, &data.1)
+            .wrap_err("failed to add refresh token family to Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let _: () = conn
+            .expire(&user_key, ttl as i64)
+            .wrap_err("failed to set expiry of refresh token families in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using refresh token in Redis", skip_all)]
+    async fn use_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, String), RefreshTokenStoreError> {
+        let ttl = get_ttl()?;
+        let mut conn = self.conn.write().await;
+
+        let value: Option<String> = conn
+            .get(get_token_key(token))
+            .wrap_err("failed to get refresh token from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let data: RefreshTokenTuple = match value {
+            Some(value) => serde_json::from_str(&value)
+                .wrap_err("failed to deserialize refresh token tuple")
+                .map_err(RefreshTokenStoreError::UnexpectedError)?,
+            None => return Err(RefreshTokenStoreError::TokenNotFound),
+        };
+
+        let family_revoked: bool = conn
+            .exists(get_revoked_family_key(&data.1))
+            .wrap_err("failed to check if refresh token family is revoked in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        if family_revoked {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        }
+
+        // SET NX makes marking the token as used atomic, so only one caller can ever win the rotation
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(ttl));
+        let first_use: bool = conn
+            .set_options(get_used_key(token), true, options)
+            .wrap_err("failed to mark refresh token as used in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        if !first_use {
+            let _: () = conn
+                .set_ex(get_revoked_family_key(&data.1), true, ttl)
+                .wrap_err("failed to revoke refresh token family in Redis")
+                .map_err(RefreshTokenStoreError::UnexpectedError)?;
+            return Err(RefreshTokenStoreError::TokenReused);
+        }
+
+        let email = Email::parse(data.0).map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok((email, data.1))
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in Redis", skip_all)]
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_revoked_family_key(family_id), true, get_ttl()?)
+            .wrap_err("failed to revoke refresh token family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking user refresh token families in Redis", skip_all)]
+    async fn revoke_user_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let ttl = get_ttl()?;
+        let user_key = get_user_families_key(email);
+        let mut conn = self.conn.write().await;
+
+        let families: Vec<String> = conn
+            .smembers(&user_key)
+            .wrap_err("failed to get refresh token families from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        for family_id in families {
+            let _: () = conn
+                .set_ex(get_revoked_family_key(&family_id), true, ttl)
+                .wrap_err("failed to revoke refresh token family in Redis")
+                .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        }
+
+        let _: () = conn
+            .del(&user_key)
+            .wrap_err("failed to delete refresh token families from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenTuple(pub String, pub String);
+
+const REFRESH_TOKEN_PREFIX: &str = "refresh_token:";
+const USED_REFRESH_TOKEN_PREFIX: &str = "used_refresh_token:";
+const REVOKED_REFRESH_FAMILY_PREFIX: &str = "revoked_refresh_family:";
+const USER_REFRESH_FAMILIES_PREFIX: &str = "refresh_token_families:";
+
+fn get_ttl() -> Result<u64, RefreshTokenStoreError> {
+    REFRESH_TOKEN_TTL_SECONDS
+        .try_into()
+        .wrap_err("failed to cast REFRESH_TOKEN_TTL_SECONDS to u64")
+        .map_err(RefreshTokenStoreError::UnexpectedError)
+}
+
+fn get_token_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_PREFIX, token.as_ref())
+}
+
+fn get_used_key(token: &RefreshToken) -> String {
+    format!("{}{}", USED_REFRESH_TOKEN_PREFIX, token.as_ref())
+}
+
+fn get_revoked_family_key(family_id: &str) -> String {
+    format!("{}{}", REVOKED_REFRESH_FAMILY_PREFIX, family_id)
+}
+
+fn get_user_families_key(email: &Email) -> String {
+    format!("{}{}", USER_REFRESH_FAMILIES_PREFIX, email.as_ref())
+}
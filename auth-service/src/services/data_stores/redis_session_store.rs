@@ -0,0 +1,192 @@
+use std::sync::Arc;
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use color_eyre::eyre::Context;
+
+use crate::{
+    domain::{
+        data_stores::{SessionStore, SessionStoreError},
+        Email, Session,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisSessionStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "Adding session to Redis", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let ttl = get_ttl()?;
+        let user_key = get_user_sessions_key(&session.email);
+        let serialized_session = serde_json::to_string(&SessionRecord::from(&session))
+            .wrap_err("failed to serialize session")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .set_ex(get_session_key(&session.id), serialized_session, ttl)
+            .wrap_err("failed to set session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        // Keep an index of the user's sessions so they can be listed and removed at once
+        let _: () = conn
+            .sadd(&user_key, &session.id)
+            .wrap_err("failed to add session to the user's sessions in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let _: () = conn
+            .expire(&user_key, ttl as i64)
+            .wrap_err("failed to set expiry of the user's sessions in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving session from Redis", skip_all)]
+    async fn get_session(&self, session_id: &str) -> Result<Session, SessionStoreError> {
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get(get_session_key(session_id))
+            .wrap_err("failed to get session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        match value {
+            Some(value) => parse_session(session_id, &value),
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving user sessions from Redis", skip_all)]
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let user_key = get_user_sessions_key(email);
+        let mut conn = self.conn.write().await;
+
+        let session_ids: Vec<String> = conn
+            .smembers(&user_key)
+            .wrap_err("failed to get the user's sessions from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut sessions = Vec::new();
+        for session_id in session_ids {
+            let value: Option<String> = conn
+                .get(get_session_key(&session_id))
+                .wrap_err("failed to get session from Redis")
+                .map_err(SessionStoreError::UnexpectedError)?;
+
+            match value {
+                Some(value) => sessions.push(parse_session(&session_id, &value)?),
+                // The session expired on its own, drop it from the index as well
+                None => {
+                    let _: () = conn
+                        .srem(&user_key, &session_id)
+                        .wrap_err("failed to remove expired session from Redis")
+                        .map_err(SessionStoreError::UnexpectedError)?;
+                }
+            }
+        }
+
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(name = "Removing session from Redis", skip_all)]
+    async fn remove_session(&mut self, session_id: &str) -> Result<(), SessionStoreError> {
+        let session = self.get_session(session_id).await?;
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .del(get_session_key(session_id))
+            .wrap_err("failed to delete session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let _: () = conn
+            .srem(get_user_sessions_key(&session.email), session_id)
+            .wrap_err("failed to remove session from the user's sessions in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing user sessions from Redis", skip_all)]
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        let user_key = get_user_sessions_key(email);
+        let mut conn = self.conn.write().await;
+
+        let session_ids: Vec<String> = conn
+            .smembers(&user_key)
+            .wrap_err("failed to get the user's sessions from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut keys: Vec<String> = session_ids.iter().map(|id| get_session_key(id)).collect();
+        keys.push(user_key);
+
+        let _: () = conn
+            .del(keys)
+            .wrap_err("failed to delete the user's sessions from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionRecord {
+    email: String,
+    created_at: i64,
+    ip: String,
+    user_agent: Option<String>,
+}
+
+impl From<&Session> for SessionRecord {
+    fn from(session: &Session) -> Self {
+        Self {
+            email: session.email.as_ref().to_owned(),
+            created_at: session.created_at,
+            ip: session.ip.clone(),
+            user_agent: session.user_agent.clone(),
+        }
+    }
+}
+
+fn parse_session(session_id: &str, value: &str) -> Result<Session, SessionStoreError> {
+    let record: SessionRecord = serde_json::from_str(value)
+        .wrap_err("failed to deserialize session")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+    Ok(Session {
+        id: session_id.to_owned(),
+        email: Email::parse(record.email).map_err(SessionStoreError::UnexpectedError)?,
+        created_at: record.created_at,
+        ip: record.ip,
+        user_agent: record.user_agent,
+    })
+}
+
+const SESSION_PREFIX: &str = "session:";
+const USER_SESSIONS_PREFIX: &str = "user_sessions:";
+
+fn get_ttl() -> Result<u64, SessionStoreError> {
+    REFRESH_TOKEN_TTL_SECONDS
+        .try_into()
+        .wrap_err("failed to cast REFRESH_TOKEN_TTL_SECONDS to u64")
+        .map_err(SessionStoreError::UnexpectedError)
+}
+
+fn get_session_key(session_id: &str) -> String {
+    format!("{}{}", SESSION_PREFIX, session_id)
+}
+
+fn get_user_sessions_key(email: &Email) -> String {
+    format!("{}{}", USER_SESSIONS_PREFIX, email.as_ref())
+}
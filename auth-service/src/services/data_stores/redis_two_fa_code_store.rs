@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use redis::{Commands, Connection};
+use redis::{Commands, Connection, Script};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use color_eyre::eyre::Context;
@@ -10,11 +10,41 @@ use crate::domain::{
 
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
+    consume_script: Script,
+    code_prefix: &'static str,
+    attempts_prefix: &'static str,
 }
 
 impl RedisTwoFACodeStore {
     pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+        Self::with_prefixes(conn, TWO_FA_CODE_PREFIX, TWO_FA_ATTEMPTS_PREFIX)
+    }
+
+    // Magic login codes live under their own keys, so requesting one never replaces a pending 2FA code
+    pub fn for_magic_login(conn: Arc<RwLock<Connection>>) -> Self {
+        Self::with_prefixes(conn, MAGIC_LOGIN_CODE_PREFIX, MAGIC_LOGIN_ATTEMPTS_PREFIX)
+    }
+
+    // The same goes for the codes that confirm enabling or disabling 2FA, they never replace a login's code
+    pub fn for_2fa_confirmation(conn: Arc<RwLock<Connection>>) -> Self {
+        Self::with_prefixes(conn, TWO_FA_CONFIRMATION_CODE_PREFIX, TWO_FA_CONFIRMATION_ATTEMPTS_PREFIX)
+    }
+
+    fn with_prefixes(conn: Arc<RwLock<Connection>>, code_prefix: &'static str, attempts_prefix: &'static str) -> Self {
+        Self {
+            conn,
+            consume_script: Script::new(CONSUME_CODE_SCRIPT),
+            code_prefix,
+            attempts_prefix,
+        }
+    }
+
+    fn get_key(&self, email: &Email) -> String {
+        format!("{}{}", self.code_prefix, email.as_ref())
+    }
+
+    fn get_attempts_key(&self, login_attempt_id: &LoginAttemptId) -> String {
+        format!("{}{}", self.attempts_prefix, login_attempt_id.as_ref())
     }
 }
 
@@ -28,7 +58,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
    
-        let key = get_key(&email);
+        let key = self.get_key(&email);
 
         let data = TwoFATuple(
             login_attempt_id.as_ref().to_owned(),
@@ -51,7 +81,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 
     #[tracing::instrument(name = "Removing 2FA code from Redis", skip_all)]
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
+        let key = self.get_key(email);
 
         let _: () = self
             .conn
@@ -69,7 +99,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let key = get_key(email);
+        let key = self.get_key(email);
 
         match self.conn.write().await.get::<_, String>(&key) {
             Ok(value) => {
@@ -88,14 +118,74 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    #[tracing::instrument(name = "Consuming 2FA code in Redis", skip_all)]
+    async fn consume_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let consumed: bool = self
+            .consume_script
+            .key(self.get_key(email))
+            .key(self.get_attempts_key(login_attempt_id))
+            .arg(login_attempt_id.as_ref())
+            .invoke(&mut *conn)
+            .wrap_err("failed to consume 2FA code in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        // Either there is no code, or a newer login attempt replaced it and keeps it
+        if !consumed {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Recording failed 2FA attempt in Redis", skip_all)]
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let key = self.get_attempts_key(login_attempt_id);
+        let mut conn = self.conn.write().await;
+
+        let attempts: u32 = conn
+            .incr(&key, 1)
+            .wrap_err("failed to increment 2FA attempts in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        // The counter only has to outlive the code it protects
+        let _: () = conn
+            .expire(&key, TEN_MINUTES_IN_SECONDS as i64)
+            .wrap_err("failed to set expiry of 2FA attempts in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(attempts)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
+// Compares the login attempt id and removes the code in one step, so only one request can ever consume it
+// and a code of another login attempt is left alone. Returns 1 if the code was consumed, 0 otherwise.
+const CONSUME_CODE_SCRIPT: &str = r"
+local value = redis.call('GET', KEYS[1])
+if not value or cjson.decode(value)[1] ~= ARGV[1] then
+    return 0
+end
+
+redis.call('DEL', KEYS[1], KEYS[2])
+return 1
+";
+
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
-
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref())
-}
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
+const MAGIC_LOGIN_CODE_PREFIX: &str = "magic_login_code:";
+const MAGIC_LOGIN_ATTEMPTS_PREFIX: &str = "magic_login_attempts:";
+const TWO_FA_CONFIRMATION_CODE_PREFIX: &str = "two_fa_confirmation_code:";
+const TWO_FA_CONFIRMATION_ATTEMPTS_PREFIX: &str = "two_fa_confirmation_attempts:";
@@ -0,0 +1,130 @@
+use std::{io::ErrorKind, path::PathBuf};
+use color_eyre::eyre::{eyre, Context, Result};
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+
+use crate::domain::BreachedPasswordCorpus;
+
+// Every 5 hex digit SHA-1 prefix has a file in the full corpus
+const PREFIX_COUNT: usize = 16usize.pow(5);
+
+// An offline copy of the Have I Been Pwned password corpus, as downloaded with the range API: one file per
+// SHA-1 prefix, named after the first 5 hex digits, ex: 5BAA6.txt, holding `<35 hex digit suffix>:<count>` lines.
+// Only the prefix file of the password is read, so the corpus can stay on disk.
+pub struct HibpRangeFiles {
+    dir: PathBuf,
+    // Whether every prefix has a file, a missing one is then an error rather than an unbreached prefix
+    complete: bool,
+}
+
+impl HibpRangeFiles {
+    // Fails unless the directory holds range files, so a wrong path can not silently let every password through
+    pub fn open(dir: PathBuf) -> Result<Self> {
+        let entries = std::fs::read_dir(&dir)
+            .wrap_err_with(|| format!("failed to read breached password directory {}", dir.display()))?;
+
+        let mut range_files = 0;
+        for entry in entries {
+            let entry = entry.wrap_err("failed to read breached password directory entry")?;
+            if entry.file_name().to_str().is_some_and(is_range_file_name) {
+                range_files += 1;
+            }
+        }
+
+        if range_files == 0 {
+            return Err(eyre!("no breached password range files in {}", dir.display()));
+        }
+
+        Ok(Self {
+            dir,
+            complete: range_files == PREFIX_COUNT,
+        })
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+fn is_range_file_name(name: &str) -> bool {
+    name.strip_suffix(".txt")
+        .is_some_and(|prefix| prefix.len() == 5 && prefix.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordCorpus for HibpRangeFiles {
+    #[tracing::instrument(name = "Looking up breached password", skip_all)]
+    async fn breach_count(&self, password: &str) -> Result<u64> {
+        let hash: String = digest(&SHA1_FOR_LEGACY_USE_ONLY, password.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        let (prefix, suffix) = hash.split_at(5);
+
+        // In a partial corpus a missing prefix file means no breached password shares the prefix
+        let range = match tokio::fs::read_to_string(self.dir.join(format!("{}.txt", prefix))).await {
+            Ok(range) => range,
+            Err(e) if e.kind() == ErrorKind::NotFound && !self.complete => return Ok(0),
+            Err(e) => return Err(e).wrap_err("failed to read breached password range"),
+        };
+
+        // Padding entries have a count of 0, so they never match
+        for line in range.lines() {
+            if let Some((line_suffix, count)) = line.trim().split_once(':') {
+                if line_suffix.eq_ignore_ascii_case(suffix) {
+                    return count.trim().parse().wrap_err("invalid breached password count");
+                }
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SHA-1 of "password" is 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+    fn corpus_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hibp-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("5BAA6.txt"), "1E4C9B93F3F0682250B6CF8331B7EE68FD8:42\r\n").unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_breach_count() {
+        let dir = corpus_dir();
+        let corpus = HibpRangeFiles::open(dir.clone()).unwrap();
+        assert!(!corpus.is_complete());
+
+        assert_eq!(corpus.breach_count("password").await.unwrap(), 42);
+        assert_eq!(corpus.breach_count("Tangerine-Harbor-82").await.unwrap(), 0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_rejects_missing_or_empty_directory() {
+        let dir = std::env::temp_dir().join(format!("hibp-{}", uuid::Uuid::new_v4()));
+        assert!(HibpRangeFiles::open(dir.clone()).is_err());
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("README.md"), "not a range file").unwrap();
+        assert!(HibpRangeFiles::open(dir.clone()).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_prefix_file_is_an_error_in_complete_corpus() {
+        let dir = corpus_dir();
+        let corpus = HibpRangeFiles { dir: dir.clone(), complete: true };
+
+        assert_eq!(corpus.breach_count("password").await.unwrap(), 42);
+        assert!(corpus.breach_count("Tangerine-Harbor-82").await.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
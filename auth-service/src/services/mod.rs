@@ -1,4 +1,7 @@
 pub mod data_stores;
 pub mod mock_email_client;
+pub mod oidc_identity_provider;
+pub mod hibp_range_files;
+pub mod argon2_password_hasher;
 
-pub use data_stores::*;
\ No newline at end of file
+pub use data_stores::*;
@@ -0,0 +1,200 @@
+use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, EllipticCurve, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+use url::Url;
+
+use crate::domain::{Email, ExternalIdentity, IdentityProvider};
+
+// Logs users in at an external OpenID Connect provider with the authorization code flow and PKCE.
+// ID tokens are verified with the provider's JWKS, so only providers signing with asymmetric keys are supported.
+pub struct OidcIdentityProvider {
+    issuer: String,
+    client_id: String,
+    client_secret: String,
+    http_client: reqwest::Client,
+    // The discovery document is fetched on first use, the JWKS on every login so key rotations are picked up
+    metadata: OnceCell<ProviderMetadata>,
+}
+
+impl OidcIdentityProvider {
+    pub fn new(issuer: String, client_id: String, client_secret: String) -> Self {
+        Self {
+            issuer,
+            client_id,
+            client_secret,
+            http_client: reqwest::Client::new(),
+            metadata: OnceCell::new(),
+        }
+    }
+
+    #[tracing::instrument(name = "Fetching provider metadata", skip_all)]
+    async fn metadata(&self) -> Result<&ProviderMetadata> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.issuer.trim_end_matches('/'));
+                let metadata: ProviderMetadata = self
+                    .http_client
+                    .get(url)
+                    .send()
+                    .await
+                    .wrap_err("failed to fetch provider metadata")?
+                    .error_for_status()
+                    .wrap_err("provider metadata not available")?
+                    .json()
+                    .await
+                    .wrap_err("failed to parse provider metadata")?;
+
+                // The document has to describe the issuer we trust, see OpenID Connect Discovery 1.0 section 4.3
+                if metadata.issuer != self.issuer {
+                    return Err(eyre!("provider metadata belongs to issuer {}", metadata.issuer));
+                }
+
+                Ok(metadata)
+            })
+            .await
+    }
+
+    #[tracing::instrument(name = "Validating external ID token", skip_all)]
+    async fn validate_id_token(&self, id_token: &str, jwks_uri: &str) -> Result<ExternalIdTokenClaims> {
+        let header = decode_header(id_token).wrap_err("failed to decode ID token header")?;
+        let jwks: JwkSet = self
+            .http_client
+            .get(jwks_uri)
+            .send()
+            .await
+            .wrap_err("failed to fetch provider JWKS")?
+            .error_for_status()
+            .wrap_err("provider JWKS not available")?
+            .json()
+            .await
+            .wrap_err("failed to parse provider JWKS")?;
+
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .wrap_err("ID token was signed with an unknown key")?;
+
+        // The algorithm follows from the published key, never from the token header
+        let algorithm = match &jwk.algorithm {
+            AlgorithmParameters::RSA(_) => Algorithm::RS256,
+            AlgorithmParameters::OctetKeyPair(_) => Algorithm::EdDSA,
+            AlgorithmParameters::EllipticCurve(parameters) => match parameters.curve {
+                EllipticCurve::P256 => Algorithm::ES256,
+                EllipticCurve::P384 => Algorithm::ES384,
+                _ => return Err(eyre!("unsupported elliptic curve")),
+            },
+            AlgorithmParameters::OctetKey(_) => return Err(eyre!("symmetric provider keys are not supported")),
+        };
+        let decoding_key = DecodingKey::from_jwk(jwk).wrap_err("failed to create decoding key")?;
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&["exp", "sub", "aud", "iss"]);
+
+        decode::<ExternalIdTokenClaims>(id_token, &decoding_key, &validation)
+            .map(|data| data.claims)
+            .wrap_err("failed to validate ID token")
+    }
+}
+
+#[async_trait::async_trait]
+impl IdentityProvider for OidcIdentityProvider {
+    async fn authorization_url(
+        &self,
+        redirect_uri: &str,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String> {
+        let metadata = self.metadata().await?;
+        let mut url =
+            Url::parse(&metadata.authorization_endpoint).wrap_err("invalid provider authorization endpoint")?;
+        url.query_pairs_mut().extend_pairs([
+            ("response_type", "code"),
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("scope", "openid email"),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", code_challenge),
+            ("code_challenge_method", "S256"),
+        ]);
+
+        Ok(url.into())
+    }
+
+    #[tracing::instrument(name = "Exchanging code at provider", skip_all)]
+    async fn exchange_code(
+        &self,
+        redirect_uri: &str,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<ExternalIdentity> {
+        let metadata = self.metadata().await?;
+
+        let response: ProviderTokenResponse = self
+            .http_client
+            .post(&metadata.token_endpoint)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await
+            .wrap_err("failed to reach provider token endpoint")?
+            .error_for_status()
+            .wrap_err("provider rejected the authorization code")?
+            .json()
+            .await
+            .wrap_err("failed to parse provider token response")?;
+
+        let claims = self.validate_id_token(&response.id_token, &metadata.jwks_uri).await?;
+
+        // The nonce ties the ID token to the login started in this browser
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(eyre!("ID token nonce does not match"));
+        }
+
+        let email = claims.email.wrap_err("ID token carries no email")?;
+
+        Ok(ExternalIdentity {
+            issuer: self.issuer.clone(),
+            subject: claims.sub,
+            email: Email::parse(email)?,
+            email_verified: claims.email_verified.unwrap_or(false),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct ProviderTokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct ExternalIdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    email_verified: Option<bool>,
+}
@@ -1,15 +1,35 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum::http::{header, HeaderMap};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
 use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::Validation;
 use serde::{Deserialize, Serialize};
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
-use crate::{app_state::BannedTokenStoreType, domain::email::Email};
+use super::{
+    constants::{AUTH_SERVICE_URL, JWT_COOKIE_NAME, REFRESH_COOKIE_NAME, SECURE_COOKIES},
+    signing_keys::key_ring,
+};
+use crate::{
+    app_state::{ApiKeyStoreType, BannedTokenStoreType, RefreshTokenStoreType, SessionStoreType, UserStoreType},
+    domain::{email::Email, ApiKey, AuthAPIError, AuthorizationGrant, LoginAttemptId, RefreshToken, Role, Session},
+};
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
 
 
 #[tracing::instrument(name = "Generating auth cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub async fn generate_auth_cookie(
+    email: &Email,
+    session_id: &str,
+    roles: &[Role],
+    banned_token_store: BannedTokenStoreType,
+) -> Result<Cookie<'static>> {
+    let generation = banned_token_store
+        .read()
+        .await
+        .get_token_generation(email)
+        .await?;
+    let token = generate_auth_token(email, generation, session_id, roles)?;
     Ok(create_auth_cookie(token))
 }
 
@@ -18,6 +38,7 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     let cookie = Cookie::build((JWT_COOKIE_NAME, token))
         .path("/")
         .http_only(true)
+        .secure(*SECURE_COOKIES)
         .same_site(SameSite::Lax)
         .build();
 
@@ -25,25 +46,103 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
 }
 
 pub const TOKEN_TTL_SECONDS: i64 = 600;
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 14;
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = 60 * 15;
+pub const EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24;
+// Authorization codes are redeemed by the relying party right after the redirect
+pub const AUTHORIZATION_CODE_TTL_SECONDS: i64 = 60;
+// Time the user has to log in at the external provider
+pub const FEDERATED_LOGIN_TTL_SECONDS: i64 = 60 * 10;
+// Magic login links live as long as the login attempt they belong to
+pub const MAGIC_LOGIN_TTL_SECONDS: i64 = 60 * 10;
+// Wrong 2FA codes allowed per login attempt before its code is invalidated
+pub const MAX_TWO_FA_ATTEMPTS: u32 = 5;
 
-#[tracing::instrument(name = "Generating auth token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .wrap_err("failed to create 10 minute time delta")?;
+const EMAIL_VERIFICATION_AUDIENCE: &str = "email-verification";
+const FEDERATED_LOGIN_AUDIENCE: &str = "federated-login";
+const MAGIC_LOGIN_AUDIENCE: &str = "magic-login";
+// ID tokens are issued for the client, access tokens for our own /userinfo endpoint
+const OIDC_ACCESS_TOKEN_AUDIENCE: &str = "userinfo";
 
-    let exp = Utc::now()
-        .checked_add_signed(delta)
-        .ok_or(eyre!("failed to add 10 minutes to current time"))?
-        .timestamp();
+// Registers the session of a successful login and issues its JWT and refresh cookies.
+// The session id is used as the id of the new refresh token family.
+#[tracing::instrument(name = "Starting session", skip_all)]
+pub async fn start_session(
+    session: Session,
+    roles: &[Role],
+    banned_token_store: BannedTokenStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+    session_store: SessionStoreType,
+) -> Result<(Cookie<'static>, Cookie<'static>)> {
+    let email = session.email.clone();
+    let session_id = session.id.clone();
 
-    let exp: usize = exp.try_into().wrap_err(format!(
-        "failed to cast exp time to usize. exp time: {}",
-        exp
-    ))?;
+    session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .wrap_err("failed to store session")?;
+
+    let auth_cookie = generate_auth_cookie(&email, &session_id, roles, banned_token_store).await?;
+    let refresh_cookie = rotate_refresh_cookie(&email, session_id, refresh_token_store).await?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
+
+// User agent of the client, recorded with its sessions
+pub fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+// Issues the next refresh token of an existing family
+#[tracing::instrument(name = "Rotating refresh cookie", skip_all)]
+pub async fn rotate_refresh_cookie(
+    email: &Email,
+    family_id: String,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<Cookie<'static>> {
+    let token = RefreshToken::default();
+    refresh_token_store
+        .write()
+        .await
+        .add_token(email.clone(), family_id, token.clone())
+        .await
+        .wrap_err("failed to store refresh token")?;
+
+    Ok(create_refresh_cookie(token))
+}
+
+#[tracing::instrument(name = "Creating refresh cookie", skip_all)]
+fn create_refresh_cookie(token: RefreshToken) -> Cookie<'static> {
+    // Outlives the browser session, so the client stays logged in until the refresh token expires
+    Cookie::build((REFRESH_COOKIE_NAME, token.as_ref().to_owned()))
+        .path("/")
+        .http_only(true)
+        .secure(*SECURE_COOKIES)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build()
+}
+
+#[tracing::instrument(name = "Generating auth token", skip_all)]
+fn generate_auth_token(email: &Email, generation: u64, session_id: &str, roles: &[Role]) -> Result<String> {
+    let (iat, exp) = token_timestamps(TOKEN_TTL_SECONDS)?;
 
     let sub = email.as_ref().to_owned();
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        sub,
+        exp,
+        iat,
+        generation,
+        jti: uuid::Uuid::new_v4().to_string(),
+        sid: Some(session_id.to_owned()),
+        roles: roles.to_vec(),
+    };
 
     create_token(&claims)
 }
@@ -52,6 +151,7 @@ fn generate_auth_token(email: &Email) -> Result<String> {
 pub async fn validate_token(
     token: &str,
     banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
 ) -> Result<Claims> {
     match banned_token_store.read().await.verify_token_in_banned_store(token).await {
         Ok(value) => {
@@ -62,29 +162,397 @@ pub async fn validate_token(
         Err(e) => return Err(e.into()),
     }
 
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    let claims = key_ring().decode::<Claims>(token, Validation::default())?;
+
+    // Tokens from an older generation were revoked together with all other tokens of the user
+    let email = Email::parse(claims.sub.clone())?;
+    let generation = banned_token_store
+        .read()
+        .await
+        .get_token_generation(&email)
+        .await?;
+    if claims.generation < generation {
+        return Err(eyre!("token generation was revoked"));
+    }
+
+    // Tokens issued before sessions were introduced carry no sid and simply run out
+    if let Some(session_id) = &claims.sid {
+        let session = session_store
+            .read()
+            .await
+            .get_session(session_id)
+            .await
+            .wrap_err("session was revoked")?;
+        if session.email != email {
+            return Err(eyre!("session belongs to another user"));
+        }
+    }
+
+    Ok(claims)
+}
+
+// API keys stop working once they expire or their owner is disabled or deleted
+#[tracing::instrument(name = "Validating API key", skip_all)]
+pub async fn validate_api_key(
+    key: &str,
+    api_key_store: ApiKeyStoreType,
+    user_store: UserStoreType,
+) -> Result<ApiKey> {
+    let api_key = api_key_store
+        .read()
+        .await
+        .get_key_by_hash(&ApiKey::hash(key))
+        .await
+        .wrap_err("unknown API key")?;
+
+    if api_key.is_expired() {
+        return Err(eyre!("API key has expired"));
+    }
+
+    let user = user_store
+        .read()
+        .await
+        .get_user(&api_key.email)
+        .await
+        .wrap_err("API key owner not found")?;
+    if user.disabled {
+        return Err(eyre!("API key owner is disabled"));
+    }
+
+    Ok(api_key)
+}
+
+// Resolves the user behind the JWT cookie, for routes that require a logged in user
+#[tracing::instrument(name = "Authenticating user", skip_all)]
+pub async fn authenticate(
+    jar: &CookieJar,
+    banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+) -> std::result::Result<Email, AuthAPIError> {
+    let claims = authenticate_claims(jar, banned_token_store, session_store).await?;
+
+    Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)
+}
+
+// Same as `authenticate`, for routes that need more than the user, e.g. the session of the token
+pub async fn authenticate_claims(
+    jar: &CookieJar,
+    banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+) -> std::result::Result<Claims, AuthAPIError> {
+    let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
+
+    validate_token(cookie.value(), banned_token_store, session_store)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)
+}
+
+// Invalidates every JWT, refresh token and session of the user issued so far
+#[tracing::instrument(name = "Revoking user tokens", skip_all)]
+pub async fn revoke_user_tokens(
+    email: &Email,
+    banned_token_store: BannedTokenStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+    session_store: SessionStoreType,
+) -> Result<()> {
+    banned_token_store
+        .write()
+        .await
+        .increment_token_generation(email)
+        .await?;
+
+    refresh_token_store
+        .write()
+        .await
+        .revoke_user_families(email)
+        .await?;
+
+    session_store
+        .write()
+        .await
+        .remove_user_sessions(email)
+        .await?;
+
+    Ok(())
+}
+
+// The audience claim keeps verification links from being accepted as auth tokens and vice versa
+#[tracing::instrument(name = "Generating email verification token", skip_all)]
+pub fn generate_email_verification_token(email: &Email) -> Result<String> {
+    let delta = chrono::Duration::try_seconds(EMAIL_VERIFICATION_TOKEN_TTL_SECONDS)
+        .wrap_err("failed to create 24 hour time delta")?;
+
+    let exp = Utc::now()
+        .checked_add_signed(delta)
+        .ok_or(eyre!("failed to add 24 hours to current time"))?
+        .timestamp();
+
+    let exp: usize = exp.try_into().wrap_err(format!(
+        "failed to cast exp time to usize. exp time: {}",
+        exp
+    ))?;
+
+    let claims = EmailVerificationClaims {
+        sub: email.as_ref().to_owned(),
+        exp,
+        aud: EMAIL_VERIFICATION_AUDIENCE.to_owned(),
+    };
+
+    key_ring()
+        .encode(&claims)
+        .wrap_err("failed to create email verification token")
+}
+
+#[tracing::instrument(name = "Validating email verification token", skip_all)]
+pub fn validate_email_verification_token(token: &str) -> Result<Email> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[EMAIL_VERIFICATION_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "sub", "aud"]);
+
+    let claims = key_ring()
+        .decode::<EmailVerificationClaims>(token, validation)
+        .wrap_err("failed to decode email verification token")?;
+
+    Email::parse(claims.sub)
+}
+
+// Keeps the state of a login at an external provider in the browser until the provider sends the user back
+#[tracing::instrument(name = "Generating federated login token", skip_all)]
+pub fn generate_federated_login_token(
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    return_to: Option<String>,
+) -> Result<String> {
+    let (_, exp) = token_timestamps(FEDERATED_LOGIN_TTL_SECONDS)?;
+
+    let claims = FederatedLoginClaims {
+        state,
+        nonce,
+        code_verifier,
+        return_to,
+        exp,
+        aud: FEDERATED_LOGIN_AUDIENCE.to_owned(),
+    };
+
+    key_ring()
+        .encode(&claims)
+        .wrap_err("failed to create federated login token")
+}
+
+#[tracing::instrument(name = "Validating federated login token", skip_all)]
+pub fn validate_federated_login_token(token: &str) -> Result<FederatedLoginClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[FEDERATED_LOGIN_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "aud"]);
+
+    key_ring()
+        .decode::<FederatedLoginClaims>(token, validation)
+        .wrap_err("failed to decode federated login token")
+}
+
+// The token of a magic login link names the login attempt it redeems, which makes it single use
+#[tracing::instrument(name = "Generating magic login token", skip_all)]
+pub fn generate_magic_login_token(email: &Email, login_attempt_id: &LoginAttemptId) -> Result<String> {
+    let (_, exp) = token_timestamps(MAGIC_LOGIN_TTL_SECONDS)?;
+
+    let claims = MagicLoginClaims {
+        sub: email.as_ref().to_owned(),
+        login_attempt_id: login_attempt_id.as_ref().to_owned(),
+        exp,
+        aud: MAGIC_LOGIN_AUDIENCE.to_owned(),
+    };
+
+    key_ring()
+        .encode(&claims)
+        .wrap_err("failed to create magic login token")
+}
+
+#[tracing::instrument(name = "Validating magic login token", skip_all)]
+pub fn validate_magic_login_token(token: &str) -> Result<(Email, LoginAttemptId)> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[MAGIC_LOGIN_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "sub", "aud"]);
+
+    let claims = key_ring()
+        .decode::<MagicLoginClaims>(token, validation)
+        .wrap_err("failed to decode magic login token")?;
+
+    Ok((Email::parse(claims.sub)?, LoginAttemptId::parse(claims.login_attempt_id)?))
+}
+
+// Signs the ID token of an OpenID Connect login, addressed to the client the grant was issued to.
+// The client secret is the one the client authenticated with at /token, None for public clients.
+#[tracing::instrument(name = "Generating ID token", skip_all)]
+pub fn generate_id_token(grant: &AuthorizationGrant, email_verified: bool, client_secret: Option<&str>) -> Result<String> {
+    let (iat, exp) = token_timestamps(TOKEN_TTL_SECONDS)?;
+    let include_email = grant.has_scope("email");
+
+    let claims = IdTokenClaims {
+        iss: AUTH_SERVICE_URL.to_owned(),
+        sub: grant.email.as_ref().to_owned(),
+        aud: grant.client_id.clone(),
+        exp,
+        iat,
+        auth_time: grant.auth_time,
+        nonce: grant.nonce.clone(),
+        email: include_email.then(|| grant.email.as_ref().to_owned()),
+        email_verified: include_email.then_some(email_verified),
+    };
+
+    key_ring()
+        .encode_id_token(&claims, client_secret)
+        .wrap_err("failed to create ID token")
+}
+
+// Access tokens carry the token generation of the user, so /logout-all revokes them as well
+#[tracing::instrument(name = "Generating OIDC access token", skip_all)]
+pub async fn generate_oidc_access_token(
+    grant: &AuthorizationGrant,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<String> {
+    let generation = banned_token_store
+        .read()
+        .await
+        .get_token_generation(&grant.email)
+        .await?;
+    let (iat, exp) = token_timestamps(TOKEN_TTL_SECONDS)?;
+
+    let claims = AccessTokenClaims {
+        iss: AUTH_SERVICE_URL.to_owned(),
+        sub: grant.email.as_ref().to_owned(),
+        aud: OIDC_ACCESS_TOKEN_AUDIENCE.to_owned(),
+        exp,
+        iat,
+        generation,
+        jti: uuid::Uuid::new_v4().to_string(),
+        client_id: grant.client_id.clone(),
+        scope: grant.scope.clone(),
+    };
+
+    key_ring().encode(&claims).wrap_err("failed to create access token")
+}
+
+#[tracing::instrument(name = "Validating OIDC access token", skip_all)]
+pub async fn validate_oidc_access_token(
+    token: &str,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<AccessTokenClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[OIDC_ACCESS_TOKEN_AUDIENCE]);
+    validation.set_issuer(&[AUTH_SERVICE_URL.as_str()]);
+    validation.set_required_spec_claims(&["exp", "sub", "aud", "iss"]);
+
+    let claims = key_ring()
+        .decode::<AccessTokenClaims>(token, validation)
+        .wrap_err("failed to decode access token")?;
+
+    let email = Email::parse(claims.sub.clone())?;
+    let generation = banned_token_store
+        .read()
+        .await
+        .get_token_generation(&email)
+        .await?;
+    if claims.generation < generation {
+        return Err(eyre!("token generation was revoked"));
+    }
+
+    Ok(claims)
+}
+
+// Issued at and expiry timestamps of a token that is valid for the given number of seconds
+fn token_timestamps(ttl_seconds: i64) -> Result<(usize, usize)> {
+    let now = Utc::now().timestamp();
+    let iat: usize = now
+        .try_into()
+        .wrap_err(format!("failed to cast iat time to usize. iat time: {}", now))?;
+    let exp: usize = (now + ttl_seconds)
+        .try_into()
+        .wrap_err(format!("failed to cast exp time to usize. exp time: {}", now + ttl_seconds))?;
+
+    Ok((iat, exp))
 }
 
 #[tracing::instrument(name = "Create token", skip_all)]
 fn create_token(claims: &Claims) -> Result<String> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
-    )
-    .wrap_err("failed to create token")
+    key_ring().encode(claims)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    // Zero for tokens issued before it was recorded
+    #[serde(default)]
+    pub iat: usize,
+    #[serde(rename = "gen", default)]
+    pub generation: u64,
+    #[serde(default)]
+    pub jti: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    // Roles of the user when the token was issued, tokens from before roles existed carry none
+    #[serde(default)]
+    pub roles: Vec<Role>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub auth_time: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    // Only present when the email scope was granted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(rename = "gen")]
+    pub generation: u64,
+    pub jti: String,
+    pub client_id: String,
+    pub scope: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FederatedLoginClaims {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    // Local page to continue with after the login, ex: a pending /authorize request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_to: Option<String>,
+    pub exp: usize,
+    pub aud: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MagicLoginClaims {
+    sub: String,
+    #[serde(rename = "lai")]
+    login_attempt_id: String,
+    exp: usize,
+    aud: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EmailVerificationClaims {
+    sub: String,
+    exp: usize,
+    aud: String,
 }
 
 /* #[cfg(test)]
@@ -0,0 +1,143 @@
+use std::marker::PhantomData;
+use axum::{extract::FromRequestParts, http::request::Parts};
+use axum_extra::extract::CookieJar;
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Role},
+    utils::auth::{authenticate_claims, Claims},
+};
+
+// Names the role an endpoint requires, see `RequireRole`
+pub trait RequiredRole {
+    const ROLE: Role;
+}
+
+pub struct AdminRole;
+
+impl RequiredRole for AdminRole {
+    const ROLE: Role = Role::Admin;
+}
+
+// Extracts the claims of the logged in user and rejects the request unless the JWT carries the role, e.g.
+// `async fn handler(RequireRole(claims, _): RequireRole<AdminRole>)`.
+// Roles are read from the token, so a changed role applies once the user receives a new JWT.
+pub struct RequireRole<R>(pub Claims, pub PhantomData<R>);
+
+#[async_trait::async_trait]
+impl<R> FromRequestParts<AppState> for RequireRole<R>
+where
+    R: RequiredRole,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let jar = CookieJar::from_headers(&parts.headers);
+        let claims =
+            authenticate_claims(&jar, state.banned_token_store.clone(), state.session_store.clone()).await?;
+
+        if !claims.roles.contains(&R::ROLE) {
+            return Err(AuthAPIError::Forbidden);
+        }
+
+        Ok(Self(claims, PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        sync::Arc,
+    };
+    use axum::http::{header, Request};
+    use tokio::sync::RwLock;
+    use super::*;
+    use crate::{
+        domain::{Email, PasswordPolicy, RateLimits, Session},
+        services::{
+            hashmap_api_key_store::HashmapApiKeyStore,
+            hashmap_authorization_code_store::HashmapAuthorizationCodeStore,
+            hashmap_federated_identity_store::HashmapFederatedIdentityStore,
+            hashmap_login_throttle_store::HashmapLoginThrottleStore,
+            hashmap_oidc_client_store::HashmapOidcClientStore,
+            hashmap_password_reset_token_store::HashmapPasswordResetTokenStore,
+            hashmap_rate_limit_store::HashmapRateLimitStore,
+            hashmap_recovery_code_store::HashmapRecoveryCodeStore,
+            hashmap_refresh_token_store::HashmapRefreshTokenStore,
+            hashmap_session_store::HashmapSessionStore, hashmap_totp_store::HashmapTotpStore,
+            hashmap_two_fa_code_store::HashmapTwoFACodeStore, hashmap_user_store::HashmapUserStore,
+            hashset_banned_token_store::HashsetBannedTokenStore, mock_email_client::MockEmailClient,
+        },
+        utils::auth::generate_auth_cookie,
+    };
+
+    fn app_state() -> AppState {
+        AppState::new(
+            Arc::new(RwLock::new(HashmapUserStore::default())),
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(MockEmailClient),
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default())),
+            Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default())),
+            Arc::new(RwLock::new(HashmapTotpStore::default())),
+            Arc::new(RwLock::new(HashmapLoginThrottleStore::default())),
+            Arc::new(RwLock::new(HashmapSessionStore::default())),
+            Arc::new(RwLock::new(HashmapRecoveryCodeStore::default())),
+            Arc::new(RwLock::new(HashmapOidcClientStore::default())),
+            Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default())),
+            Arc::new(RwLock::new(HashmapFederatedIdentityStore::default())),
+            Arc::new(RwLock::new(HashmapApiKeyStore::default())),
+            Arc::new(RwLock::new(HashmapRateLimitStore::default())),
+            RateLimits::default(),
+            PasswordPolicy::default(),
+            None,
+        )
+    }
+
+    async fn request_parts(state: &AppState, roles: &[Role]) -> Parts {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let session = Session::new(email.clone(), IpAddr::V4(Ipv4Addr::LOCALHOST), None);
+        state.session_store.write().await.add_session(session.clone()).await.unwrap();
+
+        let cookie = generate_auth_cookie(&email, &session.id, roles, state.banned_token_store.clone())
+            .await
+            .unwrap();
+
+        let (parts, _) = Request::builder()
+            .header(header::COOKIE, cookie.encoded().to_string())
+            .body(())
+            .unwrap()
+            .into_parts();
+        parts
+    }
+
+    #[tokio::test]
+    async fn accepts_token_with_required_role() {
+        let state = app_state();
+        let mut parts = request_parts(&state, &[Role::User, Role::Admin]).await;
+
+        let result = RequireRole::<AdminRole>::from_request_parts(&mut parts, &state).await;
+        let RequireRole(claims, _) = result.expect("admin token was rejected");
+        assert_eq!(claims.sub, "test@example.com");
+    }
+
+    #[tokio::test]
+    async fn rejects_token_without_required_role() {
+        let state = app_state();
+        let mut parts = request_parts(&state, &[Role::User]).await;
+
+        let result = RequireRole::<AdminRole>::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Err(AuthAPIError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn rejects_request_without_token() {
+        let state = app_state();
+        let (mut parts, _) = Request::builder().body(()).unwrap().into_parts();
+
+        let result = RequireRole::<AdminRole>::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Err(AuthAPIError::MissingToken)));
+    }
+}
@@ -8,6 +8,9 @@ lazy_static! {
     pub static ref JWT_SECRET: String = set_token();
     pub static ref DATABASE_URL: String = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
+    pub static ref SECURE_COOKIES: bool = set_secure_cookies();
+    pub static ref ACCOUNT_DELETION_GRACE_PERIOD_SECONDS: u64 = set_account_deletion_grace_period();
 }
 /* pub static  JWT_SECRET: LazyLock<String> = LazyLock::new(|| {
     set_token()
@@ -36,14 +39,60 @@ fn set_redis_host() -> String {
     std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
 }
 
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR).unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+}
+
+// Browsers drop Secure cookies on plain HTTP, so by default they are only marked Secure behind HTTPS
+fn set_secure_cookies() -> bool {
+    dotenv().ok();
+    match std_env::var(env::SECURE_COOKIES_ENV_VAR) {
+        Ok(value) => value.parse().expect("SECURE_COOKIES must be true or false."),
+        Err(_) => AUTH_SERVICE_URL.starts_with("https://"),
+    }
+}
+
+// 0 deletes accounts right away instead of keeping a restorable tombstone
+fn set_account_deletion_grace_period() -> u64 {
+    dotenv().ok();
+    match std_env::var(env::ACCOUNT_DELETION_GRACE_PERIOD_SECONDS_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("ACCOUNT_DELETION_GRACE_PERIOD_SECONDS must be a number of seconds."),
+        Err(_) => DEFAULT_ACCOUNT_DELETION_GRACE_PERIOD_SECONDS,
+    }
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const SECURE_COOKIES_ENV_VAR: &str = "SECURE_COOKIES";
+    pub const ACCOUNT_DELETION_GRACE_PERIOD_SECONDS_ENV_VAR: &str = "ACCOUNT_DELETION_GRACE_PERIOD_SECONDS";
+    pub const JWT_KEY_RING_FILE_ENV_VAR: &str = "JWT_KEY_RING_FILE";
+    pub const JWT_SIGNING_KEY_FILE_ENV_VAR: &str = "JWT_SIGNING_KEY_FILE";
+    pub const JWT_SIGNING_KEY_ID_ENV_VAR: &str = "JWT_SIGNING_KEY_ID";
+    pub const FEDERATED_LOGIN_ISSUER_ENV_VAR: &str = "FEDERATED_LOGIN_ISSUER";
+    pub const FEDERATED_LOGIN_CLIENT_ID_ENV_VAR: &str = "FEDERATED_LOGIN_CLIENT_ID";
+    pub const FEDERATED_LOGIN_CLIENT_SECRET_ENV_VAR: &str = "FEDERATED_LOGIN_CLIENT_SECRET";
+    pub const RATE_LIMITS_ENV_VAR: &str = "RATE_LIMITS";
+    pub const PASSWORD_MIN_LENGTH_ENV_VAR: &str = "PASSWORD_MIN_LENGTH";
+    pub const PASSWORD_MAX_LENGTH_ENV_VAR: &str = "PASSWORD_MAX_LENGTH";
+    pub const PASSWORD_MIN_STRENGTH_ENV_VAR: &str = "PASSWORD_MIN_STRENGTH";
+    pub const BREACHED_PASSWORDS_DIR_ENV_VAR: &str = "BREACHED_PASSWORDS_DIR";
+    pub const PASSWORD_PEPPER_ENV_VAR: &str = "PASSWORD_PEPPER";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_COOKIE_NAME: &str = "refresh_token";
+pub const FEDERATED_LOGIN_COOKIE_NAME: &str = "federated_login";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+pub const TOTP_ISSUER: &str = "Auth Service";
+pub const DEFAULT_JWT_SIGNING_KEY_ID: &str = "primary";
+pub const DEFAULT_ACCOUNT_DELETION_GRACE_PERIOD_SECONDS: u64 = 60 * 60 * 24 * 30;
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
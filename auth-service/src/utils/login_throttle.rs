@@ -0,0 +1,219 @@
+use std::net::IpAddr;
+
+use crate::{
+    app_state::LoginThrottleStoreType,
+    domain::{AuthAPIError, Email},
+};
+
+// Failed attempts are forgotten once there was no failure for this long
+pub const LOGIN_FAILURE_WINDOW_SECONDS: u64 = 60 * 15;
+// A shared IP can belong to many users, so it gets more attempts than a single account
+pub const MAX_EMAIL_LOGIN_FAILURES: u32 = 5;
+pub const MAX_IP_LOGIN_FAILURES: u32 = 20;
+// Wrong second factors per account, across login attempts, as every password login starts a new attempt
+pub const MAX_TWO_FA_FAILURES: u32 = 10;
+// Magic login emails per recipient address and window, counted whether or not the address has an account
+pub const MAGIC_LOGIN_WINDOW_SECONDS: u64 = 60 * 15;
+pub const MAX_MAGIC_LOGIN_EMAILS: u32 = 3;
+const BASE_LOCKOUT_SECONDS: u64 = 30;
+const MAX_LOCKOUT_SECONDS: u64 = 60 * 15;
+
+// Rejects the attempt if the account or the client IP is locked out
+#[tracing::instrument(name = "Checking login throttle", skip_all)]
+pub async fn check_login_throttle(
+    email: &Email,
+    ip: IpAddr,
+    login_throttle_store: LoginThrottleStoreType,
+) -> Result<(), AuthAPIError> {
+    let store = login_throttle_store.read().await;
+    let mut retry_after = None;
+
+    for (key, _) in throttle_keys(email, ip) {
+        let lockout = store
+            .get_lockout(&key)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        retry_after = retry_after.max(lockout);
+    }
+
+    match retry_after {
+        Some(retry_after) => Err(AuthAPIError::TooManyRequests(retry_after)),
+        None => Ok(()),
+    }
+}
+
+// Counts the failure for both the account and the client IP, locking them out once they exceed their limit
+#[tracing::instrument(name = "Recording login failure", skip_all)]
+pub async fn record_login_failure(
+    email: &Email,
+    ip: IpAddr,
+    login_throttle_store: LoginThrottleStoreType,
+) -> Result<(), AuthAPIError> {
+    let mut store = login_throttle_store.write().await;
+
+    for (key, max_failures) in throttle_keys(email, ip) {
+        let failures = store
+            .record_failure(&key, LOGIN_FAILURE_WINDOW_SECONDS)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        if let Some(seconds) = lockout_seconds(failures, max_failures) {
+            store
+                .lock(&key, seconds)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Only the account is cleared. The IP counter decays on its own, otherwise logging into an own account
+// between guesses would reset the lockout of an IP that is guessing the passwords of other accounts.
+#[tracing::instrument(name = "Clearing login throttle", skip_all)]
+pub async fn clear_login_throttle(
+    email: &Email,
+    login_throttle_store: LoginThrottleStoreType,
+) -> Result<(), AuthAPIError> {
+    login_throttle_store
+        .write()
+        .await
+        .clear(&email_key(email))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Rejects the second factor while the account is locked out for too many wrong codes
+#[tracing::instrument(name = "Checking 2FA throttle", skip_all)]
+pub async fn check_two_fa_throttle(
+    email: &Email,
+    login_throttle_store: LoginThrottleStoreType,
+) -> Result<(), AuthAPIError> {
+    match login_throttle_store
+        .read()
+        .await
+        .get_lockout(&two_fa_key(email))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+    {
+        Some(retry_after) => Err(AuthAPIError::TooManyRequests(retry_after)),
+        None => Ok(()),
+    }
+}
+
+// Kept apart from the password failures, which a successful password login clears
+#[tracing::instrument(name = "Recording 2FA failure", skip_all)]
+pub async fn record_two_fa_failure(
+    email: &Email,
+    login_throttle_store: LoginThrottleStoreType,
+) -> Result<(), AuthAPIError> {
+    let key = two_fa_key(email);
+    let mut store = login_throttle_store.write().await;
+
+    let failures = store
+        .record_failure(&key, LOGIN_FAILURE_WINDOW_SECONDS)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if let Some(seconds) = lockout_seconds(failures, MAX_TWO_FA_FAILURES) {
+        store
+            .lock(&key, seconds)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Clearing 2FA throttle", skip_all)]
+pub async fn clear_two_fa_throttle(
+    email: &Email,
+    login_throttle_store: LoginThrottleStoreType,
+) -> Result<(), AuthAPIError> {
+    login_throttle_store
+        .write()
+        .await
+        .clear(&two_fa_key(email))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Limits how many magic login emails an address receives, so the route can not be used to flood an inbox
+#[tracing::instrument(name = "Checking magic login rate", skip_all)]
+pub async fn check_magic_login_rate(
+    email: &Email,
+    login_throttle_store: LoginThrottleStoreType,
+) -> Result<(), AuthAPIError> {
+    let key = magic_login_key(email);
+    let mut store = login_throttle_store.write().await;
+
+    if let Some(retry_after) = store
+        .get_lockout(&key)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+    {
+        return Err(AuthAPIError::TooManyRequests(retry_after));
+    }
+
+    let sent = store
+        .record_failure(&key, MAGIC_LOGIN_WINDOW_SECONDS)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // The email that reaches the limit is still sent, only the ones after it are refused
+    if sent >= MAX_MAGIC_LOGIN_EMAILS {
+        store
+            .lock(&key, MAGIC_LOGIN_WINDOW_SECONDS)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(())
+}
+
+fn throttle_keys(email: &Email, ip: IpAddr) -> [(String, u32); 2] {
+    [
+        (email_key(email), MAX_EMAIL_LOGIN_FAILURES),
+        (ip_key(ip), MAX_IP_LOGIN_FAILURES),
+    ]
+}
+
+fn email_key(email: &Email) -> String {
+    format!("email:{}", email.as_ref())
+}
+
+fn ip_key(ip: IpAddr) -> String {
+    format!("ip:{}", ip)
+}
+
+fn two_fa_key(email: &Email) -> String {
+    format!("two_fa:{}", email.as_ref())
+}
+
+fn magic_login_key(email: &Email) -> String {
+    format!("magic:{}", email.as_ref())
+}
+
+// The lockout doubles with every failure past the limit
+fn lockout_seconds(failures: u32, max_failures: u32) -> Option<u64> {
+    if failures < max_failures {
+        return None;
+    }
+
+    let exponent = (failures - max_failures).min(16);
+    Some((BASE_LOCKOUT_SECONDS << exponent).min(MAX_LOCKOUT_SECONDS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_seconds() {
+        assert_eq!(lockout_seconds(4, 5), None);
+        assert_eq!(lockout_seconds(5, 5), Some(30));
+        assert_eq!(lockout_seconds(6, 5), Some(60));
+        assert_eq!(lockout_seconds(7, 5), Some(120));
+        assert_eq!(lockout_seconds(100, 5), Some(MAX_LOCKOUT_SECONDS));
+    }
+}
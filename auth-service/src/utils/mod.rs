@@ -1,3 +1,7 @@
 pub mod constants;
 pub mod auth;
+pub mod authorization;
+pub mod login_throttle;
+pub mod rate_limit;
+pub mod signing_keys;
 pub mod tracing;
\ No newline at end of file
@@ -0,0 +1,41 @@
+use std::net::SocketAddr;
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{app_state::AppState, domain::AuthAPIError};
+
+// Route layer that takes a token from the bucket of the client IP and the matched route before running
+// the handler. Installed with `route_layer`, so the route pattern is known and unknown paths are not counted.
+#[tracing::instrument(name = "Rate limiting", skip_all)]
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    matched_path: MatchedPath,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthAPIError> {
+    let route = matched_path.as_str();
+
+    if let Some(limit) = state.rate_limits.get(route) {
+        let retry_after = state
+            .rate_limit_store
+            .write()
+            .await
+            .take_token(&rate_limit_key(route, &addr), limit)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        if let Some(seconds) = retry_after {
+            return Err(AuthAPIError::TooManyRequests(seconds));
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
+fn rate_limit_key(route: &str, addr: &SocketAddr) -> String {
+    format!("{}:{}", route, addr.ip())
+}
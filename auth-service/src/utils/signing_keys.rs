@@ -0,0 +1,447 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use dotenvy::dotenv;
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use lazy_static::lazy_static;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    env as std_env,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use super::constants::{env, DEFAULT_JWT_SIGNING_KEY_ID, JWT_SECRET};
+
+lazy_static! {
+    pub static ref KEY_RING: RwLock<Arc<KeyRing>> =
+        RwLock::new(Arc::new(load_key_ring().expect("Failed to load JWT signing keys")));
+}
+
+// Snapshot of the current keys, a reload only affects tokens handled after it
+pub fn key_ring() -> Arc<KeyRing> {
+    KEY_RING
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+// Keeps the current keys when the new ones are invalid, so a broken key file never takes the service down
+#[tracing::instrument(name = "Reloading key ring", skip_all)]
+pub fn reload_key_ring() -> Result<()> {
+    let key_ring = load_key_ring()?;
+    *KEY_RING.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(key_ring);
+    Ok(())
+}
+
+// Keys are rotated by editing the key ring file and sending SIGHUP, no redeploy needed
+pub async fn reload_key_ring_on_sighup() -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup()).wrap_err("failed to listen for SIGHUP")?;
+    while hangup.recv().await.is_some() {
+        match reload_key_ring() {
+            Ok(()) => tracing::info!("Reloaded JWT signing keys"),
+            Err(e) => tracing::error!("Failed to reload JWT signing keys: {:?}", e),
+        }
+    }
+    Ok(())
+}
+
+// Without a key file tokens are signed with the shared JWT_SECRET, so existing deployments keep working
+fn load_key_ring() -> Result<KeyRing> {
+    dotenv().ok();
+    if let Some(path) = non_empty_var(env::JWT_KEY_RING_FILE_ENV_VAR) {
+        return KeyRing::from_file(&path)
+            .wrap_err(format!("invalid JWT key ring file {}", path));
+    }
+
+    let active = match non_empty_var(env::JWT_SIGNING_KEY_FILE_ENV_VAR) {
+        Some(path) => {
+            let kid = std_env::var(env::JWT_SIGNING_KEY_ID_ENV_VAR)
+                .unwrap_or(DEFAULT_JWT_SIGNING_KEY_ID.to_owned());
+            let pem = std::fs::read(&path)
+                .wrap_err(format!("failed to read JWT signing key file {}", path))?;
+            SigningKey::from_pem(&kid, &pem)
+                .wrap_err(format!("invalid JWT signing key file {}", path))?
+        }
+        None => SigningKey::from_secret(None, JWT_SECRET.as_bytes()),
+    };
+
+    KeyRing::new(active, Vec::new())
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    std_env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+pub struct SigningKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    // Only asymmetric keys are published
+    jwk: Option<Jwk>,
+}
+
+impl SigningKey {
+    pub fn from_secret(kid: Option<&str>, secret: &[u8]) -> Self {
+        Self {
+            kid: kid.map(str::to_owned),
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    // Accepts RSA (RS256) and Ed25519 (EdDSA) private keys in PKCS#8 PEM format
+    pub fn from_pem(kid: &str, pem: &[u8]) -> Result<Self> {
+        let der = pem::parse(pem).wrap_err("failed to parse PEM")?;
+
+        let (algorithm, encoding_key, parameters) =
+            if let Ok(key_pair) = RsaKeyPair::from_pkcs8(der.contents()) {
+                let public_key: RsaPublicKeyComponents<Vec<u8>> = key_pair.public().into();
+                let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public_key.n),
+                    e: URL_SAFE_NO_PAD.encode(public_key.e),
+                });
+                let encoding_key = EncodingKey::from_rsa_pem(pem).wrap_err("failed to load RSA key")?;
+                (Algorithm::RS256, encoding_key, parameters)
+            } else if let Ok(key_pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der.contents()) {
+                let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+                });
+                let encoding_key = EncodingKey::from_ed_pem(pem).wrap_err("failed to load Ed25519 key")?;
+                (Algorithm::EdDSA, encoding_key, parameters)
+            } else {
+                return Err(eyre!("unsupported key, expected an RSA or Ed25519 private key in PKCS#8 format"));
+            };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match algorithm {
+                    Algorithm::RS256 => KeyAlgorithm::RS256,
+                    _ => KeyAlgorithm::EdDSA,
+                }),
+                key_id: Some(kid.to_owned()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+
+        // Verifying with the published key makes sure the JWKS always matches what we check against
+        let decoding_key = DecodingKey::from_jwk(&jwk).wrap_err("failed to create decoding key")?;
+
+        Ok(Self {
+            kid: Some(kid.to_owned()),
+            algorithm,
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+        })
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.kid.clone();
+
+        encode(&header, claims, &self.encoding_key).wrap_err("failed to create token")
+    }
+
+    // The algorithm is always taken from the key, never from the token header
+    pub fn decode<T: DeserializeOwned>(&self, token: &str, mut validation: Validation) -> Result<T> {
+        let header = decode_header(token).wrap_err("failed to decode token header")?;
+        if header.kid != self.kid {
+            return Err(eyre!("token was signed with an unknown key"));
+        }
+
+        validation.algorithms = vec![self.algorithm];
+        decode::<T>(token, &self.decoding_key, &validation)
+            .map(|data| data.claims)
+            .wrap_err("failed to decode token")
+    }
+
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+}
+
+// One key signs new tokens, retired keys only verify tokens issued before the rotation until they expire
+pub struct KeyRing {
+    active: SigningKey,
+    retired: Vec<SigningKey>,
+}
+
+impl KeyRing {
+    pub fn new(active: SigningKey, retired: Vec<SigningKey>) -> Result<Self> {
+        let mut kids = vec![active.kid()];
+        for key in &retired {
+            if kids.contains(&key.kid()) {
+                return Err(eyre!("duplicate key id {:?}", key.kid()));
+            }
+            kids.push(key.kid());
+        }
+
+        Ok(Self { active, retired })
+    }
+
+    // Paths in the key ring file are relative to the file itself
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::read_to_string(path).wrap_err("failed to read key ring file")?;
+        let config: KeyRingFile = serde_json::from_str(&file).wrap_err("failed to parse key ring file")?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        let mut active = None;
+        let mut retired = Vec::new();
+        for entry in config.keys {
+            let key = match (&entry.path, &entry.secret) {
+                (Some(key_path), None) => {
+                    let kid = entry.kid.as_deref().wrap_err("PEM keys require a kid")?;
+                    let pem = std::fs::read(dir.join(key_path))
+                        .wrap_err(format!("failed to read key file {}", key_path))?;
+                    SigningKey::from_pem(kid, &pem)?
+                }
+                (None, Some(secret)) => SigningKey::from_secret(entry.kid.as_deref(), secret.as_bytes()),
+                _ => return Err(eyre!("every key needs either a path or a secret")),
+            };
+
+            if key.kid() == Some(config.active.as_str()) {
+                active = Some(key);
+            } else {
+                retired.push(key);
+            }
+        }
+
+        let active = active.wrap_err(format!("active key {} not found", config.active))?;
+        Self::new(active, retired)
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String> {
+        self.active.encode(claims)
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, token: &str, validation: Validation) -> Result<T> {
+        let header = decode_header(token).wrap_err("failed to decode token header")?;
+
+        self.keys()
+            .find(|key| key.kid() == header.kid.as_deref())
+            .wrap_err("token was signed with an unknown key")?
+            .decode(token, validation)
+    }
+
+    // Relying parties verify ID tokens themselves. HS256 ones are keyed with the client secret, see OpenID Connect
+    // Core 1.0 section 10.1, so no relying party ever learns our own secret. Public clients have no secret at all.
+    pub fn encode_id_token<T: Serialize>(&self, claims: &T, client_secret: Option<&str>) -> Result<String> {
+        match (self.active.algorithm, client_secret) {
+            (Algorithm::HS256, Some(client_secret)) => {
+                SigningKey::from_secret(None, client_secret.as_bytes()).encode(claims)
+            }
+            (Algorithm::HS256, None) => Err(eyre!("public clients need an RS256 or EdDSA signing key")),
+            _ => self.active.encode(claims),
+        }
+    }
+
+    // Algorithm new tokens are signed with
+    pub fn algorithm(&self) -> Algorithm {
+        self.active.algorithm
+    }
+
+    // Public keys other services can use to verify our tokens locally
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys().filter_map(SigningKey::jwk).cloned().collect(),
+        }
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &SigningKey> {
+        std::iter::once(&self.active).chain(&self.retired)
+    }
+}
+
+#[derive(Deserialize)]
+struct KeyRingFile {
+    // kid of the key that signs new tokens
+    active: String,
+    keys: Vec<KeyRingEntry>,
+}
+
+#[derive(Deserialize)]
+struct KeyRingEntry {
+    kid: Option<String>,
+    // PEM encoded RSA or Ed25519 private key
+    path: Option<String>,
+    // HS256 secret, an entry without kid verifies tokens issued before key ids were introduced
+    secret: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    const RSA_PEM: &[u8] = include_bytes!("../../tests/fixtures/rs256_private_key.pem");
+    const ED25519_PEM: &[u8] = include_bytes!("../../tests/fixtures/ed25519_private_key.pem");
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: (chrono::Utc::now().timestamp() + 60) as usize,
+        }
+    }
+
+    fn assert_round_trip(key: &SigningKey, algorithm: Algorithm) {
+        let token = key.encode(&claims()).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, algorithm);
+        assert_eq!(header.kid, key.kid);
+
+        let decoded: TestClaims = key.decode(&token, Validation::default()).unwrap();
+        assert_eq!(decoded, claims());
+
+        // Anyone holding the JWK can verify the token
+        if let Some(jwk) = key.jwk() {
+            let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+            let decoded = decode::<TestClaims>(&token, &decoding_key, &Validation::new(algorithm)).unwrap();
+            assert_eq!(decoded.claims, claims());
+        }
+    }
+
+    #[test]
+    fn rsa_key_signs_rs256_tokens() {
+        let key = SigningKey::from_pem("rsa-key", RSA_PEM).unwrap();
+        assert_round_trip(&key, Algorithm::RS256);
+    }
+
+    #[test]
+    fn ed25519_key_signs_eddsa_tokens() {
+        let key = SigningKey::from_pem("ed-key", ED25519_PEM).unwrap();
+        assert_round_trip(&key, Algorithm::EdDSA);
+    }
+
+    #[test]
+    fn secret_signs_hs256_tokens() {
+        let key = SigningKey::from_secret(None, b"secret");
+        assert!(key.jwk().is_none());
+        assert_round_trip(&key, Algorithm::HS256);
+    }
+
+    #[test]
+    fn token_of_another_key_is_rejected() {
+        let rsa_key = SigningKey::from_pem("rsa-key", RSA_PEM).unwrap();
+        let ed_key = SigningKey::from_pem("ed-key", ED25519_PEM).unwrap();
+        let secret_key = SigningKey::from_secret(None, b"secret");
+
+        let token = rsa_key.encode(&claims()).unwrap();
+        assert!(ed_key.decode::<TestClaims>(&token, Validation::default()).is_err());
+        assert!(secret_key.decode::<TestClaims>(&token, Validation::default()).is_err());
+    }
+
+    #[test]
+    fn invalid_pem_is_rejected() {
+        assert!(SigningKey::from_pem("key", b"not a pem").is_err());
+    }
+
+    #[test]
+    fn key_ring_accepts_tokens_of_retired_keys() {
+        let old_ring = KeyRing::new(SigningKey::from_secret(None, b"secret"), Vec::new()).unwrap();
+        let legacy_token = old_ring.encode(&claims()).unwrap();
+
+        let ring = KeyRing::new(
+            SigningKey::from_pem("ed-key", ED25519_PEM).unwrap(),
+            vec![
+                SigningKey::from_pem("rsa-key", RSA_PEM).unwrap(),
+                SigningKey::from_secret(None, b"secret"),
+            ],
+        )
+        .unwrap();
+
+        let retired_token = SigningKey::from_pem("rsa-key", RSA_PEM).unwrap().encode(&claims()).unwrap();
+        let token = ring.encode(&claims()).unwrap();
+
+        assert_eq!(decode_header(&token).unwrap().kid, Some("ed-key".to_owned()));
+        for token in [token, retired_token, legacy_token] {
+            let decoded: TestClaims = ring.decode(&token, Validation::default()).unwrap();
+            assert_eq!(decoded, claims());
+        }
+    }
+
+    #[test]
+    fn key_ring_rejects_tokens_of_unknown_keys() {
+        let ring = KeyRing::new(SigningKey::from_pem("rsa-key", RSA_PEM).unwrap(), Vec::new()).unwrap();
+
+        let token = SigningKey::from_pem("ed-key", ED25519_PEM).unwrap().encode(&claims()).unwrap();
+        assert!(ring.decode::<TestClaims>(&token, Validation::default()).is_err());
+
+        let token = SigningKey::from_secret(None, b"secret").encode(&claims()).unwrap();
+        assert!(ring.decode::<TestClaims>(&token, Validation::default()).is_err());
+    }
+
+    #[test]
+    fn hs256_id_tokens_are_keyed_with_the_client_secret() {
+        let ring = KeyRing::new(SigningKey::from_secret(None, b"secret"), Vec::new()).unwrap();
+
+        let token = ring.encode_id_token(&claims(), Some("client secret")).unwrap();
+        let client_key = SigningKey::from_secret(None, b"client secret");
+        assert_eq!(client_key.decode::<TestClaims>(&token, Validation::default()).unwrap(), claims());
+        assert!(ring.decode::<TestClaims>(&token, Validation::default()).is_err());
+
+        assert!(ring.encode_id_token(&claims(), None).is_err());
+    }
+
+    #[test]
+    fn asymmetric_id_tokens_are_signed_with_the_active_key() {
+        let ring = KeyRing::new(SigningKey::from_pem("rsa-key", RSA_PEM).unwrap(), Vec::new()).unwrap();
+
+        for client_secret in [Some("client secret"), None] {
+            let token = ring.encode_id_token(&claims(), client_secret).unwrap();
+            assert_eq!(ring.decode::<TestClaims>(&token, Validation::default()).unwrap(), claims());
+        }
+    }
+
+    #[test]
+    fn key_ring_rejects_duplicate_key_ids() {
+        let ring = KeyRing::new(
+            SigningKey::from_pem("key", RSA_PEM).unwrap(),
+            vec![SigningKey::from_pem("key", ED25519_PEM).unwrap()],
+        );
+        assert!(ring.is_err());
+    }
+
+    #[test]
+    fn key_ring_is_loaded_from_file() {
+        let ring = KeyRing::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/key_ring.json")).unwrap();
+
+        let token = ring.encode(&claims()).unwrap();
+        assert_eq!(decode_header(&token).unwrap().kid, Some("ed-key".to_owned()));
+
+        let kids: Vec<_> = ring.jwks().keys.into_iter().map(|jwk| jwk.common.key_id).collect();
+        assert_eq!(kids, vec![Some("ed-key".to_owned()), Some("rsa-key".to_owned())]);
+
+        let legacy_token = SigningKey::from_secret(None, b"secret").encode(&claims()).unwrap();
+        assert!(ring.decode::<TestClaims>(&legacy_token, Validation::default()).is_ok());
+    }
+}
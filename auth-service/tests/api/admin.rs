@@ -0,0 +1,258 @@
+use auth_service::{
+    domain::{Email, Role},
+    routes::{AdminActionResponse, AdminUserResponse, ListUsersResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "Tangerine-Harbor-82",
+    }))
+    .await
+}
+
+// Signs up an admin and leaves the test app logged in as the admin
+async fn login_as_admin(app: &TestApp) -> String {
+    let admin_email = get_random_email();
+    signup(app, &admin_email).await;
+
+    app.user_store
+        .write()
+        .await
+        .set_roles(&Email::parse(admin_email.clone()).unwrap(), vec![Role::User, Role::Admin])
+        .await
+        .unwrap();
+
+    let response = login(app, &admin_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    admin_email
+}
+
+async fn admin_user(response: reqwest::Response) -> AdminUserResponse {
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse")
+}
+
+#[tokio::test]
+async fn should_return_403_for_non_admins() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_admin_users(&[]).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Forbidden".to_owned()
+    );
+
+    let response = app.post_admin_user_action(&random_email, "revoke-sessions").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_admin_users(&[]).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_and_search_users_with_pagination() {
+    let mut app = TestApp::new().await;
+
+    let tag = uuid::Uuid::new_v4().simple().to_string();
+    let emails: Vec<String> = (0..3).map(|i| format!("{}-{}@example.com", tag, i)).collect();
+    for email in &emails {
+        signup(&app, email).await;
+    }
+    login_as_admin(&app).await;
+
+    let response = app.get_admin_users(&[("search", &tag), ("page", "1"), ("perPage", "2")]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let page = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    assert_eq!(page.total, 3);
+    assert_eq!(page.per_page, 2);
+    assert_eq!(
+        page.users.iter().map(|user| user.email.as_str()).collect::<Vec<_>>(),
+        vec![emails[0].as_str(), emails[1].as_str()]
+    );
+
+    let response = app.get_admin_users(&[("search", &tag), ("page", "2"), ("perPage", "2")]).await;
+    let page = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    assert_eq!(page.total, 3);
+    assert_eq!(
+        page.users.iter().map(|user| user.email.as_str()).collect::<Vec<_>>(),
+        vec![emails[2].as_str()]
+    );
+
+    let response = app.get_admin_users(&[("perPage", "0")]).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_view_a_single_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    login_as_admin(&app).await;
+
+    let user = admin_user(app.get_admin_user(&random_email).await).await;
+    assert_eq!(
+        user,
+        AdminUserResponse {
+            email: random_email,
+            requires_2fa: false,
+            verified: true,
+            disabled: false,
+            roles: vec![Role::User],
+        }
+    );
+
+    let response = app.get_admin_user(&get_random_email()).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_and_enable_a_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    login_as_admin(&app).await;
+
+    let user = admin_user(app.post_admin_user_action(&random_email, "disable").await).await;
+    assert!(user.disabled);
+
+    // Logging in as the disabled user replaces the admin cookies, so this comes last
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Account disabled".to_owned()
+    );
+
+    login_as_admin(&app).await;
+    let user = admin_user(app.post_admin_user_action(&random_email, "enable").await).await;
+    assert!(!user.disabled);
+
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_force_2fa_on_a_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    login_as_admin(&app).await;
+
+    let user = admin_user(app.post_admin_user_action(&random_email, "require-2fa").await).await;
+    assert!(user.requires_2fa);
+
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_trigger_a_password_reset() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    login_as_admin(&app).await;
+
+    let response = app.post_admin_user_action(&random_email, "password-reset").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<AdminActionResponse>()
+            .await
+            .expect("Could not deserialize response body to AdminActionResponse")
+            .message,
+        "Password reset email sent".to_owned()
+    );
+
+    let response = app.post_admin_user_action(&get_random_email(), "password-reset").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_all_sessions_of_a_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    login_as_admin(&app).await;
+    let response = app.post_admin_user_action(&random_email, "revoke-sessions").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,223 @@
+use auth_service::{
+    domain::{ApiKey, Email},
+    routes::{CreateApiKeyResponse, CredentialKind, ListApiKeysResponse, VerifyResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use chrono::Utc;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, email: &str) -> reqwest::Response {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+}
+
+async fn create_api_key(app: &TestApp, body: serde_json::Value) -> CreateApiKeyResponse {
+    let response = app.post_api_key(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<CreateApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateApiKeyResponse")
+}
+
+async fn verify(app: &TestApp, token: &str) -> reqwest::Response {
+    app.post_verify_token(&serde_json::json!({ "token": token })).await
+}
+
+#[tokio::test]
+async fn should_create_list_and_revoke_api_key() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response = signup_and_login(&app, &random_email).await;
+    let jwt = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let created = create_api_key(
+        &app,
+        serde_json::json!({
+            "name": "CI",
+            "scopes": ["deploy", "repo:read"],
+            "expiresInDays": 30
+        }),
+    )
+    .await;
+    assert!(ApiKey::is_api_key(&created.key));
+    assert_eq!(created.api_key.name, "CI");
+    assert_eq!(created.api_key.scopes, vec!["deploy".to_owned(), "repo:read".to_owned()]);
+    assert!(created.api_key.expires_at.is_some());
+    assert!(!created.api_key.expired);
+
+    // The key works in place of a JWT, and the caller learns which kind it presented
+    let response = verify(&app, &created.key).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let verified = response
+        .json::<VerifyResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyResponse");
+    assert_eq!(verified.kind, CredentialKind::ApiKey);
+    assert_eq!(verified.sub, random_email);
+    assert_eq!(verified.scopes, Some(vec!["deploy".to_owned(), "repo:read".to_owned()]));
+
+    let response = verify(&app, &jwt).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let verified = response
+        .json::<VerifyResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyResponse");
+    assert_eq!(verified.kind, CredentialKind::Jwt);
+    assert_eq!(verified.sub, random_email);
+    assert_eq!(verified.scopes, None);
+
+    // Listing never reveals the key itself
+    let response = app.get_api_keys().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    assert!(!body.contains(&created.key));
+    let listed: ListApiKeysResponse = serde_json::from_str(&body).unwrap();
+    assert_eq!(listed.api_keys, vec![created.api_key.clone()]);
+
+    let response = app.delete_api_key(&created.api_key.id).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = verify(&app, &created.key).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.delete_api_key(&created.api_key.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_api_key(&serde_json::json!({ "name": "CI" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_api_keys().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    let test_cases = [
+        serde_json::json!({ "name": "" }),
+        serde_json::json!({ "name": "CI", "scopes": ["repo read"] }),
+        serde_json::json!({ "name": "CI", "expiresInDays": 0 }),
+        serde_json::json!({ "name": "CI", "expiresInDays": 366 }),
+    ];
+    for test_case in test_cases.iter() {
+        let response = app.post_api_key(test_case).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", test_case);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid credentials".to_owned()
+        );
+    }
+
+    let response = app.post_api_key(&serde_json::json!({ "scopes": [] })).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_expired_api_key() {
+    let mut app = TestApp::new().await;
+
+    let email = Email::parse(get_random_email()).unwrap();
+    signup_and_login(&app, email.as_ref()).await;
+
+    let (api_key, key) = ApiKey::generate(email, "CI".to_owned(), vec![], Some(1)).unwrap();
+    let api_key = ApiKey {
+        expires_at: Some(Utc::now().timestamp() - 1),
+        ..api_key
+    };
+    app.api_key_store.write().await.add_key(api_key).await.unwrap();
+
+    let response = verify(&app, &key).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_api_key_of_disabled_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    let created = create_api_key(&app, serde_json::json!({ "name": "CI" })).await;
+    assert_eq!(created.api_key.expires_at, None);
+
+    app.user_store
+        .write()
+        .await
+        .set_disabled(&Email::parse(random_email).unwrap(), true)
+        .await
+        .unwrap();
+
+    let response = verify(&app, &created.key).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_key_belongs_to_another_user() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+    let created = create_api_key(&app, serde_json::json!({ "name": "CI" })).await;
+
+    // Logging in as somebody else replaces the auth cookie
+    signup_and_login(&app, &get_random_email()).await;
+    let response = app.delete_api_key(&created.api_key.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.get_api_keys().await;
+    let listed = response
+        .json::<ListApiKeysResponse>()
+        .await
+        .expect("Could not deserialize response body to ListApiKeysResponse");
+    assert!(listed.api_keys.is_empty());
+
+    let response = verify(&app, &created.key).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,214 @@
+use auth_service::{
+    routes::ChangePasswordResponse,
+    utils::{constants::JWT_COOKIE_NAME, login_throttle::MAX_EMAIL_LOGIN_FAILURES},
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    token
+}
+
+#[tokio::test]
+async fn should_return_200_and_revoke_other_sessions() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let old_token = signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "Tangerine-Harbor-82",
+            "newPassword": "Quiet-Falcon-Meadow-19",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    assert_eq!(
+        response
+            .json::<ChangePasswordResponse>()
+            .await
+            .expect("Could not deserialize response body to ChangePasswordResponse"),
+        ChangePasswordResponse {
+            message: "Password changed successfully!".to_owned(),
+        }
+    );
+
+    // Tokens issued before the change are no longer accepted
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": old_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The session that changed the password stays logged in
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The old password no longer works, the new one does
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Quiet-Falcon-Meadow-19",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_current_password() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let token = signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "wrongpassword",
+            "newPassword": "Quiet-Falcon-Meadow-19",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    // Nothing was revoked
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_after_too_many_incorrect_current_passwords() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    for _ in 0..MAX_EMAIL_LOGIN_FAILURES {
+        let response = app
+            .post_change_password(&serde_json::json!({
+                "currentPassword": "wrongpassword",
+                "newPassword": "Quiet-Falcon-Meadow-19",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The lockout is shared with /login, so even the correct password is rejected
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "Tangerine-Harbor-82",
+            "newPassword": "Quiet-Falcon-Meadow-19",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let test_cases = [
+        serde_json::json!({
+            "currentPassword": "Tangerine-Harbor-82",
+            "newPassword": "short",
+        }),
+        serde_json::json!({
+            "currentPassword": "",
+            "newPassword": "Quiet-Falcon-Meadow-19",
+        }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_change_password(test_case).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", test_case);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "Tangerine-Harbor-82",
+            "newPassword": "Quiet-Falcon-Meadow-19",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
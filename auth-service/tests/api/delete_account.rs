@@ -0,0 +1,205 @@
+use auth_service::{
+    routes::{DeleteAccountResponse, RestoreAccountResponse},
+    utils::{constants::JWT_COOKIE_NAME, login_throttle::MAX_EMAIL_LOGIN_FAILURES},
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    token
+}
+
+#[tokio::test]
+async fn should_return_200_and_allow_restore_during_grace_period() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let token = signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_delete_account(&serde_json::json!({ "password": "Tangerine-Harbor-82" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+
+    assert_eq!(
+        response
+            .json::<DeleteAccountResponse>()
+            .await
+            .expect("Could not deserialize response body to DeleteAccountResponse"),
+        DeleteAccountResponse {
+            message: "Account deleted successfully!".to_owned(),
+        }
+    );
+
+    // The session of the deleted account is gone
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The email address stays reserved while the account can still be restored
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let response = app
+        .post_restore_account(&serde_json::json!({
+            "email": random_email,
+            "password": "wrongpassword",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_restore_account(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        response
+            .json::<RestoreAccountResponse>()
+            .await
+            .expect("Could not deserialize response body to RestoreAccountResponse"),
+        RestoreAccountResponse {
+            message: "Account restored successfully!".to_owned(),
+        }
+    );
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_password() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let token = signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_delete_account(&serde_json::json!({ "password": "wrongpassword" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    // The account and its session are untouched
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_after_too_many_incorrect_passwords() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let token = signup_and_login(&app, &random_email).await;
+
+    for _ in 0..MAX_EMAIL_LOGIN_FAILURES {
+        let response = app
+            .post_delete_account(&serde_json::json!({ "password": "wrongpassword" }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the correct password is rejected while the account is locked out
+    let response = app
+        .post_delete_account(&serde_json::json!({ "password": "Tangerine-Harbor-82" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_delete_account(&serde_json::json!({ "password": "Tangerine-Harbor-82" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn restore_should_return_401_if_account_was_not_deleted() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    for email in [random_email, get_random_email()] {
+        let response = app
+            .post_restore_account(&serde_json::json!({
+                "email": email,
+                "password": "Tangerine-Harbor-82",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.clean_up().await;
+}
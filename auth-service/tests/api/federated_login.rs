@@ -0,0 +1,576 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use auth_service::{
+    domain::{pkce_challenge, Email},
+    routes::TwoFactorAuthResponse,
+    services::oidc_identity_provider::OidcIdentityProvider,
+    utils::{
+        auth::Claims,
+        constants::{AUTH_SERVICE_URL, JWT_COOKIE_NAME},
+        signing_keys::{key_ring, SigningKey},
+    },
+    ErrorResponse,
+};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use jsonwebtoken::{jwk::JwkSet, Validation};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const MOCK_CLIENT_ID: &str = "mock-client";
+const MOCK_CLIENT_SECRET: &str = "mock-secret";
+
+// The user the mock provider asserts for every login
+#[derive(Clone)]
+struct MockUser {
+    subject: String,
+    email: String,
+    email_verified: bool,
+}
+
+struct PendingCode {
+    redirect_uri: String,
+    nonce: String,
+    code_challenge: String,
+}
+
+struct MockIssuerState {
+    issuer: String,
+    signing_key: SigningKey,
+    user: Mutex<MockUser>,
+    // Lets a test make the provider issue ID tokens meant for somebody else
+    audience: Mutex<String>,
+    codes: Mutex<HashMap<String, PendingCode>>,
+}
+
+// A local OpenID Connect provider, just complete enough for the authorization code flow with PKCE
+struct MockIssuer {
+    state: Arc<MockIssuerState>,
+}
+
+impl MockIssuer {
+    async fn start(user: MockUser) -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+        let state = Arc::new(MockIssuerState {
+            issuer,
+            signing_key: SigningKey::from_pem("mock-key", include_bytes!("../fixtures/rs256_private_key.pem"))
+                .expect("Failed to load mock signing key"),
+            user: Mutex::new(user),
+            audience: Mutex::new(MOCK_CLIENT_ID.to_owned()),
+            codes: Mutex::new(HashMap::new()),
+        });
+
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(mock_discovery))
+            .route("/jwks", get(mock_jwks))
+            .route("/authorize", get(mock_authorize))
+            .route("/token", post(mock_token))
+            .with_state(state.clone());
+
+        #[allow(clippy::let_underscore_future)]
+        let _ = tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Self { state }
+    }
+
+    fn identity_provider(&self) -> Arc<OidcIdentityProvider> {
+        Arc::new(OidcIdentityProvider::new(
+            self.state.issuer.clone(),
+            MOCK_CLIENT_ID.to_owned(),
+            MOCK_CLIENT_SECRET.to_owned(),
+        ))
+    }
+
+    fn set_user(&self, user: MockUser) {
+        *self.state.user.lock().unwrap() = user;
+    }
+
+    fn set_audience(&self, audience: &str) {
+        *self.state.audience.lock().unwrap() = audience.to_owned();
+    }
+}
+
+async fn mock_discovery(State(state): State<Arc<MockIssuerState>>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "issuer": state.issuer,
+        "authorization_endpoint": format!("{}/authorize", state.issuer),
+        "token_endpoint": format!("{}/token", state.issuer),
+        "jwks_uri": format!("{}/jwks", state.issuer),
+    }))
+}
+
+async fn mock_jwks(State(state): State<Arc<MockIssuerState>>) -> impl IntoResponse {
+    Json(JwkSet {
+        keys: state.signing_key.jwk().into_iter().cloned().collect(),
+    })
+}
+
+// Logs the mock user in right away and sends the browser back with a code
+async fn mock_authorize(
+    State(state): State<Arc<MockIssuerState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    if params.get("client_id").map(String::as_str) != Some(MOCK_CLIENT_ID)
+        || params.get("code_challenge_method").map(String::as_str) != Some("S256")
+    {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let redirect_uri = params["redirect_uri"].clone();
+    let code = Uuid::new_v4().to_string();
+    state.codes.lock().unwrap().insert(
+        code.clone(),
+        PendingCode {
+            redirect_uri: redirect_uri.clone(),
+            nonce: params["nonce"].clone(),
+            code_challenge: params["code_challenge"].clone(),
+        },
+    );
+
+    let mut location = Url::parse(&redirect_uri).unwrap();
+    location
+        .query_pairs_mut()
+        .append_pair("code", &code)
+        .append_pair("state", &params["state"]);
+
+    Redirect::to(location.as_str()).into_response()
+}
+
+#[derive(Deserialize)]
+struct MockTokenRequest {
+    code: String,
+    redirect_uri: String,
+    code_verifier: String,
+}
+
+#[derive(Serialize)]
+struct MockIdTokenClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    exp: usize,
+    iat: usize,
+    nonce: String,
+    email: String,
+    email_verified: bool,
+}
+
+async fn mock_token(
+    State(state): State<Arc<MockIssuerState>>,
+    headers: HeaderMap,
+    Form(request): Form<MockTokenRequest>,
+) -> Response {
+    let expected_authorization = format!(
+        "Basic {}",
+        base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            format!("{}:{}", MOCK_CLIENT_ID, MOCK_CLIENT_SECRET)
+        )
+    );
+    if headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok()) != Some(expected_authorization.as_str())
+    {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let Some(pending) = state.codes.lock().unwrap().remove(&request.code) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    if pending.redirect_uri != request.redirect_uri || pkce_challenge(&request.code_verifier) != pending.code_challenge {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let user = state.user.lock().unwrap().clone();
+    let now = chrono::Utc::now().timestamp() as usize;
+    let id_token = state
+        .signing_key
+        .encode(&MockIdTokenClaims {
+            iss: state.issuer.clone(),
+            sub: user.subject,
+            aud: state.audience.lock().unwrap().clone(),
+            exp: now + 300,
+            iat: now,
+            nonce: pending.nonce,
+            email: user.email,
+            email_verified: user.email_verified,
+        })
+        .unwrap();
+
+    Json(serde_json::json!({
+        "access_token": "mock-access-token",
+        "token_type": "Bearer",
+        "id_token": id_token,
+    }))
+    .into_response()
+}
+
+fn mock_user(email: &str) -> MockUser {
+    MockUser {
+        subject: Uuid::new_v4().to_string(),
+        email: email.to_owned(),
+        email_verified: true,
+    }
+}
+
+fn redirect_location(response: &reqwest::Response) -> String {
+    assert_eq!(response.status().as_u16(), 303);
+    response
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .expect("No location header")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+// Plays the browser: starts the login, lets the mock provider authenticate and returns the
+// code and state the provider sent back to the callback
+async fn authorize_at_provider(app: &TestApp, return_to: Option<&str>) -> HashMap<String, String> {
+    let response = app.get_federated_login(return_to).await;
+    let authorization_url = redirect_location(&response);
+
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(authorization_url)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let callback_url = Url::parse(&redirect_location(&response)).unwrap();
+    assert_eq!(
+        callback_url.as_str().split('?').next(),
+        Some(format!("{}/login/federated/callback", AUTH_SERVICE_URL.as_str()).as_str())
+    );
+
+    callback_url.query_pairs().into_owned().collect()
+}
+
+async fn federated_login(app: &TestApp, return_to: Option<&str>) -> reqwest::Response {
+    let params = authorize_at_provider(app, return_to).await;
+    app.get_federated_login_callback(&[("code", &params["code"]), ("state", &params["state"])])
+        .await
+}
+
+fn logged_in_as(response: &reqwest::Response) -> String {
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    key_ring()
+        .decode::<Claims>(&token, Validation::default())
+        .expect("Could not decode auth token")
+        .sub
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_create_verified_user_on_first_federated_login() {
+    let random_email = get_random_email();
+    let issuer = MockIssuer::start(mock_user(&random_email)).await;
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    let response = federated_login(&app, None).await;
+    assert_eq!(redirect_location(&response), "/");
+    assert_eq!(logged_in_as(&response), random_email);
+
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .expect("Federated user was not created");
+    assert!(user.verified);
+    assert!(!user.requires_2fa);
+
+    // The new session works like one started with a password
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_return_to_pending_authorization_requests() {
+    let issuer = MockIssuer::start(mock_user(&get_random_email())).await;
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    let authorization_path = "/authorize?response_type=code&client_id=app";
+    let response = federated_login(&app, Some(authorization_path)).await;
+    assert_eq!(redirect_location(&response), authorization_path);
+
+    let response = federated_login(&app, Some("https://evil.example.com/")).await;
+    assert_eq!(redirect_location(&response), "/");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_link_existing_user_by_verified_email() {
+    let random_email = get_random_email();
+    let issuer = MockIssuer::start(mock_user(&random_email)).await;
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    // Linking an existing account takes a browser that is logged in to it
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = federated_login(&app, None).await;
+    assert_eq!(redirect_location(&response), "/");
+    assert_eq!(logged_in_as(&response), random_email);
+
+    // Once linked, the provider's subject decides the user, not the email it reports
+    let subject = issuer.state.user.lock().unwrap().subject.clone();
+    issuer.set_user(MockUser {
+        subject,
+        email: get_random_email(),
+        email_verified: true,
+    });
+
+    let response = federated_login(&app, None).await;
+    assert_eq!(redirect_location(&response), "/");
+    assert_eq!(logged_in_as(&response), random_email);
+
+    // The local password keeps working
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_2fa_after_federated_login() {
+    let random_email = get_random_email();
+    let issuer = MockIssuer::start(mock_user(&random_email)).await;
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    let response = federated_login(&app, None).await;
+    assert_eq!(logged_in_as(&response), random_email);
+
+    // The federated user turns on emailed 2FA
+    let response = app.post_2fa_code().await;
+    assert_eq!(response.status().as_u16(), 202);
+    let (_, code) = app
+        .two_fa_confirmation_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+    let response = app.post_2fa_enable(&serde_json::json!({ "2FACode": code.as_ref() })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The provider stands in for the password, not for the second factor
+    let response = federated_login(&app, None).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(!response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_link_existing_user_without_local_login() {
+    let random_email = get_random_email();
+    let issuer = MockIssuer::start(mock_user(&random_email)).await;
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let response = federated_login(&app, None).await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(error_message(response).await, "User already exists");
+
+    // Nothing was linked, the next attempt is refused as well
+    let response = federated_login(&app, None).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_take_over_unverified_user_with_same_email() {
+    let random_email = get_random_email();
+    let issuer = MockIssuer::start(mock_user(&random_email)).await;
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    // Someone signs up with the address before its owner and never verifies it
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = federated_login(&app, None).await;
+    assert_eq!(redirect_location(&response), "/");
+    assert_eq!(logged_in_as(&response), random_email);
+
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+    assert!(user.verified);
+
+    // The password chosen at signup no longer works
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_provider_has_not_verified_email() {
+    let random_email = get_random_email();
+    let issuer = MockIssuer::start(MockUser {
+        email_verified: false,
+        ..mock_user(&random_email)
+    })
+    .await;
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    let response = federated_login(&app, None).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Email not verified");
+
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(random_email).unwrap())
+        .await;
+    assert!(user.is_err());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_state_does_not_match() {
+    let issuer = MockIssuer::start(mock_user(&get_random_email())).await;
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    let params = authorize_at_provider(&app, None).await;
+    let response = app
+        .get_federated_login_callback(&[("code", &params["code"]), ("state", "forged-state")])
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The login state is gone after the first callback
+    let response = app
+        .get_federated_login_callback(&[("code", &params["code"]), ("state", &params["state"])])
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_id_token_is_meant_for_another_client() {
+    let issuer = MockIssuer::start(mock_user(&get_random_email())).await;
+    issuer.set_audience("another-client");
+    let mut app = TestApp::with_identity_provider(issuer.identity_provider()).await;
+
+    let response = federated_login(&app, None).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(error_message(response).await, "Incorrect credentials");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_federated_login_is_not_configured() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_federated_login(None).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app
+        .get_federated_login_callback(&[("code", "code"), ("state", "state")])
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
@@ -3,18 +3,43 @@ use sqlx::{postgres::{PgConnectOptions, PgPoolOptions}, Connection, Executor, Pg
 use tokio::sync::RwLock;
 use reqwest::cookie::Jar;
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType}, 
+    app_state::{ApiKeyStoreType, AppState, BannedTokenStoreType, IdentityProviderType, PasswordResetTokenStoreType, RefreshTokenStoreType, TwoFACodeStoreType, UserStoreType}, 
+    domain::{Email, PasswordPolicy, RateLimits},
     get_postgres_pool, get_redis_client, 
     services::{
         //hashmap_two_fa_code_store::HashmapTwoFACodeStore, 
         //hashmap_user_store::HashmapUserStore, 
         //hashset_banned_token_store::HashsetBannedTokenStore, 
+        //hashmap_refresh_token_store::HashmapRefreshTokenStore,
+        //hashmap_password_reset_token_store::HashmapPasswordResetTokenStore,
+        //hashmap_totp_store::HashmapTotpStore,
+        //hashmap_session_store::HashmapSessionStore,
+        //hashmap_recovery_code_store::HashmapRecoveryCodeStore,
+        //hashmap_oidc_client_store::HashmapOidcClientStore,
+        //hashmap_authorization_code_store::HashmapAuthorizationCodeStore,
+        //hashmap_federated_identity_store::HashmapFederatedIdentityStore,
+        //hashmap_api_key_store::HashmapApiKeyStore,
+        hashmap_login_throttle_store::HashmapLoginThrottleStore,
+        hashmap_rate_limit_store::HashmapRateLimitStore,
         mock_email_client::MockEmailClient, 
         postgres_user_store::PostgresUserStore, 
+        argon2_password_hasher::Argon2PasswordHasher,
+        postgres_totp_store::PostgresTotpStore,
+        postgres_recovery_code_store::PostgresRecoveryCodeStore,
+        postgres_oidc_client_store::PostgresOidcClientStore,
+        postgres_federated_identity_store::PostgresFederatedIdentityStore,
+        postgres_api_key_store::PostgresApiKeyStore,
         redis_banned_token_store::RedisBannedTokenStore, 
         redis_two_fa_code_store::RedisTwoFACodeStore,
+        redis_refresh_token_store::RedisRefreshTokenStore,
+        redis_password_reset_token_store::RedisPasswordResetTokenStore,
+        redis_session_store::RedisSessionStore,
+        redis_authorization_code_store::RedisAuthorizationCodeStore,
     },
-    utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME}, 
+    utils::{
+        auth::generate_email_verification_token,
+        constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME},
+    }, 
     Application
 };
 use uuid::Uuid;
@@ -22,19 +47,50 @@ use uuid::Uuid;
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
+    pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub magic_login_code_store: TwoFACodeStoreType,
+    pub two_fa_confirmation_code_store: TwoFACodeStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub api_key_store: ApiKeyStoreType,
     pub http_client: reqwest::Client, 
+    // For tests that have to look at or prepare rows directly
+    pub pg_pool: PgPool,
     pub db_name: String,
     pub clean_up_called: bool,
 }
 
 impl TestApp {
-    #[tracing::instrument(name = "Creating test app", skip_all)]
     pub async fn new() -> Self {
+        Self::build(None, RateLimits::default(), PasswordPolicy::default()).await
+    }
+
+    // For the federated login tests, which bring their own external identity provider
+    pub async fn with_identity_provider(identity_provider: IdentityProviderType) -> Self {
+        Self::build(Some(identity_provider), RateLimits::default(), PasswordPolicy::default()).await
+    }
+
+    // For the rate limiting tests, which need limits that are quickly exceeded
+    pub async fn with_rate_limits(rate_limits: RateLimits) -> Self {
+        Self::build(None, rate_limits, PasswordPolicy::default()).await
+    }
+
+    // For the password policy tests, which configure lengths or a breached password corpus
+    pub async fn with_password_policy(password_policy: PasswordPolicy) -> Self {
+        Self::build(None, RateLimits::default(), password_policy).await
+    }
+
+    #[tracing::instrument(name = "Creating test app", skip_all)]
+    async fn build(
+        identity_provider: Option<IdentityProviderType>,
+        rate_limits: RateLimits,
+        password_policy: PasswordPolicy,
+    ) -> Self {
         let (pg_pool,db_name) = configure_postgresql().await;
         //let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone(), Arc::new(Argon2PasswordHasher::default()))));
         
         let redis_conn = Arc::new(RwLock::new(configure_redis()));
         
@@ -42,15 +98,67 @@ impl TestApp {
         let banned_token_store= Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
         
         //let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
-        let two_fa_code_store= Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+        let two_fa_code_store= Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
+        //let magic_login_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+        let magic_login_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::for_magic_login(redis_conn.clone())));
+        //let two_fa_confirmation_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+        let two_fa_confirmation_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::for_2fa_confirmation(redis_conn.clone())));
+
+        //let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let refresh_token_store = Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())));
+
+        //let password_reset_token_store = Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default()));
+        let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_conn.clone())));
+
+        //let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let session_store = Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())));
+
+        //let authorization_code_store = Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default()));
+        let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(redis_conn)));
         
+        //let totp_store = Arc::new(RwLock::new(HashmapTotpStore::default()));
+        let totp_store = Arc::new(RwLock::new(PostgresTotpStore::new(pg_pool.clone())));
+
+        //let recovery_code_store = Arc::new(RwLock::new(HashmapRecoveryCodeStore::default()));
+        let recovery_code_store = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+
+        //let oidc_client_store = Arc::new(RwLock::new(HashmapOidcClientStore::default()));
+        let oidc_client_store = Arc::new(RwLock::new(PostgresOidcClientStore::new(pg_pool.clone())));
+
+        //let federated_identity_store = Arc::new(RwLock::new(HashmapFederatedIdentityStore::default()));
+        let federated_identity_store = Arc::new(RwLock::new(PostgresFederatedIdentityStore::new(pg_pool.clone())));
+
+        //let api_key_store = Arc::new(RwLock::new(HashmapApiKeyStore::default()));
+        let api_key_store = Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool.clone())));
+
+        // All test requests come from 127.0.0.1, a per app store keeps the IP counters of parallel tests apart
+        let login_throttle_store = Arc::new(RwLock::new(HashmapLoginThrottleStore::default()));
+        // The same goes for the rate limit buckets, the Redis store is covered by the Redis tests
+        let rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
+
         let email_client = Arc::new(MockEmailClient);
 
         let app_state = AppState::new(
-            user_store,
+            user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
-            email_client
+            magic_login_code_store.clone(),
+            two_fa_confirmation_code_store.clone(),
+            email_client,
+            refresh_token_store.clone(),
+            password_reset_token_store.clone(),
+            totp_store,
+            login_throttle_store,
+            session_store,
+            recovery_code_store,
+            oidc_client_store,
+            authorization_code_store,
+            federated_identity_store,
+            api_key_store.clone(),
+            rate_limit_store,
+            rate_limits,
+            password_policy,
+            identity_provider,
         );
 
         let app = Application::build(app_state, test::APP_ADDRESS)
@@ -75,9 +183,16 @@ impl TestApp {
         TestApp {
             address,
             cookie_jar,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            magic_login_code_store,
+            two_fa_confirmation_code_store,
+            refresh_token_store,
+            password_reset_token_store,
+            api_key_store,
             http_client,
+            pg_pool,
             db_name,
             clean_up_called: false,
         }
@@ -93,7 +208,15 @@ impl TestApp {
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -104,7 +227,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -116,7 +239,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .json(body)
             .send()
             .await
@@ -125,24 +248,343 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_recovery_codes(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/recovery-codes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_2fa_code(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/2fa/code", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_2fa_enable<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/enable", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_2fa_disable<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/disable", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_users(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_user_action(&self, email: &str, action: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/users/{}/{}", &self.address, email, action))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/openid-configuration", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oidc_client<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/oidc/clients", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Redirects are not followed, so the tests can inspect where /authorize sends the browser
+    pub async fn get_authorize_path(&self, path_and_query: &str) -> reqwest::Response {
+        self.no_redirect_client()
+            .get(format!("{}{}", &self.address, path_and_query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_token(&self, form: &[(&str, &str)], basic_auth: Option<(&str, &str)>) -> reqwest::Response {
+        let mut request = self.http_client.post(format!("{}/token", &self.address)).form(form);
+        if let Some((client_id, client_secret)) = basic_auth {
+            request = request.basic_auth(client_id, Some(client_secret));
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_userinfo(&self, access_token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/userinfo", &self.address))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_magic_login<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/magic", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_magic_login<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/magic/verify", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Redirects are not followed, so the tests can hand the browser over to the mock provider themselves
+    pub async fn get_federated_login(&self, return_to: Option<&str>) -> reqwest::Response {
+        let mut request = self.no_redirect_client().get(format!("{}/login/federated", &self.address));
+        if let Some(return_to) = return_to {
+            request = request.query(&[("return_to", return_to)]);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_federated_login_callback(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.no_redirect_client()
+            .get(format!("{}/login/federated/callback", &self.address))
+            .query(query)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    fn no_redirect_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .cookie_provider(self.cookie_jar.clone())
+            .build()
+            .unwrap()
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response 
         where
           Body: serde::Serialize
         {
             self.http_client
-                .post(&format!("{}/verify-2fa", &self.address))
+                .post(format!("{}/verify-2fa", &self.address))
                 .json(body)
                 .send()
                 .await
                 .expect("Failed to execute request.")
     }
 
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_forgot_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/forgot-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_delete_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/delete-account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_restore_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/restore-account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, session_id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, session_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_key<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/api-keys", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_keys(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api-keys", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_api_key(&self, key_id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/api-keys/{}", &self.address, key_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_enroll(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/totp/enroll", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Follows the link a user would receive after signing up, so they are able to log in
+    pub async fn verify_email(&self, email: &str) {
+        let email = Email::parse(email.to_owned()).expect("Invalid email");
+        let token = generate_email_verification_token(&email).expect("Failed to generate verification token");
+
+        let response = self.get_verify_email(&token).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
     pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -154,6 +596,15 @@ impl TestApp {
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn post_introspect(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/introspect", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 }
 
 impl Drop for TestApp {
@@ -241,7 +692,7 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
-fn configure_redis() -> redis::Connection {
+pub fn configure_redis() -> redis::Connection {
 
     get_redis_client(DEFAULT_REDIS_HOSTNAME.to_owned())
         .expect("Failed to get Redis client")
@@ -0,0 +1,165 @@
+use auth_service::{
+    domain::Role,
+    routes::{CreateApiKeyResponse, IntrospectionResponse},
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME, signing_keys::key_ring},
+};
+use chrono::Utc;
+use reqwest::header;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    token
+}
+
+async fn introspect(app: &TestApp, token: &str) -> IntrospectionResponse {
+    let response = app.post_introspect(&[("token", token)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get(header::CACHE_CONTROL).map(|value| value.to_str().unwrap()),
+        Some("no-store")
+    );
+
+    response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse")
+}
+
+#[tokio::test]
+async fn should_return_claims_of_active_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let token = signup_and_login(&app, &random_email).await;
+
+    let introspection = introspect(&app, &token).await;
+    assert!(introspection.active);
+    assert_eq!(introspection.sub, Some(random_email));
+    assert_eq!(introspection.roles, Some(vec![Role::User]));
+    assert_eq!(introspection.scope, None);
+
+    let now = Utc::now().timestamp();
+    let iat = introspection.iat.expect("No iat claim");
+    let exp = introspection.exp.expect("No exp claim");
+    assert!(iat <= now && now < exp);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_report_banned_token_as_inactive() {
+    let mut app = TestApp::new().await;
+
+    let token = signup_and_login(&app, &get_random_email()).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_introspect(&[("token", &token), ("token_type_hint", "access_token")]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.text().await.unwrap(), r#"{"active":false}"#);
+
+    // /verify-token keeps answering with a status only
+    let response = app.post_verify_token(&serde_json::json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_report_expired_and_unknown_tokens_as_inactive() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let expired = key_ring()
+        .encode(&Claims {
+            sub: random_email,
+            exp: (Utc::now().timestamp() - 3600) as usize,
+            iat: (Utc::now().timestamp() - 4200) as usize,
+            generation: 0,
+            jti: String::new(),
+            sid: None,
+            roles: vec![Role::User],
+        })
+        .unwrap();
+
+    for token in [expired.as_str(), "invalid", "lbk_invalid"] {
+        let introspection = introspect(&app, token).await;
+        assert_eq!(introspection, IntrospectionResponse::inactive(), "Failed for token: {}", token);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_scopes_of_api_key() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "CI", "scopes": ["repo:read", "deploy"] }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let created = response
+        .json::<CreateApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateApiKeyResponse");
+
+    let introspection = introspect(&app, &created.key).await;
+    assert!(introspection.active);
+    assert_eq!(introspection.sub, Some(random_email));
+    assert_eq!(introspection.scope, Some("deploy repo:read".to_owned()));
+    assert_eq!(introspection.exp, None);
+    assert!(introspection.iat.is_some());
+    assert_eq!(introspection.roles, None);
+
+    let response = app.delete_api_key(&created.api_key.id).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let introspection = introspect(&app, &created.key).await;
+    assert!(!introspection.active);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_token_is_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_introspect(&[("token", "")]).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post_introspect(&[("token_type_hint", "access_token")]).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
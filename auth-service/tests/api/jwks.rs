@@ -0,0 +1,23 @@
+use auth_service::utils::signing_keys::key_ring;
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::helpers::TestApp;
+
+#[tokio::test]
+async fn should_return_200_with_public_signing_keys() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("cache-control").is_some());
+
+    let body = response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    assert_eq!(body, key_ring().jwks());
+
+    app.clean_up().await;
+}
@@ -1,10 +1,11 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
     routes::TwoFactorAuthResponse, 
-    utils::constants::JWT_COOKIE_NAME, 
+    utils::{constants::JWT_COOKIE_NAME, login_throttle::{MAX_EMAIL_LOGIN_FAILURES, MAX_IP_LOGIN_FAILURES}}, 
     ErrorResponse,
     domain::Email
 };
+use argon2::{password_hash::SaltString, Algorithm, Argon2, Params, PasswordHasher, Version};
 
 
 #[tokio::test]
@@ -16,17 +17,18 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": false
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;
@@ -51,17 +53,18 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": true
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123"
+        "password": "Tangerine-Harbor-82"
     });
 
     let response = app.post_login(&login_body).await;
@@ -95,16 +98,17 @@ async fn should_return_422_if_malformed_credentials() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": true
     });
 
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let test_cases = [
        serde_json::json!({
-            "password": "password123",
+            "password": "Tangerine-Harbor-82",
         }),
         serde_json::json!({
             "email": random_email,
@@ -134,18 +138,19 @@ async fn should_return_400_if_invalid_input() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": false
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let input = [
         serde_json::json!({
             "email": "ssss",
-            "password": "password123",
+            "password": "Tangerine-Harbor-82",
         }),
         serde_json::json!({
             "email": random_email,
@@ -159,7 +164,7 @@ async fn should_return_400_if_invalid_input() {
         }),
         serde_json::json!({
             "email": "invalid_email",
-            "password": "password123",
+            "password": "Tangerine-Harbor-82",
             "requires2FA": true
         }),
         serde_json::json!({
@@ -197,13 +202,14 @@ async fn should_return_401_if_incorrect_credentials() {
     let signup_body = 
         serde_json::json!({
             "email": random_email,
-            "password": "password123",
+            "password": "Tangerine-Harbor-82",
             "requires2FA": false,
         });
     
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
     let input = [
         serde_json::json!({
             "email": random_email,
@@ -211,7 +217,7 @@ async fn should_return_401_if_incorrect_credentials() {
         }),
         serde_json::json!({
             "email": "test@email.com",
-            "password": "password123",
+            "password": "Tangerine-Harbor-82",
             
         }),
     ];
@@ -232,4 +238,205 @@ async fn should_return_401_if_incorrect_credentials() {
         );
     }
     app.clean_up().await;
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn should_return_429_after_too_many_failed_attempts() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrongpassword",
+    });
+
+    for _ in 0..MAX_EMAIL_LOGIN_FAILURES {
+        let response = app.post_login(&wrong_login_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the correct password is rejected while the account is locked out
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after = response
+        .headers()
+        .get("Retry-After")
+        .expect("No Retry-After header found")
+        .to_str()
+        .unwrap()
+        .parse::<u64>()
+        .expect("Retry-After is not a number of seconds");
+    assert!(retry_after > 0);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_clear_failed_attempts_after_successful_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrongpassword",
+    });
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    for _ in 0..2 {
+        for _ in 0..MAX_EMAIL_LOGIN_FAILURES - 1 {
+            let response = app.post_login(&wrong_login_body).await;
+            assert_eq!(response.status().as_u16(), 401);
+        }
+
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_clear_ip_lockout_after_successful_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    // Guessing passwords of other accounts, logging into the own account halfway through
+    for attempt in 0..MAX_IP_LOGIN_FAILURES {
+        if attempt == MAX_IP_LOGIN_FAILURES / 2 {
+            let response = app.post_login(&login_body).await;
+            assert_eq!(response.status().as_u16(), 200);
+        }
+
+        let response = app
+            .post_login(&serde_json::json!({
+                "email": get_random_email(),
+                "password": "wrongpassword",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_rehash_password_with_outdated_parameters() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let password_hash = || async {
+        sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE email = $1")
+            .bind(&random_email)
+            .fetch_one(&app.pg_pool)
+            .await
+            .expect("Failed to read password hash")
+    };
+    let current_hash = password_hash().await;
+
+    // A hash from the days of cheaper settings
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let outdated_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(4096, 1, 1, None).unwrap())
+        .hash_password(b"Tangerine-Harbor-82", &salt)
+        .unwrap()
+        .to_string();
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE email = $2")
+        .bind(&outdated_hash)
+        .bind(&random_email)
+        .execute(&app.pg_pool)
+        .await
+        .expect("Failed to store outdated password hash");
+
+    // A wrong password leaves the hash alone
+    let response = app
+        .post_login(&serde_json::json!({ "email": random_email, "password": "wrongpassword" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(password_hash().await, outdated_hash);
+
+    let login_body = serde_json::json!({ "email": random_email, "password": "Tangerine-Harbor-82" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let rehashed = password_hash().await;
+    assert_ne!(rehashed, outdated_hash);
+    assert_eq!(
+        rehashed.split('$').nth(3),
+        current_hash.split('$').nth(3),
+        "Rehashed with other parameters than new passwords"
+    );
+
+    // Up to date hashes are kept
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(password_hash().await, rehashed);
+
+    app.clean_up().await;
+}
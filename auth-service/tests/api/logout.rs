@@ -21,17 +21,18 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": false
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;
@@ -78,17 +79,18 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": false
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;
@@ -0,0 +1,103 @@
+use auth_service::{
+    routes::{ListSessionsResponse, LogoutAllResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    token
+}
+
+#[tokio::test]
+async fn should_return_200_and_revoke_every_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let first_token = login(&app, &random_email).await;
+    let second_token = login(&app, &random_email).await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+
+    assert_eq!(
+        response
+            .json::<LogoutAllResponse>()
+            .await
+            .expect("Could not deserialize response body to LogoutAllResponse"),
+        LogoutAllResponse {
+            message: "Logged out of all sessions".to_owned(),
+        }
+    );
+
+    // Tokens that were never presented to /logout are rejected as well
+    for token in [first_token, second_token] {
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The refresh cookie was removed together with the JWT
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Logging in again works and starts from a clean slate
+    let token = login(&app, &random_email).await;
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_sessions().await;
+    let sessions = response
+        .json::<ListSessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListSessionsResponse")
+        .sessions;
+    assert_eq!(sessions.len(), 1);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,357 @@
+use auth_service::{
+    domain::{Email, LoginAttemptId, TwoFACode},
+    routes::{EnrollTotpResponse, MagicLoginResponse, TwoFactorAuthResponse},
+    utils::{auth::generate_magic_login_token, constants::JWT_COOKIE_NAME, login_throttle::MAX_MAGIC_LOGIN_EMAILS},
+    ErrorResponse,
+};
+use reqwest::header;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+}
+
+async fn request_magic_login(app: &TestApp, email: &str, method: &str) -> LoginAttemptId {
+    let response = app
+        .post_magic_login(&serde_json::json!({ "email": email, "method": method }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<MagicLoginResponse>()
+        .await
+        .expect("Could not deserialize response body to MagicLoginResponse");
+    assert_eq!(body.message, "If the account exists, a login email has been sent".to_owned());
+
+    LoginAttemptId::parse(body.login_attempt_id).expect("Invalid login attempt id")
+}
+
+// The code the mock email client would have delivered
+async fn emailed_code(app: &TestApp, email: &str, login_attempt_id: &LoginAttemptId) -> TwoFACode {
+    let (stored_attempt_id, code) = app
+        .magic_login_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(email.to_owned()).unwrap())
+        .await
+        .expect("No login code was stored");
+    assert_eq!(&stored_attempt_id, login_attempt_id);
+    code
+}
+
+fn has_auth_cookie(response: &reqwest::Response) -> bool {
+    response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME && !cookie.value().is_empty())
+}
+
+#[tokio::test]
+async fn should_log_in_with_emailed_code_once() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let login_attempt_id = request_magic_login(&app, &random_email, "code").await;
+    let code = emailed_code(&app, &random_email, &login_attempt_id).await;
+
+    let verify_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "code": code.as_ref(),
+    });
+
+    let response = app.post_verify_magic_login(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(has_auth_cookie(&response));
+
+    let response = app.post_verify_magic_login(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_log_in_with_magic_link_once() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let login_attempt_id = request_magic_login(&app, &random_email, "link").await;
+    let token = generate_magic_login_token(&Email::parse(random_email.clone()).unwrap(), &login_attempt_id).unwrap();
+
+    let response = app.post_verify_magic_login(&serde_json::json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(has_auth_cookie(&response));
+
+    let response = app.post_verify_magic_login(&serde_json::json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Links of superseded login attempts stop working as well
+    let stale_attempt_id = request_magic_login(&app, &random_email, "link").await;
+    let stale_token =
+        generate_magic_login_token(&Email::parse(random_email.clone()).unwrap(), &stale_attempt_id).unwrap();
+    request_magic_login(&app, &random_email, "link").await;
+
+    let response = app.post_verify_magic_login(&serde_json::json!({ "token": stale_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_verify_magic_login(&serde_json::json!({ "token": "invalid" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_reveal_unknown_users() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    request_magic_login(&app, &random_email, "code").await;
+
+    let result = app
+        .magic_login_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email).unwrap())
+        .await;
+    assert!(result.is_err());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_after_too_many_emails_to_one_address() {
+    let mut app = TestApp::new().await;
+
+    // Unknown addresses are limited too, otherwise the limit would reveal which accounts exist
+    for email in [get_random_email(), {
+        let email = get_random_email();
+        signup(&app, &email).await;
+        email
+    }] {
+        for _ in 0..MAX_MAGIC_LOGIN_EMAILS {
+            request_magic_login(&app, &email, "code").await;
+        }
+
+        let response = app.post_magic_login(&serde_json::json!({ "email": email })).await;
+        assert_eq!(response.status().as_u16(), 429);
+        assert!(response.headers().get(header::RETRY_AFTER).is_some());
+    }
+
+    // Other recipients are not affected
+    request_magic_login(&app, &get_random_email(), "code").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_code_after_too_many_wrong_codes() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let login_attempt_id = request_magic_login(&app, &random_email, "code").await;
+    let code = emailed_code(&app, &random_email, &login_attempt_id).await;
+    let wrong_code = if code.as_ref() == "000000" { "111111" } else { "000000" };
+
+    for _ in 0..5 {
+        let response = app
+            .post_verify_magic_login(&serde_json::json!({
+                "email": random_email,
+                "loginAttemptId": login_attempt_id.as_ref(),
+                "code": wrong_code,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_verify_magic_login(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id.as_ref(),
+            "code": code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_still_require_totp_after_magic_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let enrollment = response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to EnrollTotpResponse");
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        0,
+        30,
+        Secret::Encoded(enrollment.secret).to_bytes().unwrap(),
+        None,
+        String::new(),
+    )
+    .unwrap();
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": totp.generate_current().unwrap() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_attempt_id = request_magic_login(&app, &random_email, "code").await;
+    let code = emailed_code(&app, &random_email, &login_attempt_id).await;
+
+    let response = app
+        .post_verify_magic_login(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id.as_ref(),
+            "code": code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(!has_auth_cookie(&response));
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(body.message, "2FA required".to_owned());
+    assert_ne!(body.login_attempt_id, login_attempt_id.as_ref());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_touch_pending_2fa_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    let two_fa_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .expect("No 2FA code was stored");
+
+    // Anyone can request a magic login for the address while the user is entering the 2FA code
+    let magic_attempt_id = request_magic_login(&app, &random_email, "code").await;
+    let magic_code = emailed_code(&app, &random_email, &magic_attempt_id).await;
+
+    // Neither code can be redeemed at the other route
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": magic_attempt_id.as_ref(),
+            "2FACode": magic_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_magic_login(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": two_fa_attempt_id,
+            "code": two_fa_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": two_fa_attempt_id,
+            "2FACode": two_fa_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(has_auth_cookie(&response));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_magic_login(&serde_json::json!({ "email": "invalid" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid credentials".to_owned()
+    );
+
+    let response = app
+        .post_verify_magic_login(&serde_json::json!({
+            "email": get_random_email(),
+            "loginAttemptId": "not-an-id",
+            "code": "123456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_magic_login(&serde_json::json!({ "email": get_random_email(), "method": "carrier-pigeon" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app.post_verify_magic_login(&serde_json::json!({ "code": "123456" })).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
@@ -1,8 +1,29 @@
 mod helpers;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
 mod root;
 mod signup;
 mod verify_2fa;
 mod verify_token;
-mod redis;
\ No newline at end of file
+mod redis;
+mod refresh;
+mod reset_password;
+mod verify_email;
+mod totp;
+mod change_password;
+mod delete_account;
+mod sessions;
+
+mod recovery_codes;
+mod two_fa;
+mod roles;
+mod admin;
+mod oidc;
+mod federated_login;
+mod api_keys;
+mod magic_login;
+mod introspect;
+mod rate_limit;
+mod password_policy;
@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+use auth_service::{
+    domain::{pkce_challenge, Email, Role},
+    routes::{IntrospectionResponse, OpenIdConfiguration, RegisterClientResponse, TokenResponse, TwoFactorAuthResponse, UserInfoResponse},
+    utils::{
+        auth::IdTokenClaims,
+        constants::AUTH_SERVICE_URL,
+        signing_keys::{key_ring, SigningKey},
+    },
+    OidcErrorResponse,
+};
+use jsonwebtoken::{Algorithm, Validation};
+use reqwest::header;
+use url::{form_urlencoded, Url};
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const REDIRECT_URI: &str = "http://127.0.0.1:8000/callback";
+
+// A minimal relying party, it drives the flow the way a browser and the client's backend would
+struct RelyingParty {
+    client_id: String,
+    client_secret: Option<String>,
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    scope: &'static str,
+}
+
+impl RelyingParty {
+    // Registering a client needs an admin, so this leaves the test app logged in as one
+    async fn register(app: &TestApp, confidential: bool) -> Self {
+        login_as_admin(app).await;
+
+        let response = app
+            .post_oidc_client(&serde_json::json!({
+                "clientName": "Test app",
+                "redirectUris": [REDIRECT_URI],
+                "confidential": confidential,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+
+        let client = response
+            .json::<RegisterClientResponse>()
+            .await
+            .expect("Could not deserialize response body to RegisterClientResponse");
+        assert_eq!(client.client_secret.is_some(), confidential);
+
+        Self {
+            client_id: client.client_id,
+            client_secret: client.client_secret,
+            state: Uuid::new_v4().to_string(),
+            nonce: Uuid::new_v4().to_string(),
+            code_verifier: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+            scope: "openid email",
+        }
+    }
+
+    fn authorization_path(&self) -> String {
+        let code_challenge = pkce_challenge(&self.code_verifier);
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs([
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", REDIRECT_URI),
+                ("scope", self.scope),
+                ("state", self.state.as_str()),
+                ("nonce", self.nonce.as_str()),
+                ("code_challenge", code_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ])
+            .finish();
+
+        format!("/authorize?{}", query)
+    }
+
+    // Returns the query parameters /authorize sent back to the redirect URI
+    async fn authorize(&self, app: &TestApp) -> HashMap<String, String> {
+        let response = app.get_authorize_path(&self.authorization_path()).await;
+        let location = redirect_location(&response);
+        assert!(location.starts_with(REDIRECT_URI), "unexpected redirect to {}", location);
+
+        let params: HashMap<String, String> = Url::parse(&location).unwrap().query_pairs().into_owned().collect();
+        assert_eq!(params.get("state"), Some(&self.state));
+        params
+    }
+
+    async fn authorization_code(&self, app: &TestApp) -> String {
+        self.authorize(app).await.remove("code").expect("No authorization code returned")
+    }
+
+    async fn exchange_code(&self, app: &TestApp, code: &str, code_verifier: &str) -> reqwest::Response {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_verifier", code_verifier),
+        ];
+
+        match &self.client_secret {
+            Some(client_secret) => app.post_token(&form, Some((&self.client_id, client_secret))).await,
+            None => {
+                form.push(("client_id", &self.client_id));
+                app.post_token(&form, None).await
+            }
+        }
+    }
+
+    async fn tokens(&self, app: &TestApp) -> TokenResponse {
+        let code = self.authorization_code(app).await;
+        let response = self.exchange_code(app, &code, &self.code_verifier).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).map(|value| value.to_str().unwrap()),
+            Some("no-store")
+        );
+
+        response
+            .json::<TokenResponse>()
+            .await
+            .expect("Could not deserialize response body to TokenResponse")
+    }
+
+    // Checks signature, issuer, audience and nonce, see OpenID Connect Core 1.0 section 3.1.3.7
+    fn verify_id_token(&self, id_token: &str) -> IdTokenClaims {
+        let mut validation = Validation::default();
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&[AUTH_SERVICE_URL.as_str()]);
+        validation.set_required_spec_claims(&["exp", "sub", "aud", "iss"]);
+
+        // HS256 ID tokens are keyed with the client secret, never with the service's own secret
+        let claims = match (key_ring().algorithm(), &self.client_secret) {
+            (Algorithm::HS256, Some(client_secret)) => {
+                assert!(key_ring().decode::<IdTokenClaims>(id_token, validation.clone()).is_err());
+                SigningKey::from_secret(None, client_secret.as_bytes()).decode::<IdTokenClaims>(id_token, validation)
+            }
+            _ => key_ring().decode::<IdTokenClaims>(id_token, validation),
+        }
+        .expect("ID token did not verify");
+        assert_eq!(claims.nonce.as_ref(), Some(&self.nonce));
+        claims
+    }
+}
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": requires_2fa
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "Tangerine-Harbor-82",
+    }))
+    .await
+}
+
+async fn login_as_admin(app: &TestApp) {
+    let admin_email = get_random_email();
+    signup(app, &admin_email, false).await;
+
+    app.user_store
+        .write()
+        .await
+        .set_roles(&Email::parse(admin_email.clone()).unwrap(), vec![Role::User, Role::Admin])
+        .await
+        .unwrap();
+
+    let response = login(app, &admin_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+fn redirect_location(response: &reqwest::Response) -> String {
+    assert_eq!(response.status().as_u16(), 303);
+    response
+        .headers()
+        .get(header::LOCATION)
+        .expect("No location header")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+async fn oidc_error(response: reqwest::Response) -> String {
+    response
+        .json::<OidcErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to OidcErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_publish_discovery_document() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_openid_configuration().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let configuration = response
+        .json::<OpenIdConfiguration>()
+        .await
+        .expect("Could not deserialize response body to OpenIdConfiguration");
+    assert_eq!(configuration.issuer, AUTH_SERVICE_URL.as_str());
+    assert_eq!(configuration.authorization_endpoint, format!("{}/authorize", AUTH_SERVICE_URL.as_str()));
+    assert_eq!(configuration.token_endpoint, format!("{}/token", AUTH_SERVICE_URL.as_str()));
+    assert_eq!(configuration.userinfo_endpoint, format!("{}/userinfo", AUTH_SERVICE_URL.as_str()));
+    assert_eq!(configuration.jwks_uri, format!("{}/.well-known/jwks.json", AUTH_SERVICE_URL.as_str()));
+    assert_eq!(configuration.introspection_endpoint, format!("{}/introspect", AUTH_SERVICE_URL.as_str()));
+    assert_eq!(configuration.response_types_supported, vec!["code".to_owned()]);
+    assert_eq!(configuration.code_challenge_methods_supported, vec!["S256".to_owned()]);
+    assert_eq!(configuration.id_token_signing_alg_values_supported, vec![key_ring().algorithm()]);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_complete_authorization_code_flow_after_login() {
+    let mut app = TestApp::new().await;
+
+    let relying_party = RelyingParty::register(&app, true).await;
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Without a session the browser is sent to the login page, which brings it back afterwards
+    let authorization_path = relying_party.authorization_path();
+    let response = app.get_authorize_path(&authorization_path).await;
+    let location = redirect_location(&response);
+    let login_url = Url::parse(&format!("{}{}", app.address, location)).unwrap();
+    assert_eq!(login_url.path(), "/");
+    let return_to = login_url
+        .query_pairs()
+        .find(|(name, _)| name == "return_to")
+        .map(|(_, value)| value.into_owned())
+        .expect("No return_to parameter");
+    assert_eq!(return_to, authorization_path);
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_authorize_path(&return_to).await;
+    let location = redirect_location(&response);
+    let params: HashMap<String, String> = Url::parse(&location).unwrap().query_pairs().into_owned().collect();
+    assert_eq!(params.get("state"), Some(&relying_party.state));
+    let code = params.get("code").expect("No authorization code returned");
+
+    let response = relying_party.exchange_code(&app, code, &relying_party.code_verifier).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, "openid email");
+
+    let claims = relying_party.verify_id_token(&tokens.id_token);
+    assert_eq!(claims.sub, random_email);
+    assert_eq!(claims.email, Some(random_email.clone()));
+    assert_eq!(claims.email_verified, Some(true));
+
+    let response = app.get_userinfo(&tokens.access_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let userinfo = response
+        .json::<UserInfoResponse>()
+        .await
+        .expect("Could not deserialize response body to UserInfoResponse");
+    assert_eq!(userinfo.sub, random_email);
+    assert_eq!(userinfo.email, Some(random_email));
+    assert_eq!(userinfo.email_verified, Some(true));
+
+    // Authorization codes can be redeemed only once
+    let response = relying_party.exchange_code(&app, code, &relying_party.code_verifier).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oidc_error(response).await, "invalid_grant");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_authorize_public_client_after_2fa_login() {
+    let mut app = TestApp::new().await;
+
+    let relying_party = RelyingParty::register(&app, false).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, true).await;
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Without a secret to key an HS256 ID token with, public clients need an RS256 or EdDSA key
+    if key_ring().algorithm() == Algorithm::HS256 {
+        let code = relying_party.authorization_code(&app).await;
+        let response = relying_party.exchange_code(&app, &code, &relying_party.code_verifier).await;
+        assert_eq!(response.status().as_u16(), 500);
+        assert_eq!(oidc_error(response).await, "server_error");
+
+        app.clean_up().await;
+        return;
+    }
+
+    let tokens = relying_party.tokens(&app).await;
+    let claims = relying_party.verify_id_token(&tokens.id_token);
+    assert_eq!(claims.sub, random_email);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_wrong_code_verifier() {
+    let mut app = TestApp::new().await;
+
+    let relying_party = RelyingParty::register(&app, false).await;
+    let code = relying_party.authorization_code(&app).await;
+
+    let wrong_verifier = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let response = relying_party.exchange_code(&app, &code, &wrong_verifier).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oidc_error(response).await, "invalid_grant");
+
+    // A failed attempt burns the code
+    let response = relying_party.exchange_code(&app, &code, &relying_party.code_verifier).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oidc_error(response).await, "invalid_grant");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_grant_each_requested_scope_once() {
+    let mut app = TestApp::new().await;
+
+    let mut relying_party = RelyingParty::register(&app, true).await;
+    relying_party.scope = "openid email openid profile email";
+
+    let tokens = relying_party.tokens(&app).await;
+    assert_eq!(tokens.scope, "openid email");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invalid_client_credentials() {
+    let mut app = TestApp::new().await;
+
+    let relying_party = RelyingParty::register(&app, true).await;
+    let code = relying_party.authorization_code(&app).await;
+
+    let form = [
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", relying_party.code_verifier.as_str()),
+    ];
+    let response = app.post_token(&form, Some((&relying_party.client_id, "wrong-secret"))).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oidc_error(response).await, "invalid_client");
+
+    // A confidential client cannot fall back to PKCE alone
+    let mut form = form.to_vec();
+    form.push(("client_id", &relying_party.client_id));
+    let response = app.post_token(&form, None).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oidc_error(response).await, "invalid_client");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_redirect_to_unregistered_uri() {
+    let mut app = TestApp::new().await;
+
+    let relying_party = RelyingParty::register(&app, false).await;
+
+    let path = relying_party
+        .authorization_path()
+        .replace("8000%2Fcallback", "8000%2Fother");
+    let response = app.get_authorize_path(&path).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oidc_error(response).await, "invalid_request");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_pkce() {
+    let mut app = TestApp::new().await;
+
+    let relying_party = RelyingParty::register(&app, true).await;
+
+    let path = relying_party
+        .authorization_path()
+        .replace("code_challenge_method=S256", "code_challenge_method=plain");
+    let response = app.get_authorize_path(&path).await;
+    let location = redirect_location(&response);
+    let params: HashMap<String, String> = Url::parse(&location).unwrap().query_pairs().into_owned().collect();
+    assert_eq!(params.get("error").map(String::as_str), Some("invalid_request"));
+    assert_eq!(params.get("state"), Some(&relying_party.state));
+    assert!(!params.contains_key("code"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_accept_access_tokens_at_userinfo() {
+    let mut app = TestApp::new().await;
+
+    let relying_party = RelyingParty::register(&app, true).await;
+    let tokens = relying_party.tokens(&app).await;
+
+    let response = app.get_userinfo(&tokens.id_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(response.headers().contains_key(header::WWW_AUTHENTICATE));
+
+    // Neither token works in place of the auth cookie
+    for token in [&tokens.access_token, &tokens.id_token] {
+        let response = app.post_verify_token(&serde_json::json!({ "token": token })).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_userinfo(&tokens.access_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oidc_error(response).await, "invalid_token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_introspect_access_token() {
+    let mut app = TestApp::new().await;
+
+    let relying_party = RelyingParty::register(&app, true).await;
+    let tokens = relying_party.tokens(&app).await;
+
+    let response = app.post_introspect(&[("token", &tokens.access_token)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let introspection = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(introspection.active);
+    assert_eq!(introspection.scope, Some(tokens.scope));
+    assert_eq!(introspection.client_id, Some(relying_party.client_id.clone()));
+    assert!(introspection.iat.unwrap() < introspection.exp.unwrap());
+
+    // ID tokens are meant for the client, not as bearer tokens
+    let response = app.post_introspect(&[("token", &tokens.id_token)]).await;
+    let introspection = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(!introspection.active);
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_introspect(&[("token", &tokens.access_token)]).await;
+    let introspection = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert_eq!(introspection, IntrospectionResponse::inactive());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_non_admin_registers_client() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_oidc_client(&serde_json::json!({
+            "clientName": "Test app",
+            "redirectUris": [REDIRECT_URI],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+use auth_service::{
+    domain::{Email, PasswordPolicy, PasswordResetToken},
+    services::hibp_range_files::HibpRangeFiles,
+    ErrorResponse,
+};
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn assert_policy_violation(response: reqwest::Response, expected_error: &str) {
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        expected_error.to_owned()
+    );
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn signup_should_reject_weak_passwords() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let local_part = random_email.split('@').next().unwrap().to_owned();
+
+    // Passwords made of the email address are known to an attacker
+    for password in ["password123", "qwertyuiop", "P@ssw0rd2024", local_part.as_str()] {
+        let response = app
+            .post_signup(&serde_json::json!({
+                "email": random_email,
+                "password": password,
+                "requires2FA": false
+            }))
+            .await;
+        assert_policy_violation(response, "Password is too easy to guess").await;
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn signup_should_enforce_configured_lengths() {
+    let mut app =
+        TestApp::with_password_policy(PasswordPolicy::default().with_length(20, 32).unwrap()).await;
+
+    let random_email = get_random_email();
+    let test_cases = [
+        ("Tangerine-Harbor-82", "Password must be at least 20 characters long"),
+        ("Tangerine-Harbor-82-Quiet-Falcon-Meadow", "Password must be at most 32 characters long"),
+    ];
+
+    for (password, expected_error) in test_cases {
+        let response = app
+            .post_signup(&serde_json::json!({
+                "email": random_email,
+                "password": password,
+                "requires2FA": false
+            }))
+            .await;
+        assert_policy_violation(response, expected_error).await;
+    }
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82-Quiet",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn signup_should_reject_breached_passwords() {
+    let breached = "Glacier-Tandem-47-Orbit";
+    let hash: String = digest(&SHA1_FOR_LEGACY_USE_ONLY, breached.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    let (prefix, suffix) = hash.split_at(5);
+
+    // Same layout as the files of the range API, including a padding entry
+    let dir = std::env::temp_dir().join(format!("hibp-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join(format!("{}.txt", prefix)),
+        format!("0018A45C4D1DEF81644B54AB7F969B88D65:0\r\n{}:42\r\n", suffix),
+    )
+    .unwrap();
+
+    let mut app = TestApp::with_password_policy(
+        PasswordPolicy::default().with_breached_passwords(Arc::new(HibpRangeFiles::open(dir.clone()).unwrap())),
+    )
+    .await;
+
+    let random_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": breached,
+            "requires2FA": false
+        }))
+        .await;
+    assert_policy_violation(response, "Password has appeared in a data breach").await;
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn reset_password_should_reject_weak_passwords_without_using_up_the_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let token = PasswordResetToken::default();
+    app.password_reset_token_store
+        .write()
+        .await
+        .add_token(Email::parse(random_email.clone()).unwrap(), token.clone())
+        .await
+        .expect("Failed to add password reset token");
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token.as_ref(),
+            "newPassword": "password123",
+        }))
+        .await;
+    assert_policy_violation(response, "Password is too easy to guess").await;
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token.as_ref(),
+            "newPassword": "Quiet-Falcon-Meadow-19",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_password_should_reject_weak_passwords() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "Tangerine-Harbor-82",
+            "newPassword": "password123",
+        }))
+        .await;
+    assert_policy_violation(response, "Password is too easy to guess").await;
+
+    // The old password keeps working
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
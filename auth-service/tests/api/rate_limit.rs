@@ -0,0 +1,98 @@
+use auth_service::{
+    domain::{RateLimit, RateLimits},
+    ErrorResponse,
+};
+use reqwest::header;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn assert_too_many_requests(response: reqwest::Response) {
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after: u64 = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .expect("No Retry-After header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 30);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests".to_owned()
+    );
+}
+
+#[tokio::test]
+async fn should_return_429_once_route_limit_is_exceeded() {
+    let mut app = TestApp::with_rate_limits(
+        RateLimits::unlimited().with_route("/login", RateLimit::new(2, 60).unwrap()),
+    )
+    .await;
+
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "Tangerine-Harbor-82",
+    });
+
+    // Failed logins use up tokens just like successful ones
+    for _ in 0..2 {
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_login(&login_body).await;
+    assert_too_many_requests(response).await;
+
+    // Other routes have buckets of their own
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_apply_default_limit_per_route_pattern() {
+    let mut app = TestApp::with_rate_limits(
+        RateLimits::unlimited().with_default(Some(RateLimit::new(2, 60).unwrap())),
+    )
+    .await;
+
+    // Requests for different sessions all count against /sessions/:session_id
+    let response = app.delete_session("first").await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app.delete_session("second").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_session("third").await;
+    assert_too_many_requests(response).await;
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": "invalid" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_limit_routes_without_limit() {
+    let mut app = TestApp::with_rate_limits(RateLimits::unlimited()).await;
+
+    for _ in 0..20 {
+        let response = app.post_verify_token(&serde_json::json!({ "token": "invalid" })).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.clean_up().await;
+}
@@ -0,0 +1,158 @@
+use auth_service::{
+    domain::RECOVERY_CODE_COUNT,
+    routes::{RecoveryCodesResponse, SignupResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_with_2fa(app: &TestApp, email: &str) -> Vec<String> {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+
+    response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .expect("No recovery codes returned")
+}
+
+async fn start_login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+async fn verify_with_code(app: &TestApp, email: &str, code: &str) -> reqwest::Response {
+    let login_attempt_id = start_login(app, email).await;
+
+    app.post_verify_2fa(&serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn should_return_recovery_codes_only_for_2fa_users() {
+    let mut app = TestApp::new().await;
+
+    let codes = signup_with_2fa(&app, &get_random_email()).await;
+    assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let json_body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse");
+    assert_eq!(json_body.recovery_codes, None);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_recovery_code_only_once() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let codes = signup_with_2fa(&app, &random_email).await;
+
+    // Recovery codes are accepted regardless of case and separator
+    let typed_code = codes[0].to_uppercase().replace('-', " ");
+    let response = verify_with_code(&app, &random_email, &typed_code).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    let response = verify_with_code(&app, &random_email, &codes[0]).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = verify_with_code(&app, &random_email, &codes[1]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_accept_recovery_codes_of_another_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_with_2fa(&app, &random_email).await;
+    let other_codes = signup_with_2fa(&app, &get_random_email()).await;
+
+    let response = verify_with_code(&app, &random_email, &other_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_old_codes_on_regeneration() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let old_codes = signup_with_2fa(&app, &random_email).await;
+
+    let response = verify_with_code(&app, &random_email, &old_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_recovery_codes().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(new_codes.len(), RECOVERY_CODE_COUNT);
+
+    let response = verify_with_code(&app, &random_email, &old_codes[1]).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = verify_with_code(&app, &random_email, &new_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_recovery_codes().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
@@ -1,6 +1,17 @@
-use auth_service::domain::{Email, LoginAttemptId, TwoFACode};
+use std::{net::{IpAddr, Ipv4Addr}, sync::Arc};
+use auth_service::{
+    domain::{
+        Email, LoginAttemptId, LoginThrottleStore, RateLimit, RateLimitStore, Session, SessionStore,
+        SessionStoreError, TwoFACode, TwoFACodeStore, TwoFACodeStoreError,
+    },
+    services::{
+        redis_login_throttle_store::RedisLoginThrottleStore, redis_rate_limit_store::RedisRateLimitStore,
+        redis_session_store::RedisSessionStore, redis_two_fa_code_store::RedisTwoFACodeStore,
+    },
+};
+use tokio::sync::RwLock;
 
-use crate::helpers::TestApp;
+use crate::helpers::{configure_redis, get_random_email, TestApp};
 
 #[tokio::test]
 async fn redis_returns_stored_token() {
@@ -39,4 +50,133 @@ async fn redis_store_and_return_2fa() {
         
     }
     app.clean_up().await;
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn redis_counts_login_failures_and_locks() {
+    let mut store = RedisLoginThrottleStore::new(Arc::new(RwLock::new(configure_redis())));
+    let key = format!("email:{}", get_random_email());
+
+    assert_eq!(store.record_failure(&key, 60).await, Ok(1));
+    assert_eq!(store.record_failure(&key, 60).await, Ok(2));
+    assert_eq!(store.get_lockout(&key).await, Ok(None));
+
+    store.lock(&key, 30).await.expect("Failed to lock key");
+    let lockout = store
+        .get_lockout(&key)
+        .await
+        .expect("Failed to get lockout")
+        .expect("Key should be locked");
+    assert!(lockout > 0 && lockout <= 30);
+
+    store.clear(&key).await.expect("Failed to clear key");
+    assert_eq!(store.get_lockout(&key).await, Ok(None));
+    assert_eq!(store.record_failure(&key, 60).await, Ok(1));
+}
+
+#[tokio::test]
+async fn redis_consumes_2fa_code_once() {
+    let mut app = TestApp::new().await;
+    let email = Email::parse(get_random_email()).unwrap();
+    let login_attempt_id = LoginAttemptId::default();
+    {
+        let mut two_fa_store = app.two_fa_code_store.write().await;
+
+        two_fa_store
+            .add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default())
+            .await
+            .expect("Failed to add 2FA code");
+
+        assert_eq!(two_fa_store.record_failed_attempt(&login_attempt_id).await, Ok(1));
+        assert_eq!(two_fa_store.record_failed_attempt(&login_attempt_id).await, Ok(2));
+
+        assert_eq!(two_fa_store.consume_code(&email, &login_attempt_id).await, Ok(()));
+        assert_eq!(
+            two_fa_store.consume_code(&email, &login_attempt_id).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+        assert!(two_fa_store.get_code(&email).await.is_err());
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn redis_leaves_2fa_code_of_another_login_attempt_alone() {
+    let mut store = RedisTwoFACodeStore::new(Arc::new(RwLock::new(configure_redis())));
+    let email = Email::parse(get_random_email()).unwrap();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    store
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .expect("Failed to add 2FA code");
+    assert_eq!(store.record_failed_attempt(&login_attempt_id).await, Ok(1));
+
+    // A stale login attempt neither consumes the code nor resets its failed attempts
+    assert_eq!(
+        store.consume_code(&email, &LoginAttemptId::default()).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+    assert_eq!(store.get_code(&email).await, Ok((login_attempt_id.clone(), code)));
+    assert_eq!(store.record_failed_attempt(&login_attempt_id).await, Ok(2));
+
+    assert_eq!(store.consume_code(&email, &login_attempt_id).await, Ok(()));
+    assert_eq!(store.record_failed_attempt(&login_attempt_id).await, Ok(1));
+}
+
+#[tokio::test]
+async fn redis_stores_and_removes_sessions() {
+    let mut store = RedisSessionStore::new(Arc::new(RwLock::new(configure_redis())));
+    let email = Email::parse(get_random_email()).unwrap();
+    let first = Session::new(email.clone(), IpAddr::V4(Ipv4Addr::LOCALHOST), Some("test-agent".to_owned()));
+    let second = Session::new(email.clone(), IpAddr::V4(Ipv4Addr::LOCALHOST), None);
+
+    store.add_session(first.clone()).await.expect("Failed to add session");
+    store.add_session(second.clone()).await.expect("Failed to add session");
+
+    assert_eq!(store.get_session(&first.id).await, Ok(first.clone()));
+    let sessions = store.get_user_sessions(&email).await.expect("Failed to get sessions");
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.contains(&first) && sessions.contains(&second));
+
+    assert_eq!(store.remove_session(&first.id).await, Ok(()));
+    assert_eq!(store.get_session(&first.id).await, Err(SessionStoreError::SessionNotFound));
+    assert_eq!(store.get_user_sessions(&email).await, Ok(vec![second]));
+
+    assert_eq!(store.remove_user_sessions(&email).await, Ok(()));
+    assert_eq!(store.get_user_sessions(&email).await, Ok(vec![]));
+}
+
+#[tokio::test]
+async fn redis_takes_rate_limit_tokens_until_the_bucket_is_empty() {
+    let mut store = RedisRateLimitStore::new(Arc::new(RwLock::new(configure_redis())));
+    let key = format!("ip:{}", get_random_email());
+    let limit = RateLimit::new(2, 3).unwrap();
+
+    assert_eq!(store.take_token(&key, limit).await.unwrap(), None);
+    assert_eq!(store.take_token(&key, limit).await.unwrap(), None);
+    // One token refills every 1.5 seconds, Retry-After rounds it up
+    assert_eq!(store.take_token(&key, limit).await.unwrap(), Some(2));
+
+    // Other keys have buckets of their own
+    let other_key = format!("ip:{}", get_random_email());
+    assert_eq!(store.take_token(&other_key, limit).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn redis_refills_rate_limit_tokens() {
+    let mut store = RedisRateLimitStore::new(Arc::new(RwLock::new(configure_redis())));
+    let key = format!("ip:{}", get_random_email());
+    let limit = RateLimit::new(4, 1).unwrap();
+
+    for _ in 0..4 {
+        assert_eq!(store.take_token(&key, limit).await.unwrap(), None);
+    }
+    assert_eq!(store.take_token(&key, limit).await.unwrap(), Some(1));
+
+    // A token refills every 250 milliseconds
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert_eq!(store.take_token(&key, limit).await.unwrap(), None);
+    assert_eq!(store.take_token(&key, limit).await.unwrap(), Some(1));
+}
@@ -0,0 +1,177 @@
+use auth_service::{
+    domain::RefreshToken,
+    utils::{
+        auth::REFRESH_TOKEN_TTL_SECONDS,
+        constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME, SECURE_COOKIES},
+    },
+    ErrorResponse,
+};
+use reqwest::Url;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found");
+
+    refresh_cookie.value().to_owned()
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_refresh_token() {
+    let mut app = TestApp::new().await;
+
+    let refresh_token = signup_and_login(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    let rotated_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found");
+    assert_ne!(rotated_cookie.value(), refresh_token);
+    // The refresh cookie is persistent, it lives as long as the refresh token
+    assert_eq!(rotated_cookie.max_age(), Some(std::time::Duration::from_secs(REFRESH_TOKEN_TTL_SECONDS as u64)));
+    // Secure follows the one setting shared with the auth cookie
+    assert_eq!(rotated_cookie.secure(), *SECURE_COOKIES);
+    assert_eq!(auth_cookie.secure(), *SECURE_COOKIES);
+
+    // The rotated token can be used for the next refresh
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_and_revoke_family_if_token_reused() {
+    let mut app = TestApp::new().await;
+
+    let refresh_token = signup_and_login(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let rotated_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    // Replay the already rotated token
+    set_refresh_cookie(&app, &refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "InvalidToken".to_owned()
+    );
+
+    // Reuse detection revokes every token of the family, including the latest one
+    set_refresh_cookie(&app, &rotated_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_after_logout() {
+    let mut app = TestApp::new().await;
+
+    let refresh_token = signup_and_login(&app).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_unknown_token() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = ["invalid".to_owned(), RefreshToken::default().as_ref().to_owned()];
+
+    for test_case in test_cases.iter() {
+        set_refresh_cookie(&app, test_case);
+        let response = app.post_refresh().await;
+        assert_eq!(response.status().as_u16(), 401, "Failed for input: {:?}", test_case);
+    }
+
+    {
+        let mut refresh_token_store = app.refresh_token_store.write().await;
+        let result = refresh_token_store.use_token(&RefreshToken::default()).await;
+        assert!(result.is_err());
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing token credentials".to_owned()
+    );
+
+    app.clean_up().await;
+}
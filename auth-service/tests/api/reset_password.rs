@@ -0,0 +1,225 @@
+use auth_service::{
+    domain::{Email, PasswordResetToken},
+    routes::{ForgotPasswordResponse, ResetPasswordResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn forgot_password_should_return_200_for_known_and_unknown_emails() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let expected_response = ForgotPasswordResponse {
+        message: "If the account exists, a password reset email has been sent".to_owned(),
+    };
+
+    for email in [random_email, get_random_email()] {
+        let response = app
+            .post_forgot_password(&serde_json::json!({ "email": email }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        assert_eq!(
+            response
+                .json::<ForgotPasswordResponse>()
+                .await
+                .expect("Could not deserialize response body to ForgotPasswordResponse"),
+            expected_response
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn forgot_password_should_return_400_if_invalid_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "email": "invalid_email" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_revoke_outstanding_tokens() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let old_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let token = PasswordResetToken::default();
+    app.password_reset_token_store
+        .write()
+        .await
+        .add_token(Email::parse(random_email.clone()).unwrap(), token.clone())
+        .await
+        .expect("Failed to add password reset token");
+
+    let reset_body = serde_json::json!({
+        "token": token.as_ref(),
+        "newPassword": "Quiet-Falcon-Meadow-19",
+    });
+
+    let response = app.post_reset_password(&reset_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        response
+            .json::<ResetPasswordResponse>()
+            .await
+            .expect("Could not deserialize response body to ResetPasswordResponse"),
+        ResetPasswordResponse {
+            message: "Password reset successfully!".to_owned(),
+        }
+    );
+
+    // The JWT issued before the reset is no longer accepted
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": old_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The refresh token issued before the reset is revoked as well
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The old password no longer works, the new one does
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Quiet-Falcon-Meadow-19",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // A reset token can only be used once
+    let response = app.post_reset_password(&reset_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({
+            "token": "invalid",
+            "newPassword": "Quiet-Falcon-Meadow-19",
+        }),
+        serde_json::json!({
+            "token": PasswordResetToken::default().as_ref(),
+            "newPassword": "short",
+        }),
+    ];
+    let expected_errors = ["Invalid credentials", "Password must be at least 8 characters long"];
+
+    for (test_case, expected_error) in test_cases.iter().zip(expected_errors) {
+        let response = app.post_reset_password(test_case).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", test_case);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            expected_error.to_owned()
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_unknown_token() {
+    let mut app = TestApp::new().await;
+
+    let reset_body = serde_json::json!({
+        "token": PasswordResetToken::default().as_ref(),
+        "newPassword": "Quiet-Falcon-Meadow-19",
+    });
+
+    let response = app.post_reset_password(&reset_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({
+            "token": PasswordResetToken::default().as_ref(),
+        }),
+        serde_json::json!({
+            "newPassword": "Quiet-Falcon-Meadow-19",
+        }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_reset_password(test_case).await;
+        assert_eq!(response.status().as_u16(), 422, "Failed for input: {:?}", test_case);
+    }
+
+    app.clean_up().await;
+}
@@ -0,0 +1,92 @@
+use auth_service::{
+    domain::{Email, Role},
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME, signing_keys::key_ring},
+};
+use jsonwebtoken::Validation;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+}
+
+fn roles_of(response: &reqwest::Response) -> Vec<Role> {
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    key_ring()
+        .decode::<Claims>(&token, Validation::default())
+        .expect("Could not decode auth token")
+        .roles
+}
+
+#[tokio::test]
+async fn should_embed_user_roles_in_jwt() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(roles_of(&response), vec![Role::User]);
+
+    app.user_store
+        .write()
+        .await
+        .set_roles(&Email::parse(random_email.clone()).unwrap(), vec![Role::User, Role::Admin])
+        .await
+        .unwrap();
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(roles_of(&response), vec![Role::User, Role::Admin]);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_pick_up_changed_roles_on_refresh() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.user_store
+        .write()
+        .await
+        .set_roles(&Email::parse(random_email.clone()).unwrap(), vec![Role::User, Role::Admin])
+        .await
+        .unwrap();
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(roles_of(&response), vec![Role::User, Role::Admin]);
+
+    app.clean_up().await;
+}
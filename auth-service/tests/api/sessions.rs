@@ -0,0 +1,202 @@
+use auth_service::{
+    routes::{ListSessionsResponse, RevokeSessionResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+}
+
+async fn login(app: &TestApp, email: &str, user_agent: &str) -> String {
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("User-Agent", user_agent)
+        .json(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    token
+}
+
+async fn get_sessions(app: &TestApp) -> ListSessionsResponse {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ListSessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListSessionsResponse")
+}
+
+#[tokio::test]
+async fn should_list_active_sessions() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    login(&app, &random_email, "first-device").await;
+    login(&app, &random_email, "second-device").await;
+
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 2);
+
+    // Only the session of the latest login is the one making the request
+    for session in &sessions {
+        let expected_current = session.user_agent == Some("second-device".to_owned());
+        assert_eq!(session.current, expected_current);
+    }
+    assert!(sessions.iter().any(|session| session.user_agent == Some("first-device".to_owned())));
+
+    for session in &sessions {
+        assert_eq!(session.ip, "127.0.0.1");
+        assert!(chrono::DateTime::parse_from_rfc3339(&session.created_at).is_ok());
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_a_single_session() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    let first_token = login(&app, &random_email, "first-device").await;
+    let second_token = login(&app, &random_email, "second-device").await;
+
+    let sessions = get_sessions(&app).await.sessions;
+    let other_session = sessions
+        .iter()
+        .find(|session| !session.current)
+        .expect("No other session found");
+
+    let response = app.delete_session(&other_session.id).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        response
+            .json::<RevokeSessionResponse>()
+            .await
+            .expect("Could not deserialize response body to RevokeSessionResponse"),
+        RevokeSessionResponse {
+            message: "Session revoked successfully!".to_owned(),
+        }
+    );
+
+    // Only the revoked session is logged out
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": first_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": second_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    // A revoked session can not be revoked twice
+    let response = app.delete_session(&other_session.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_sessions_of_other_users() {
+    let mut app = TestApp::new().await;
+
+    let other_email = get_random_email();
+    signup(&app, &other_email).await;
+    let other_token = login(&app, &other_email, "other-user").await;
+    let other_session_id = get_sessions(&app).await.sessions[0].id.clone();
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    login(&app, &random_email, "device").await;
+
+    let response = app.delete_session(&other_session_id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Session not found".to_owned()
+    );
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": other_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn logout_should_end_the_session() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    login(&app, &random_email, "first-device").await;
+    let second_token = login(&app, &random_email, "second-device").await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The first session is still open, the logged out one is gone
+    login(&app, &random_email, "third-device").await;
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.iter().all(|session| session.user_agent != Some("second-device".to_owned())));
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": second_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_session("unknown").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
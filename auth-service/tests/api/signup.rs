@@ -1,5 +1,5 @@
 use crate::helpers::{get_random_email, TestApp};
-use auth_service::{routes::SignupResponse, ErrorResponse};
+use auth_service::{domain::RECOVERY_CODE_COUNT, routes::SignupResponse, ErrorResponse};
 use tracing::{info,instrument};
 //use tokio::io::AsyncWriteExt;
 
@@ -12,24 +12,24 @@ async fn should_return_201_if_valid_input() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": true
     });
 
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status().as_u16(), 201);
 
-    let expected_response = SignupResponse {
-        message: "User created successfully!".to_owned(),
-    };
+    let json_body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to UserBody");
 
     // Assert that we are getting the correct response body!
-   assert_eq!(
-        response
-            .json::<SignupResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        expected_response
+    assert_eq!(json_body.message, "User created successfully!".to_owned());
+    // 2FA users receive their recovery codes on signup
+    assert_eq!(
+        json_body.recovery_codes.map(|codes| codes.len()),
+        Some(RECOVERY_CODE_COUNT)
     );
     app.clean_up().await;
 }
@@ -40,7 +40,7 @@ async fn should_return_400_if_invalid_input() {
     // The signup route should return a 400 HTTP status code if an invalid input is sent.
     // The input is considered invalid if:
     // - The email is empty or does not contain '@'
-    // - The password is less than 8 characters
+    // - The password is less than 8 characters, which is reported as a password policy violation
 
     // Create an array of invalid inputs. Then, iterate through the array and 
     // make HTTP calls to the signup route. Assert a 400 HTTP status code is returned.
@@ -51,12 +51,7 @@ async fn should_return_400_if_invalid_input() {
     let input = [
         serde_json::json!({
             "email": "",
-            "password": "password123",
-            "requires2FA": true
-        }),
-        serde_json::json!({
-            "email": random_email,
-            "password": "",
+            "password": "Tangerine-Harbor-82",
             "requires2FA": true
         }),
         serde_json::json!({
@@ -66,12 +61,7 @@ async fn should_return_400_if_invalid_input() {
         }),
         serde_json::json!({
             "email": "invalid_email",
-            "password": "password123",
-            "requires2FA": true
-        }),
-        serde_json::json!({
-            "email": random_email,
-            "password": "invalid",
+            "password": "Tangerine-Harbor-82",
             "requires2FA": true
         }),
     ];
@@ -89,6 +79,26 @@ async fn should_return_400_if_invalid_input() {
             "Invalid credentials".to_owned()
         );
     }
+
+    for password in ["", "invalid"] {
+        let response = app
+            .post_signup(&serde_json::json!({
+                "email": random_email,
+                "password": password,
+                "requires2FA": true
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", password);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Password must be at least 8 characters long".to_owned()
+        );
+    }
     app.clean_up().await;
 }
 
@@ -102,7 +112,7 @@ async fn should_return_409_if_email_already_exists() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": true
     });
     info!("response is{:?}",signup_body);
@@ -135,7 +145,7 @@ async fn should_return_422_if_malformed_input() {
     // add more malformed input test cases
     let test_cases = [
        serde_json::json!({
-            "password": "password123",
+            "password": "Tangerine-Harbor-82",
             "requires2FA": true
         }),
         serde_json::json!({
@@ -144,7 +154,7 @@ async fn should_return_422_if_malformed_input() {
         }),
         serde_json::json!({
             "email": random_email,
-            "password": "password123",
+            "password": "Tangerine-Harbor-82",
             //"requires2FA": true
         })
     ];
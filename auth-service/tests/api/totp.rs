@@ -0,0 +1,280 @@
+use auth_service::{
+    domain::{Email, RECOVERY_CODE_COUNT},
+    routes::{ConfirmTotpResponse, EnrollTotpResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn generate_totp_code(secret: &str, offset_steps: i64) -> String {
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        0,
+        30,
+        Secret::Encoded(secret.to_owned()).to_bytes().unwrap(),
+        None,
+        String::new(),
+    )
+    .unwrap();
+    let time = chrono::Utc::now().timestamp() + offset_steps * 30;
+    totp.generate(time as u64)
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn enroll_and_confirm(app: &TestApp) -> String {
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let enrollment = response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to EnrollTotpResponse");
+
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment.otpauth_uri.contains(&enrollment.secret));
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": generate_totp_code(&enrollment.secret, 0) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let confirmation = response
+        .json::<ConfirmTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to ConfirmTotpResponse");
+
+    assert_eq!(confirmation.message, "TOTP enabled successfully!".to_owned());
+    assert_eq!(confirmation.recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+    enrollment.secret
+}
+
+#[tokio::test]
+async fn should_require_totp_code_on_login_after_enrollment() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let secret = enroll_and_confirm(&app).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    // The emailed code is not accepted for TOTP users
+    let (_, emailed_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": json_body.login_attempt_id,
+            "2FACode": emailed_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The code of the next time step is accepted thanks to the skew window,
+    // the one used for the confirmation was already consumed
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": json_body.login_attempt_id,
+        "2FACode": generate_totp_code(&secret, 1),
+    });
+
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    // A code can only be used once
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": json_body.login_attempt_id,
+            "2FACode": generate_totp_code(&secret, 1),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_totp_already_enabled() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    enroll_and_confirm(&app).await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "TOTP already enabled".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_confirmation_code() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let enrollment = response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to EnrollTotpResponse");
+
+    // A code far outside of the skew window
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": generate_totp_code(&enrollment.secret, -10) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": "invalid" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Login still works without a second factor since TOTP was never confirmed
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_authentication() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": "123456" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cookie_jar.add_cookie_str(
+        &format!("{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/", JWT_COOKIE_NAME),
+        &reqwest::Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_totp_with_password_and_authenticator_code() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    let secret = enroll_and_confirm(&app).await;
+
+    // An emailed confirmation code is no substitute for the authenticator app
+    let response = app.post_2fa_code().await;
+    assert_eq!(response.status().as_u16(), 202);
+    let (_, emailed_code) = app
+        .two_fa_confirmation_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+    let response = app
+        .post_2fa_disable(&serde_json::json!({
+            "password": "Tangerine-Harbor-82",
+            "2FACode": emailed_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_2fa_disable(&serde_json::json!({
+            "password": "Tangerine-Harbor-82",
+            "2FACode": generate_totp_code(&secret, 1),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "Tangerine-Harbor-82",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
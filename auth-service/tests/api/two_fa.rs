@@ -0,0 +1,281 @@
+use auth_service::{
+    domain::{Email, RECOVERY_CODE_COUNT},
+    routes::{Enable2FAResponse, TwoFactorAuthResponse},
+    utils::login_throttle::MAX_EMAIL_LOGIN_FAILURES,
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Tangerine-Harbor-82",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(email).await;
+
+    let response = app.post_login(&login_body(email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+fn login_body(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "Tangerine-Harbor-82",
+    })
+}
+
+async fn request_code(app: &TestApp, email: &str) -> String {
+    let response = app.post_2fa_code().await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let (_, code) = app
+        .two_fa_confirmation_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(email.to_owned()).unwrap())
+        .await
+        .unwrap();
+    code.as_ref().to_owned()
+}
+
+async fn enable_2fa(app: &TestApp, email: &str) -> Enable2FAResponse {
+    let code = request_code(app, email).await;
+
+    let response = app.post_2fa_enable(&serde_json::json!({ "2FACode": code })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<Enable2FAResponse>()
+        .await
+        .expect("Could not deserialize response body to Enable2FAResponse")
+}
+
+fn wrong_code(code: &str) -> String {
+    if code == "000000" { "111111" } else { "000000" }.to_owned()
+}
+
+#[tokio::test]
+async fn should_require_2fa_on_login_after_enabling() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let code = request_code(&app, &random_email).await;
+    let response = app
+        .post_2fa_enable(&serde_json::json!({ "2FACode": wrong_code(&code) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // A wrong code does not enable anything
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let enabled = enable_2fa(&app, &random_email).await;
+    assert_eq!(enabled.message, "2FA enabled successfully!".to_owned());
+    assert_eq!(enabled.recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_no_code_was_requested() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app.post_2fa_enable(&serde_json::json!({ "2FACode": "123456" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_2fa_already_enabled() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    enable_2fa(&app, &random_email).await;
+
+    let code = request_code(&app, &random_email).await;
+    let response = app.post_2fa_enable(&serde_json::json!({ "2FACode": code })).await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "2FA already enabled".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_2fa_with_password_and_code() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    enable_2fa(&app, &random_email).await;
+
+    let code = request_code(&app, &random_email).await;
+
+    let response = app
+        .post_2fa_disable(&serde_json::json!({ "password": "wrongpassword", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_2fa_disable(&serde_json::json!({ "password": "Tangerine-Harbor-82", "2FACode": wrong_code(&code) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_2fa_disable(&serde_json::json!({ "password": "Tangerine-Harbor-82", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_confirmation_codes_apart_from_login_codes() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    enable_2fa(&app, &random_email).await;
+
+    // A login is waiting for its 2FA code while the logged in user asks for a confirmation code
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let (_, login_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+
+    let code = request_code(&app, &random_email).await;
+
+    // The login code does not confirm disabling 2FA
+    let response = app
+        .post_2fa_disable(&serde_json::json!({ "password": "Tangerine-Harbor-82", "2FACode": login_code.as_ref() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // And the confirmation code did not replace the pending login
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": login_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_after_too_many_incorrect_passwords_on_disable() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    enable_2fa(&app, &random_email).await;
+
+    let code = request_code(&app, &random_email).await;
+
+    for _ in 0..MAX_EMAIL_LOGIN_FAILURES {
+        let response = app
+            .post_2fa_disable(&serde_json::json!({ "password": "wrongpassword", "2FACode": code }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the correct password is rejected while the account is locked out
+    let response = app
+        .post_2fa_disable(&serde_json::json!({ "password": "Tangerine-Harbor-82", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_2fa_not_enabled() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let code = request_code(&app, &random_email).await;
+    let response = app
+        .post_2fa_disable(&serde_json::json!({ "password": "Tangerine-Harbor-82", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app.post_2fa_enable(&serde_json::json!({ "2FACode": "12ab" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_2fa_disable(&serde_json::json!({ "password": "Tangerine-Harbor-82", "2FACode": "12ab" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_2fa_code().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post_2fa_enable(&serde_json::json!({ "2FACode": "123456" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
@@ -1,8 +1,9 @@
 use crate::helpers::{get_random_email, TestApp};
+use reqwest::header;
 use auth_service::{
     domain::Email,
     routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::{auth::MAX_TWO_FA_ATTEMPTS, constants::JWT_COOKIE_NAME, login_throttle::MAX_TWO_FA_FAILURES},
 
 };
 
@@ -15,17 +16,18 @@ async fn verify_2fa_returns_200() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": true
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;
@@ -81,17 +83,18 @@ async fn should_return_400_if_invalid_input() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": true
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;
@@ -148,17 +151,18 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": true
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;
@@ -216,17 +220,18 @@ async fn should_return_401_if_old_code() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": true
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;
@@ -301,3 +306,141 @@ async fn should_return_422_if_malformed_input() {
     app.clean_up().await;
 }
 
+
+#[tokio::test]
+async fn should_invalidate_code_after_too_many_incorrect_attempts() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let code_tuple = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+
+    let wrong_code = if code_tuple.1.as_ref() == "123456" { "654321" } else { "123456" };
+
+    for _ in 0..MAX_TWO_FA_ATTEMPTS {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": random_email,
+                "loginAttemptId": code_tuple.0.as_ref(),
+                "2FACode": wrong_code,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The correct code is no longer accepted once the attempts are used up
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": code_tuple.0.as_ref(),
+            "2FACode": code_tuple.1.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let result = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await;
+    assert!(result.is_err());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_out_2fa_after_too_many_wrong_codes_across_logins() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Tangerine-Harbor-82",
+    });
+
+    // Every password login brings a new login attempt with a fresh per attempt budget
+    let mut failures = 0;
+    while failures < MAX_TWO_FA_FAILURES {
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 206);
+
+        let code_tuple = app
+            .two_fa_code_store
+            .read()
+            .await
+            .get_code(&Email::parse(random_email.clone()).unwrap())
+            .await
+            .unwrap();
+        let wrong_code = if code_tuple.1.as_ref() == "123456" { "654321" } else { "123456" };
+
+        for _ in 0..MAX_TWO_FA_ATTEMPTS.min(MAX_TWO_FA_FAILURES - failures) {
+            let response = app
+                .post_verify_2fa(&serde_json::json!({
+                    "email": random_email,
+                    "loginAttemptId": code_tuple.0.as_ref(),
+                    "2FACode": wrong_code,
+                }))
+                .await;
+            assert_eq!(response.status().as_u16(), 401);
+            failures += 1;
+        }
+    }
+
+    // The account is locked for second factors, even with the correct code of a new login attempt
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let code_tuple = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .unwrap();
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": code_tuple.0.as_ref(),
+            "2FACode": code_tuple.1.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().get(header::RETRY_AFTER).is_some());
+
+    app.clean_up().await;
+}
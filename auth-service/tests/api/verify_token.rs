@@ -9,17 +9,18 @@ use auth_service::utils::constants::JWT_COOKIE_NAME;
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": false
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;
@@ -73,17 +74,18 @@ async fn should_return_401_if_banned_token() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
         "requires2FA": false
     });
 
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "Tangerine-Harbor-82",
     });
 
     let response = app.post_login(&login_body).await;